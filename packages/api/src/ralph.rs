@@ -1,11 +1,18 @@
+use dioxus::fullstack::ServerEvents;
 use dioxus::prelude::*;
-use ralph::{Branch, Guardrail, Prd, PrdConversation, Session, SessionConfig};
+use ralph::{
+    ActivityEvent, ActivityFilter, ActivityPage, AgentBackendKind, AgentModel, Branch, Diff,
+    Guardrail, Prd, PrdConversation, PullRequest, Session, SessionConfig,
+};
 
 #[cfg(feature = "server")]
 use ralph::{run_memory_monitor, run_health_watchdog, shutdown_signal, GitOperations};
 
 #[cfg(feature = "server")]
-use ralph::{GuardrailManager, PrdConversationManager, SessionManager};
+use ralph::{
+    parse_dependencies, GuardrailManager, GutterRecovery, PrdConversationManager, SessionManager,
+    SessionStore,
+};
 #[cfg(feature = "server")]
 use std::sync::Arc;
 #[cfg(feature = "server")]
@@ -16,10 +23,41 @@ use std::time::Duration;
 // Global session manager
 #[cfg(feature = "server")]
 lazy_static::lazy_static! {
-    static ref SESSION_MANAGER: Arc<SessionManager> = Arc::new(SessionManager::new());
+    static ref SESSION_MANAGER: Arc<SessionManager> =
+        Arc::new(
+            SessionManager::with_store(SessionStore::new(state_dir()))
+                .with_max_concurrent_sessions(max_concurrent_sessions())
+                .with_worktree_root(state_dir().join("worktrees")),
+        );
     static ref CONVERSATION_MANAGER: Arc<PrdConversationManager> = Arc::new(PrdConversationManager::new());
 }
 
+/// Directory holding server-level state (persisted sessions).
+///
+/// Overridable with `RALPH_STATE_DIR`; defaults to the platform data directory.
+#[cfg(feature = "server")]
+fn state_dir() -> std::path::PathBuf {
+    if let Ok(dir) = std::env::var("RALPH_STATE_DIR") {
+        return std::path::PathBuf::from(dir);
+    }
+
+    dirs::data_local_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("ferris_wiggum")
+}
+
+/// How many sessions may run at once; the rest wait in the queue.
+///
+/// Overridable with `RALPH_MAX_CONCURRENT_SESSIONS`.
+#[cfg(feature = "server")]
+fn max_concurrent_sessions() -> usize {
+    std::env::var("RALPH_MAX_CONCURRENT_SESSIONS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(ralph::DEFAULT_MAX_CONCURRENT_SESSIONS)
+}
+
 #[cfg(feature = "server")]
 static BACKGROUND_TASKS_STARTED: OnceLock<()> = OnceLock::new();
 
@@ -182,6 +220,123 @@ pub async fn stop_session(id: String) -> Result<Session, ServerFnError> {
         })
 }
 
+// Iteration Diffs
+
+/// What the agent committed during one iteration of a session.
+#[server]
+pub async fn get_iteration_diff(session_id: String, iteration: u32) -> Result<Diff, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    SESSION_MANAGER
+        .iteration_diff(&session_id, iteration)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// Everything the agent committed for one story of a session.
+#[server]
+pub async fn get_story_diff(session_id: String, story_id: String) -> Result<Diff, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    SESSION_MANAGER
+        .story_diff(&session_id, &story_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// Undo an iteration and everything after it by resetting the session's
+/// branch to where that iteration started.
+#[server]
+pub async fn rollback_iteration(session_id: String, iteration: u32) -> Result<Session, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    tracing::info!("⏪ API: rollback_iteration({}, {})", session_id, iteration);
+    SESSION_MANAGER
+        .rollback_iteration(&session_id, iteration)
+        .await
+        .map_err(|e| {
+            tracing::error!("   Failed to roll back session {}: {}", session_id, e);
+            ServerFnError::new(e.to_string())
+        })
+}
+
+// Gutter Recovery
+
+#[cfg(feature = "server")]
+async fn recover(id: String, recovery: GutterRecovery) -> Result<Session, ServerFnError> {
+    init_background_tasks();
+
+    tracing::info!("🛟 API: recover_from_gutter({}, {})", id, recovery.describe());
+    SESSION_MANAGER
+        .recover_from_gutter(&id, recovery)
+        .await
+        .map_err(|e| {
+            tracing::error!("   Failed to recover session {}: {}", id, e);
+            ServerFnError::new(e.to_string())
+        })
+}
+
+/// Resume a session stuck in the gutter on the same story.
+#[server]
+pub async fn resume_from_gutter(id: String) -> Result<Session, ServerFnError> {
+    recover(id, GutterRecovery::Resume).await
+}
+
+/// Set aside the story a session got stuck on and carry on with the rest.
+#[server]
+pub async fn skip_stuck_story(id: String) -> Result<Session, ServerFnError> {
+    recover(id, GutterRecovery::SkipStory).await
+}
+
+/// Record what went wrong as a guardrail, then resume.
+#[server]
+pub async fn add_guardrail_and_resume(id: String, guardrail: Guardrail) -> Result<Session, ServerFnError> {
+    recover(id, GutterRecovery::AddGuardrail(guardrail)).await
+}
+
+/// Roll back the stuck iteration and retry it with another model.
+#[server]
+pub async fn retry_with_model(id: String, model: String) -> Result<Session, ServerFnError> {
+    recover(id, GutterRecovery::RetryWithModel { model }).await
+}
+
+// Story Status
+
+/// Set a story aside so the loop passes over it.
+#[server]
+pub async fn skip_story(session_id: String, story_id: String) -> Result<Session, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    tracing::info!("⏭️ API: skip_story({}, {})", session_id, story_id);
+    SESSION_MANAGER
+        .skip_story(&session_id, &story_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("   Failed to skip story {}: {}", story_id, e);
+            ServerFnError::new(e.to_string())
+        })
+}
+
+/// Hand a done, skipped or blocked story back to the loop.
+#[server]
+pub async fn reopen_story(session_id: String, story_id: String) -> Result<Session, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    tracing::info!("↩️ API: reopen_story({}, {})", session_id, story_id);
+    SESSION_MANAGER
+        .reopen_story(&session_id, &story_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("   Failed to reopen story {}: {}", story_id, e);
+            ServerFnError::new(e.to_string())
+        })
+}
+
 // Git Operations
 
 #[server]
@@ -262,9 +417,10 @@ pub async fn merge_branches(project_path: String, source: String) -> Result<(),
     }
 }
 
+/// Open a pull request on the forge configured for a session.
 #[server]
 pub async fn create_pull_request(
-    project_path: String,
+    session_id: String,
     branch: String,
     title: String,
     body: String,
@@ -272,21 +428,48 @@ pub async fn create_pull_request(
     #[cfg(feature = "server")]
     {
     tracing::info!("Creating PR for branch '{}'", branch);
-    let git = GitOperations::new(project_path);
-    git.create_pr(&branch, &title, &body)
+    SESSION_MANAGER
+        .open_pull_request(&session_id, branch, title, body)
         .await
+        .map(|pr| pr.url)
         .map_err(|e| ServerFnError::new(e.to_string()))
     }
 
     #[cfg(not(feature = "server"))]
     {
-        let _ = (project_path, branch, title, body);
+        let _ = (session_id, branch, title, body);
         Err(ServerFnError::new(
             "Git operations are only available on the server".to_string(),
         ))
     }
 }
 
+#[server]
+pub async fn get_pull_request_status(session_id: String) -> Result<PullRequest, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    SESSION_MANAGER
+        .pull_request_status(&session_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+#[server]
+pub async fn comment_on_pull_request(session_id: String, body: String) -> Result<(), ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    tracing::info!("💬 API: comment_on_pull_request({})", session_id);
+    SESSION_MANAGER
+        .comment_on_pull_request(&session_id, &body)
+        .await
+        .map_err(|e| {
+            tracing::error!("   Failed to comment on the PR of session {}: {}", session_id, e);
+            ServerFnError::new(e.to_string())
+        })
+}
+
 #[server]
 pub async fn push_branch(
     project_path: String,
@@ -431,8 +614,10 @@ fn parse_markdown_prd(
                     description: String::new(),
                     acceptance_criteria: Vec::new(),
                     priority: stories.len() as u32 + 1,
-                    passes: false,
+                    status: ralph::StoryStatus::Pending,
                     notes: String::new(),
+                    depends_on: Vec::new(),
+                    attempts: Vec::new(),
                 });
                 in_acceptance = false;
             }
@@ -457,6 +642,10 @@ fn parse_markdown_prd(
                 current_story.as_mut().unwrap().priority = priority;
             }
             in_acceptance = false;
+        } else if trimmed.starts_with("**Dependencies:**") && current_story.is_some() {
+            let dependencies = trimmed.trim_start_matches("**Dependencies:**");
+            current_story.as_mut().unwrap().depends_on = parse_dependencies(dependencies);
+            in_acceptance = false;
         } else if in_acceptance
             && (trimmed.starts_with("- [ ]")
                 || trimmed.starts_with("- [x]")
@@ -499,6 +688,14 @@ fn parse_markdown_prd(
     })
 }
 
+// Agent backends
+
+#[server]
+pub async fn list_agent_models(agent: AgentBackendKind) -> Result<Vec<AgentModel>, ServerFnError> {
+    tracing::debug!("Listing models for agent backend: {}", agent.label());
+    Ok(ralph::models_for(&agent))
+}
+
 // PRD Conversation
 
 #[server]
@@ -516,11 +713,12 @@ pub async fn start_prd_conversation(session_id: String) -> Result<PrdConversatio
     
     let model = session.config.prd_model.clone();
     let root_path = session.project_path.clone();
+    let agent = session.config.agent.clone();
     tracing::debug!("Using PRD model '{}' from session config", model);
     tracing::debug!("Using root_path '{}' for PRD generation", root_path);
     
     CONVERSATION_MANAGER
-        .start_conversation(session_id.clone(), model, root_path)
+        .start_conversation(session_id.clone(), model, root_path, agent)
         .await
         .map_err(|e| {
             tracing::error!("Failed to start PRD conversation for {}: {}", session_id, e);
@@ -554,11 +752,12 @@ pub async fn send_prd_message(
     
     let model = session.config.prd_model.clone();
     let root_path = session.project_path.clone();
+    let agent = session.config.agent.clone();
     tracing::debug!("Using PRD model '{}' from session config", model);
     tracing::debug!("Using root_path '{}' for PRD generation", root_path);
     
     CONVERSATION_MANAGER
-        .send_message(&session_id, message, model, root_path)
+        .send_message(&session_id, message, model, root_path, agent)
         .await
         .map_err(|e| {
             tracing::error!("Failed to send message for {}: {}", session_id, e);
@@ -931,5 +1130,69 @@ pub async fn get_common_directories() -> Result<Vec<CommonDirectory>, ServerFnEr
     Ok(directories)
 }
 
+// Activity History
+
+/// Page through a session's persisted activity history.
+///
+/// Returns up to `limit` events with a sequence number greater than `since`
+/// that match `filter`. Continue paging by passing the last returned `seq`.
+#[server]
+pub async fn get_activity(
+    session_id: String,
+    since: u64,
+    limit: u32,
+    filter: ActivityFilter,
+) -> Result<ActivityPage, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    tracing::debug!(
+        "📜 API: get_activity({}, since={}, limit={})",
+        session_id,
+        since,
+        limit
+    );
+    SESSION_MANAGER
+        .get_activity(&session_id, since, limit as usize, &filter)
+        .await
+        .map_err(|e| {
+            tracing::error!("   Failed to read activity for {}: {}", session_id, e);
+            ServerFnError::new(e.to_string())
+        })
+}
+
 // Activity Streaming
-// Note: SSE streaming will be implemented in the web package using use_resource
+
+/// Stream a session's activity as Server-Sent Events.
+///
+/// Events with a sequence number greater than `since` that are still buffered
+/// on the server are replayed first, so a reconnecting client passes the last
+/// `seq` it received and picks up where it left off. Pass `0` to replay
+/// everything the server still has.
+#[get("/api/ralph/sessions/{id}/activity/stream?since")]
+pub async fn stream_activity(
+    id: String,
+    since: u64,
+) -> Result<ServerEvents<ActivityEvent>, ServerFnError> {
+    #[cfg(feature = "server")]
+    init_background_tasks();
+
+    tracing::info!("📡 API: stream_activity({}, since={})", id, since);
+
+    // Make sure the session exists before holding a connection open for it.
+    SESSION_MANAGER.get_session(&id).await.map_err(|e| {
+        tracing::warn!("   Session {} not found for activity stream: {}", id, e);
+        ServerFnError::new(e.to_string())
+    })?;
+
+    let mut rx = SESSION_MANAGER.subscribe_to_activity(&id, Some(since)).await;
+
+    Ok(ServerEvents::new(move |mut tx| async move {
+        while let Some(event) = rx.recv().await {
+            if tx.send(event).await.is_err() {
+                tracing::debug!("Activity stream client for {} disconnected", id);
+                break;
+            }
+        }
+    }))
+}
@@ -0,0 +1,186 @@
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// One line of `.ralph/activity.log`.
+///
+/// Several sessions may target the same project, so every line records which
+/// session it belongs to; sequence numbers are per session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalLine {
+    session_id: String,
+    #[serde(flatten)]
+    event: ActivityEvent,
+}
+
+/// Append-only JSON-lines activity history stored in the project's `.ralph/`.
+pub struct ActivityJournal {
+    path: PathBuf,
+}
+
+impl ActivityJournal {
+    pub fn new(project_path: &str) -> Self {
+        Self {
+            path: Path::new(project_path).join(".ralph/activity.log"),
+        }
+    }
+
+    pub async fn append(&self, session_id: &str, event: &ActivityEvent) -> Result<(), RalphError> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let line = JournalLine {
+            session_id: session_id.to_string(),
+            event: event.clone(),
+        };
+        let mut json = serde_json::to_string(&line)
+            .map_err(|e| RalphError::Io(format!("Failed to serialize activity: {}", e)))?;
+        json.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(json.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    /// Highest sequence number recorded for `session_id`, or 0 if none.
+    pub async fn last_seq(&self, session_id: &str) -> Result<u64, RalphError> {
+        Ok(self
+            .load(session_id)
+            .await?
+            .last()
+            .map(|e| e.seq)
+            .unwrap_or(0))
+    }
+
+    /// Page forward through a session's history: events after `since` that match
+    /// `filter`, at most `limit` of them.
+    pub async fn read(
+        &self,
+        session_id: &str,
+        since: u64,
+        limit: usize,
+        filter: &ActivityFilter,
+    ) -> Result<ActivityPage, RalphError> {
+        let mut events: Vec<ActivityEvent> = self
+            .load(session_id)
+            .await?
+            .into_iter()
+            .filter(|e| e.seq > since && filter.matches(&e.entry))
+            .collect();
+
+        let has_more = events.len() > limit;
+        events.truncate(limit);
+
+        Ok(ActivityPage { events, has_more })
+    }
+
+    async fn load(&self, session_id: &str) -> Result<Vec<ActivityEvent>, RalphError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        let mut events: Vec<ActivityEvent> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<JournalLine>(line) {
+                Ok(line) => Some(line),
+                Err(e) => {
+                    // A torn write from a crash should not hide the rest of the history.
+                    tracing::warn!("Skipping unreadable activity line in {:?}: {}", self.path, e);
+                    None
+                }
+            })
+            .filter(|line| line.session_id == session_id)
+            .map(|line| line.event)
+            .collect();
+
+        events.sort_by_key(|e| e.seq);
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn event(seq: u64, kind: ActivityKind) -> ActivityEvent {
+        ActivityEvent {
+            seq,
+            entry: ActivityEntry {
+                timestamp: SystemTime::now(),
+                iteration: seq as u32,
+                kind,
+                health: ContextHealth::Healthy,
+                tokens: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_append_and_page() {
+        let dir = std::env::temp_dir().join(format!("ralph-activity-{}", uuid::Uuid::new_v4()));
+        let journal = ActivityJournal::new(dir.to_str().unwrap());
+
+        journal
+            .append("s1", &event(1, ActivityKind::Error("one".into())))
+            .await
+            .unwrap();
+        journal
+            .append(
+                "s1",
+                &event(
+                    2,
+                    ActivityKind::Shell {
+                        command: "cargo test".into(),
+                        exit_code: 0,
+                        output_bytes: 0,
+                    },
+                ),
+            )
+            .await
+            .unwrap();
+        journal
+            .append("other", &event(1, ActivityKind::Error("other".into())))
+            .await
+            .unwrap();
+        journal
+            .append("s1", &event(3, ActivityKind::Error("three".into())))
+            .await
+            .unwrap();
+
+        assert_eq!(journal.last_seq("s1").await.unwrap(), 3);
+        assert_eq!(journal.last_seq("missing").await.unwrap(), 0);
+
+        let page = journal
+            .read("s1", 0, 2, &ActivityFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(page.events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(page.has_more);
+
+        let page = journal
+            .read("s1", 2, 2, &ActivityFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(page.events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![3]);
+        assert!(!page.has_more);
+
+        let errors_only = ActivityFilter {
+            kinds: vec!["error".into()],
+            ..Default::default()
+        };
+        let page = journal.read("s1", 0, 10, &errors_only).await.unwrap();
+        assert_eq!(page.events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 3]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
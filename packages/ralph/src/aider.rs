@@ -0,0 +1,160 @@
+use crate::backend::{AgentBackend, OutputParser};
+use crate::types::*;
+use tokio::process::Command;
+
+/// aider backend, driven one message at a time with `--message`.
+///
+/// aider has no machine-readable output, so its plain-text progress lines are
+/// recognised by their wording.
+pub struct AiderBackend {
+    project_path: String,
+    model: String,
+}
+
+impl AiderBackend {
+    pub fn new(project_path: String, model: String) -> Self {
+        Self {
+            project_path,
+            model,
+        }
+    }
+
+    fn base_command(&self, prompt: &str) -> Command {
+        let mut command = Command::new("aider");
+        command
+            .arg("--model")
+            .arg(&self.model)
+            .arg("--yes-always")
+            .arg("--no-stream")
+            .arg("--no-pretty")
+            .arg("--no-check-update")
+            .arg("--message")
+            .arg(prompt);
+        command
+    }
+}
+
+impl AgentBackend for AiderBackend {
+    fn name(&self) -> &str {
+        "aider"
+    }
+
+    fn models(&self) -> Vec<AgentModel> {
+        vec![
+            AgentModel::new("sonnet", "Claude Sonnet"),
+            AgentModel::new("opus", "Claude Opus"),
+            AgentModel::new("gpt-4o", "GPT-4o"),
+            AgentModel::new("deepseek", "DeepSeek"),
+        ]
+    }
+
+    fn iteration_command(&self, prompt: &str) -> Command {
+        tracing::debug!("aider model: {} (in {})", self.model, self.project_path);
+        self.base_command(prompt)
+    }
+
+    fn chat_command(&self, prompt: &str) -> Command {
+        let mut command = self.base_command(prompt);
+        command.arg("--chat-mode").arg("ask").arg("--no-auto-commits");
+        command
+    }
+
+    fn output_parser(&self) -> Box<dyn OutputParser> {
+        Box::new(AiderOutputParser)
+    }
+
+    fn stop_signal(&self) -> &'static str {
+        // aider treats Ctrl-C as "stop what you're doing".
+        "INT"
+    }
+}
+
+struct AiderOutputParser;
+
+impl OutputParser for AiderOutputParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError> {
+        let line = line.trim_end();
+
+        if let Some(path) = line.strip_prefix("Applied edit to ") {
+            return Ok(vec![ActivityKind::Write {
+                path: path.trim().to_string(),
+                lines: 0,
+                bytes: 0,
+            }]);
+        }
+
+        if let Some(usage) = parse_token_line(line) {
+            return Ok(vec![ActivityKind::Usage(usage)]);
+        }
+
+        Ok(vec![ActivityKind::Assistant(line.to_string())])
+    }
+}
+
+/// `Tokens: 12k sent, 1.3k cache write, 2.1k cache hit, 512 received. Cost: ...`
+fn parse_token_line(line: &str) -> Option<ReportedUsage> {
+    let rest = line.strip_prefix("Tokens: ")?;
+    let counts = rest.split('.').next().unwrap_or(rest);
+    let counts = match rest.find(" received") {
+        Some(end) => &rest[..end + " received".len()],
+        None => counts,
+    };
+
+    let mut usage = ReportedUsage::default();
+    for part in counts.split(", ") {
+        let (number, label) = part.trim().split_once(' ')?;
+        let value = parse_count(number)?;
+        match label {
+            "sent" => usage.input_tokens = value,
+            "received" => usage.output_tokens = value,
+            "cache write" => usage.cache_write_tokens = value,
+            // aider's "sent" already includes cache hits.
+            "cache hit" => {}
+            _ => return None,
+        }
+    }
+
+    Some(usage)
+}
+
+/// `512`, `1.3k`, `12k`.
+fn parse_count(text: &str) -> Option<u32> {
+    match text.strip_suffix('k') {
+        Some(thousands) => thousands.parse::<f64>().ok().map(|k| (k * 1000.0).round() as u32),
+        None => text.replace(',', "").parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aider_output() {
+        let mut parser = AiderOutputParser;
+
+        assert_eq!(
+            parser.parse_line("Applied edit to src/lib.rs").unwrap(),
+            vec![ActivityKind::Write {
+                path: "src/lib.rs".into(),
+                lines: 0,
+                bytes: 0
+            }]
+        );
+        assert_eq!(
+            parser
+                .parse_line("Tokens: 12k sent, 1.3k cache write, 2.1k cache hit, 512 received. Cost: $0.04 message, $0.10 session.")
+                .unwrap(),
+            vec![ActivityKind::Usage(ReportedUsage {
+                input_tokens: 12_000,
+                output_tokens: 512,
+                cache_read_tokens: 0,
+                cache_write_tokens: 1_300,
+            })]
+        );
+        assert_eq!(
+            parser.parse_line("<ralph>COMPLETE</ralph>").unwrap(),
+            vec![ActivityKind::Assistant("<ralph>COMPLETE</ralph>".into())]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::aider::AiderBackend;
+use crate::claude::ClaudeCodeBackend;
+use crate::codex::CodexBackend;
+use crate::command::CommandBackend;
+use crate::cursor::CursorRunner;
+use crate::types::*;
+use std::sync::Arc;
+use tokio::process::Command;
+
+/// A coding agent CLI that Ralph can drive.
+///
+/// A backend is bound to one project directory and model. It knows how to
+/// spawn the agent, how to read its output and how to ask it to stop; the
+/// process lifecycle itself is handled by `AgentRunner`.
+pub trait AgentBackend: Send + Sync {
+    /// Short name used in logs and errors.
+    fn name(&self) -> &str;
+
+    /// Models the agent accepts.
+    fn models(&self) -> Vec<AgentModel>;
+
+    /// Command for one headless iteration on `prompt`, with machine-readable
+    /// output on stdout. The runner sets up the working directory and pipes.
+    fn iteration_command(&self, prompt: &str) -> Command;
+
+    /// Command that answers `prompt` with plain text on stdout.
+    fn chat_command(&self, prompt: &str) -> Command;
+
+    /// A fresh parser for one run's stdout.
+    fn output_parser(&self) -> Box<dyn OutputParser>;
+
+    /// Signal sent (via `kill -<signal>`) to ask the agent to exit cleanly.
+    fn stop_signal(&self) -> &'static str {
+        "TERM"
+    }
+}
+
+/// Turns an agent's stdout, line by line, into activities.
+///
+/// Parsers are stateful so tool calls can be paired with their results across
+/// lines.
+pub trait OutputParser: Send {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError>;
+}
+
+/// Builds the backend for a session's next iteration, overriding the one
+/// selected in its config.
+pub type BackendFactory = Arc<dyn Fn(&Session) -> Box<dyn AgentBackend> + Send + Sync>;
+
+/// Build the backend selected in a session's config.
+pub fn backend_for(kind: &AgentBackendKind, project_path: String, model: String) -> Box<dyn AgentBackend> {
+    match kind {
+        AgentBackendKind::Cursor => Box::new(CursorRunner::new(project_path, model)),
+        AgentBackendKind::ClaudeCode => Box::new(ClaudeCodeBackend::new(project_path, model)),
+        AgentBackendKind::Codex => Box::new(CodexBackend::new(project_path, model)),
+        AgentBackendKind::Aider => Box::new(AiderBackend::new(project_path, model)),
+        AgentBackendKind::Command { program, args } => Box::new(CommandBackend::new(
+            project_path,
+            model,
+            program.clone(),
+            args.clone(),
+        )),
+    }
+}
+
+/// Models offered for `kind`, for the session form.
+pub fn models_for(kind: &AgentBackendKind) -> Vec<AgentModel> {
+    backend_for(kind, String::new(), String::new()).models()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_for_each_kind() {
+        let kinds = vec![
+            AgentBackendKind::Cursor,
+            AgentBackendKind::ClaudeCode,
+            AgentBackendKind::Codex,
+            AgentBackendKind::Aider,
+            AgentBackendKind::Command {
+                program: "my-agent".to_string(),
+                args: vec!["--model".to_string(), "{model}".to_string(), "{prompt}".to_string()],
+            },
+        ];
+
+        for kind in kinds {
+            let backend = backend_for(&kind, "/tmp/project".to_string(), "m".to_string());
+            assert!(!backend.name().is_empty());
+            assert!(!backend.models().is_empty(), "{} lists no models", backend.name());
+
+            let command = backend.iteration_command("do the thing");
+            let args: Vec<String> = command
+                .as_std()
+                .get_args()
+                .map(|a| a.to_string_lossy().to_string())
+                .collect();
+            assert!(
+                args.iter().any(|a| a.contains("do the thing")),
+                "{} does not pass the prompt: {:?}",
+                backend.name(),
+                args
+            );
+        }
+    }
+}
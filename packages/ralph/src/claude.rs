@@ -0,0 +1,289 @@
+use crate::backend::{AgentBackend, OutputParser};
+use crate::types::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+/// Claude Code CLI (`claude -p`) backend.
+pub struct ClaudeCodeBackend {
+    project_path: String,
+    model: String,
+}
+
+impl ClaudeCodeBackend {
+    pub fn new(project_path: String, model: String) -> Self {
+        Self {
+            project_path,
+            model,
+        }
+    }
+}
+
+impl AgentBackend for ClaudeCodeBackend {
+    fn name(&self) -> &str {
+        "claude"
+    }
+
+    fn models(&self) -> Vec<AgentModel> {
+        vec![
+            AgentModel::new("opus", "Claude Opus (latest)"),
+            AgentModel::new("sonnet", "Claude Sonnet (latest)"),
+            AgentModel::new("haiku", "Claude Haiku (latest)"),
+        ]
+    }
+
+    fn iteration_command(&self, prompt: &str) -> Command {
+        tracing::debug!("claude model: {} (in {})", self.model, self.project_path);
+        let mut command = Command::new("claude");
+        command
+            .arg("-p")
+            .arg("--output-format")
+            .arg("stream-json")
+            // stream-json requires verbose output in print mode.
+            .arg("--verbose")
+            .arg("--dangerously-skip-permissions")
+            .arg("--model")
+            .arg(&self.model)
+            .arg(prompt);
+        command
+    }
+
+    fn chat_command(&self, prompt: &str) -> Command {
+        let mut command = Command::new("claude");
+        command
+            .arg("-p")
+            .arg("--output-format")
+            .arg("text")
+            .arg("--model")
+            .arg(&self.model)
+            .arg(prompt);
+        command
+    }
+
+    fn output_parser(&self) -> Box<dyn OutputParser> {
+        Box::new(ClaudeStreamParser::default())
+    }
+}
+
+/// One line of `claude -p --output-format stream-json`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeEvent {
+    Assistant { message: ClaudeMessage },
+    User { message: ClaudeMessage },
+    Result {
+        #[serde(default)]
+        is_error: bool,
+        #[serde(default)]
+        result: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeMessage {
+    #[serde(default)]
+    content: ClaudeContent,
+    #[serde(default)]
+    usage: Option<ReportedUsage>,
+}
+
+/// Message content is either a list of blocks or a bare string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ClaudeContent {
+    Blocks(Vec<ClaudeBlock>),
+    Text(String),
+}
+
+impl Default for ClaudeContent {
+    fn default() -> Self {
+        ClaudeContent::Blocks(Vec::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: serde_json::Value,
+        #[serde(default)]
+        is_error: bool,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Pairs `tool_use` blocks with the `tool_result` that answers them.
+#[derive(Default)]
+struct ClaudeStreamParser {
+    pending: HashMap<String, (String, serde_json::Value)>,
+}
+
+impl OutputParser for ClaudeStreamParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError> {
+        let event: ClaudeEvent = serde_json::from_str(line)
+            .map_err(|e| RalphError::Parse(format!("Invalid claude stream-json line: {}", e)))?;
+
+        let mut activities = Vec::new();
+        match event {
+            ClaudeEvent::Assistant { message } => {
+                let mut text = String::new();
+                match message.content {
+                    ClaudeContent::Blocks(blocks) => {
+                        for block in blocks {
+                            match block {
+                                ClaudeBlock::Text { text: t } => text.push_str(&t),
+                                ClaudeBlock::ToolUse { id, name, input } => {
+                                    self.pending.insert(id, (name, input));
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    ClaudeContent::Text(t) => text = t,
+                }
+                if !text.trim().is_empty() {
+                    activities.push(ActivityKind::Assistant(text));
+                }
+                if let Some(usage) = message.usage {
+                    activities.push(ActivityKind::Usage(usage));
+                }
+            }
+            ClaudeEvent::User { message } => {
+                if let ClaudeContent::Blocks(blocks) = message.content {
+                    for block in blocks {
+                        if let ClaudeBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                            is_error,
+                        } = block
+                        {
+                            if let Some((name, input)) = self.pending.remove(&tool_use_id) {
+                                activities.extend(tool_activity(&name, &input, &content, is_error));
+                            }
+                        }
+                    }
+                }
+            }
+            ClaudeEvent::Result { is_error: true, result } => {
+                activities.push(ActivityKind::Error(
+                    result.unwrap_or_else(|| "claude reported an error".to_string()),
+                ));
+            }
+            _ => {}
+        }
+
+        Ok(activities)
+    }
+}
+
+fn tool_activity(
+    name: &str,
+    input: &serde_json::Value,
+    content: &serde_json::Value,
+    is_error: bool,
+) -> Option<ActivityKind> {
+    let str_field = |key: &str| input.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let output = result_text(content);
+
+    match name {
+        "Bash" => Some(ActivityKind::Shell {
+            command: str_field("command"),
+            exit_code: if is_error { 1 } else { 0 },
+            output_bytes: output.len() as u32,
+        }),
+        _ if is_error => Some(ActivityKind::Error(format!("{} failed: {}", name, output))),
+        "Read" => Some(ActivityKind::Read {
+            path: str_field("file_path"),
+            lines: output.lines().count() as u32,
+            bytes: output.len() as u32,
+        }),
+        "Write" => {
+            let text = str_field("content");
+            Some(ActivityKind::Write {
+                path: str_field("file_path"),
+                lines: text.lines().count() as u32,
+                bytes: text.len() as u32,
+            })
+        }
+        "Edit" | "MultiEdit" => {
+            let text = str_field("new_string");
+            Some(ActivityKind::Write {
+                path: str_field("file_path"),
+                lines: text.lines().count() as u32,
+                bytes: text.len() as u32,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Tool results are either a string or a list of text blocks.
+fn result_text(content: &serde_json::Value) -> String {
+    match content {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_claude_fixture() {
+        let mut parser = ClaudeStreamParser::default();
+        let activities: Vec<ActivityKind> = include_str!("../fixtures/claude/story.jsonl")
+            .lines()
+            .flat_map(|line| parser.parse_line(line).unwrap())
+            .collect();
+
+        assert_eq!(
+            activities,
+            vec![
+                ActivityKind::Assistant("Reading the PRD first.".into()),
+                ActivityKind::Usage(ReportedUsage {
+                    input_tokens: 4,
+                    output_tokens: 90,
+                    cache_read_tokens: 14_000,
+                    cache_write_tokens: 2_000,
+                }),
+                ActivityKind::Read {
+                    path: "/repo/prd.json".into(),
+                    lines: 2,
+                    bytes: 21,
+                },
+                ActivityKind::Write {
+                    path: "/repo/src/lib.rs".into(),
+                    lines: 1,
+                    bytes: 15,
+                },
+                ActivityKind::Shell {
+                    command: "cargo test".into(),
+                    exit_code: 1,
+                    output_bytes: 12,
+                },
+                ActivityKind::Assistant("<ralph>COMPLETE</ralph>".into()),
+            ]
+        );
+        assert!(parser.pending.is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::backend::{AgentBackend, OutputParser};
+use crate::types::*;
+use serde::Deserialize;
+use tokio::process::Command;
+
+/// OpenAI Codex CLI (`codex exec`) backend.
+pub struct CodexBackend {
+    project_path: String,
+    model: String,
+}
+
+impl CodexBackend {
+    pub fn new(project_path: String, model: String) -> Self {
+        Self {
+            project_path,
+            model,
+        }
+    }
+}
+
+impl AgentBackend for CodexBackend {
+    fn name(&self) -> &str {
+        "codex"
+    }
+
+    fn models(&self) -> Vec<AgentModel> {
+        vec![
+            AgentModel::new("gpt-5-codex", "GPT-5 Codex"),
+            AgentModel::new("gpt-5", "GPT-5"),
+        ]
+    }
+
+    fn iteration_command(&self, prompt: &str) -> Command {
+        tracing::debug!("codex model: {} (in {})", self.model, self.project_path);
+        let mut command = Command::new("codex");
+        command
+            .arg("exec")
+            .arg("--json")
+            .arg("--full-auto")
+            .arg("--model")
+            .arg(&self.model)
+            .arg(prompt);
+        command
+    }
+
+    fn chat_command(&self, prompt: &str) -> Command {
+        // Without --json, `codex exec` prints only the final message on stdout.
+        let mut command = Command::new("codex");
+        command
+            .arg("exec")
+            .arg("--sandbox")
+            .arg("read-only")
+            .arg("--model")
+            .arg(&self.model)
+            .arg(prompt);
+        command
+    }
+
+    fn output_parser(&self) -> Box<dyn OutputParser> {
+        Box::new(CodexJsonParser)
+    }
+}
+
+/// One line of `codex exec --json`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum CodexEvent {
+    #[serde(rename = "item.completed")]
+    ItemCompleted { item: CodexItem },
+    #[serde(rename = "turn.completed")]
+    TurnCompleted {
+        // `cached_input_tokens` is a subset of `input_tokens`, so it is ignored.
+        #[serde(default)]
+        usage: Option<ReportedUsage>,
+    },
+    #[serde(rename = "turn.failed")]
+    TurnFailed {
+        #[serde(default)]
+        error: Option<CodexError>,
+    },
+    #[serde(rename = "error")]
+    Error { message: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodexError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CodexItem {
+    AgentMessage {
+        #[serde(default)]
+        text: String,
+    },
+    CommandExecution {
+        command: String,
+        #[serde(default)]
+        aggregated_output: String,
+        #[serde(default)]
+        exit_code: Option<i32>,
+    },
+    FileChange {
+        #[serde(default)]
+        changes: Vec<CodexFileChange>,
+    },
+    Error {
+        message: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodexFileChange {
+    path: String,
+}
+
+struct CodexJsonParser;
+
+impl OutputParser for CodexJsonParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError> {
+        let event: CodexEvent = serde_json::from_str(line)
+            .map_err(|e| RalphError::Parse(format!("Invalid codex JSON line: {}", e)))?;
+
+        let activities = match event {
+            CodexEvent::ItemCompleted { item } => match item {
+                CodexItem::AgentMessage { text } if !text.trim().is_empty() => {
+                    vec![ActivityKind::Assistant(text)]
+                }
+                CodexItem::CommandExecution {
+                    command,
+                    aggregated_output,
+                    exit_code,
+                } => vec![ActivityKind::Shell {
+                    command,
+                    exit_code: exit_code.unwrap_or(1),
+                    output_bytes: aggregated_output.len() as u32,
+                }],
+                // Codex reports which files changed but not how much.
+                CodexItem::FileChange { changes } => changes
+                    .into_iter()
+                    .map(|change| ActivityKind::Write {
+                        path: change.path,
+                        lines: 0,
+                        bytes: 0,
+                    })
+                    .collect(),
+                CodexItem::Error { message } => vec![ActivityKind::Error(message)],
+                _ => Vec::new(),
+            },
+            CodexEvent::TurnCompleted { usage: Some(usage) } => vec![ActivityKind::Usage(usage)],
+            CodexEvent::TurnFailed { error } => vec![ActivityKind::Error(
+                error
+                    .map(|e| e.message)
+                    .unwrap_or_else(|| "codex turn failed".to_string()),
+            )],
+            CodexEvent::Error { message } => vec![ActivityKind::Error(message)],
+            _ => Vec::new(),
+        };
+
+        Ok(activities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codex_fixture() {
+        let mut parser = CodexJsonParser;
+        let activities: Vec<ActivityKind> = include_str!("../fixtures/codex/story.jsonl")
+            .lines()
+            .flat_map(|line| parser.parse_line(line).unwrap())
+            .collect();
+
+        assert_eq!(
+            activities,
+            vec![
+                ActivityKind::Shell {
+                    command: "bash -lc 'cat prd.json'".into(),
+                    exit_code: 0,
+                    output_bytes: 20,
+                },
+                ActivityKind::Write {
+                    path: "/repo/src/greet.rs".into(),
+                    lines: 0,
+                    bytes: 0,
+                },
+                ActivityKind::Write {
+                    path: "/repo/src/lib.rs".into(),
+                    lines: 0,
+                    bytes: 0,
+                },
+                ActivityKind::Shell {
+                    command: "bash -lc 'cargo test'".into(),
+                    exit_code: 101,
+                    output_bytes: 35,
+                },
+                ActivityKind::Assistant("Fixed the build. <ralph>COMPLETE</ralph>".into()),
+                ActivityKind::Usage(ReportedUsage {
+                    input_tokens: 24763,
+                    output_tokens: 122,
+                    ..Default::default()
+                }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::backend::{AgentBackend, OutputParser};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Any program that speaks Ralph's own JSON-lines activity format.
+///
+/// `{prompt}` and `{model}` in `args` are substituted before spawning; if no
+/// argument mentions `{prompt}`, the prompt is appended as the last argument.
+/// Each stdout line is either an activity object (see `CommandEvent`) or plain
+/// text, which is treated as assistant output.
+pub struct CommandBackend {
+    project_path: String,
+    model: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandBackend {
+    pub fn new(project_path: String, model: String, program: String, args: Vec<String>) -> Self {
+        Self {
+            project_path,
+            model,
+            program,
+            args,
+        }
+    }
+
+    fn command(&self, prompt: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        let mut saw_prompt = false;
+        for arg in &self.args {
+            saw_prompt |= arg.contains("{prompt}");
+            command.arg(arg.replace("{model}", &self.model).replace("{prompt}", prompt));
+        }
+        if !saw_prompt {
+            command.arg(prompt);
+        }
+        command
+    }
+}
+
+impl AgentBackend for CommandBackend {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn models(&self) -> Vec<AgentModel> {
+        vec![AgentModel::new("default", "Default (passed as {model})")]
+    }
+
+    fn iteration_command(&self, prompt: &str) -> Command {
+        tracing::debug!("{} model: {} (in {})", self.program, self.model, self.project_path);
+        self.command(prompt)
+    }
+
+    fn chat_command(&self, prompt: &str) -> Command {
+        // Custom commands have no separate chat mode; stdout is used verbatim.
+        self.command(prompt)
+    }
+
+    fn output_parser(&self) -> Box<dyn OutputParser> {
+        Box::new(CommandOutputParser)
+    }
+}
+
+/// One JSON line from a custom command.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum CommandEvent {
+    Read {
+        path: String,
+        #[serde(default)]
+        lines: u32,
+        #[serde(default)]
+        bytes: u32,
+    },
+    Write {
+        path: String,
+        #[serde(default)]
+        lines: u32,
+        #[serde(default)]
+        bytes: u32,
+    },
+    Shell {
+        command: String,
+        #[serde(default)]
+        exit_code: i32,
+        #[serde(default)]
+        output_bytes: u32,
+    },
+    Assistant {
+        text: String,
+    },
+    Usage(ReportedUsage),
+    Error {
+        message: String,
+    },
+}
+
+pub(crate) struct CommandOutputParser;
+
+impl OutputParser for CommandOutputParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError> {
+        if !line.trim_start().starts_with('{') {
+            return Ok(vec![ActivityKind::Assistant(line.to_string())]);
+        }
+
+        let event: CommandEvent = serde_json::from_str(line)
+            .map_err(|e| RalphError::Parse(format!("Invalid command activity line: {}", e)))?;
+
+        let activity = match event {
+            CommandEvent::Read { path, lines, bytes } => ActivityKind::Read { path, lines, bytes },
+            CommandEvent::Write { path, lines, bytes } => ActivityKind::Write { path, lines, bytes },
+            CommandEvent::Shell {
+                command,
+                exit_code,
+                output_bytes,
+            } => ActivityKind::Shell {
+                command,
+                exit_code,
+                output_bytes,
+            },
+            CommandEvent::Assistant { text } => ActivityKind::Assistant(text),
+            CommandEvent::Usage(usage) => ActivityKind::Usage(usage),
+            CommandEvent::Error { message } => ActivityKind::Error(message),
+        };
+
+        Ok(vec![activity])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args_of(command: &Command) -> Vec<String> {
+        command
+            .as_std()
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_command_substitutes_placeholders() {
+        let backend = CommandBackend::new(
+            "/tmp/project".into(),
+            "fast".into(),
+            "agent".into(),
+            vec!["--model={model}".into(), "--task".into(), "{prompt}".into()],
+        );
+        assert_eq!(
+            args_of(&backend.iteration_command("fix it")),
+            vec!["--model=fast", "--task", "fix it"]
+        );
+
+        let appended = CommandBackend::new(String::new(), "m".into(), "agent".into(), vec!["run".into()]);
+        assert_eq!(args_of(&appended.iteration_command("fix it")), vec!["run", "fix it"]);
+    }
+
+    #[test]
+    fn test_command_parser() {
+        let mut parser = CommandOutputParser;
+
+        assert_eq!(
+            parser
+                .parse_line(r#"{"type":"shell","command":"cargo test","exit_code":0,"output_bytes":9}"#)
+                .unwrap(),
+            vec![ActivityKind::Shell {
+                command: "cargo test".into(),
+                exit_code: 0,
+                output_bytes: 9,
+            }]
+        );
+        assert_eq!(
+            parser.parse_line(r#"{"type":"usage","input_tokens":100,"output_tokens":20}"#).unwrap(),
+            vec![ActivityKind::Usage(ReportedUsage {
+                input_tokens: 100,
+                output_tokens: 20,
+                ..Default::default()
+            })]
+        );
+        assert_eq!(
+            parser.parse_line("thinking out loud").unwrap(),
+            vec![ActivityKind::Assistant("thinking out loud".into())]
+        );
+        assert!(parser.parse_line(r#"{"type":"bogus"}"#).is_err());
+    }
+}
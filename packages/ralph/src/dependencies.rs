@@ -0,0 +1,194 @@
+use crate::types::*;
+use std::collections::{HashMap, HashSet};
+
+/// Check that every dependency names a story in `stories` and that no story
+/// depends on itself, directly or through other stories.
+pub fn validate_dependencies(stories: &[Story]) -> Result<(), RalphError> {
+    let ids: HashSet<&str> = stories.iter().map(|s| s.id.as_str()).collect();
+    for story in stories {
+        if let Some(unknown) = story.depends_on.iter().find(|dep| !ids.contains(dep.as_str())) {
+            return Err(RalphError::InvalidPrd(format!(
+                "{} depends on unknown story {}",
+                story.id, unknown
+            )));
+        }
+    }
+
+    match find_cycle(stories) {
+        Some(cycle) => Err(RalphError::InvalidPrd(format!(
+            "Stories depend on each other in a cycle: {}",
+            cycle.join(" → ")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Story ids from a `**Dependencies:**` line, e.g. "US-001, US-002" or
+/// "None". Words that aren't shaped like a story id (`US-001`) are ignored.
+pub fn parse_dependencies(line: &str) -> Vec<String> {
+    line.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|word| word.trim_matches(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|word| is_story_id(word))
+        .map(String::from)
+        .collect()
+}
+
+/// Whether `word` looks like `US-001`: capitals, a dash and digits.
+fn is_story_id(word: &str) -> bool {
+    word.split_once('-').is_some_and(|(prefix, number)| {
+        !prefix.is_empty()
+            && prefix.chars().all(|c| c.is_ascii_uppercase())
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// Whether every story `story` depends on has passed.
+pub fn dependencies_met(story: &Story, stories: &[Story]) -> bool {
+    story
+        .depends_on
+        .iter()
+        .all(|dep| stories.iter().any(|s| &s.id == dep && s.passes()))
+}
+
+/// `stories` in layers where each story depends only on stories in earlier
+/// layers, by priority within a layer. Stories caught in a cycle are left out.
+pub fn dependency_layers(stories: &[Story]) -> Vec<Vec<&Story>> {
+    let ids: HashSet<&str> = stories.iter().map(|s| s.id.as_str()).collect();
+    let mut placed: HashSet<&str> = HashSet::new();
+    let mut remaining: Vec<&Story> = stories.iter().collect();
+    let mut layers = Vec::new();
+
+    while !remaining.is_empty() {
+        let (mut layer, rest): (Vec<&Story>, Vec<&Story>) = remaining.into_iter().partition(|story| {
+            story
+                .depends_on
+                .iter()
+                .all(|dep| placed.contains(dep.as_str()) || !ids.contains(dep.as_str()))
+        });
+        if layer.is_empty() {
+            break;
+        }
+
+        layer.sort_by_key(|s| s.priority);
+        placed.extend(layer.iter().map(|s| s.id.as_str()));
+        layers.push(layer);
+        remaining = rest;
+    }
+    layers
+}
+
+/// The first dependency cycle found, as story ids from a story back to itself.
+fn find_cycle(stories: &[Story]) -> Option<Vec<String>> {
+    let by_id: HashMap<&str, &Story> = stories.iter().map(|s| (s.id.as_str(), s)).collect();
+    let mut done = HashSet::new();
+    stories
+        .iter()
+        .find_map(|story| visit(&story.id, &by_id, &mut Vec::new(), &mut done))
+}
+
+fn visit<'a>(
+    id: &'a str,
+    by_id: &HashMap<&'a str, &'a Story>,
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+) -> Option<Vec<String>> {
+    if done.contains(id) {
+        return None;
+    }
+    if let Some(start) = path.iter().position(|&p| p == id) {
+        let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+        cycle.push(id.to_string());
+        return Some(cycle);
+    }
+    let story = by_id.get(id)?;
+
+    path.push(id);
+    for dep in &story.depends_on {
+        if let Some(cycle) = visit(dep, by_id, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(id);
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story(id: &str, priority: u32, depends_on: &[&str]) -> Story {
+        Story {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            acceptance_criteria: Vec::new(),
+            priority,
+            status: StoryStatus::Pending,
+            notes: String::new(),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            attempts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_dependencies() {
+        assert!(parse_dependencies(" None").is_empty());
+        assert_eq!(parse_dependencies(" US-001, US-002"), vec!["US-001", "US-002"]);
+        assert_eq!(parse_dependencies(" US-001 and US-002"), vec!["US-001", "US-002"]);
+        assert_eq!(parse_dependencies(" US-001 (after the schema lands)"), vec!["US-001"]);
+        assert!(parse_dependencies(" Needs the login work first").is_empty());
+    }
+
+    #[test]
+    fn test_validate_dependencies() {
+        let stories = vec![story("US-001", 1, &[]), story("US-002", 2, &["US-001"])];
+        assert!(validate_dependencies(&stories).is_ok());
+
+        let unknown = vec![story("US-001", 1, &["US-009"])];
+        assert_eq!(
+            validate_dependencies(&unknown).unwrap_err().to_string(),
+            "Invalid PRD: US-001 depends on unknown story US-009"
+        );
+
+        let cycle = vec![
+            story("US-001", 1, &["US-003"]),
+            story("US-002", 2, &["US-001"]),
+            story("US-003", 3, &["US-002"]),
+        ];
+        assert_eq!(
+            validate_dependencies(&cycle).unwrap_err().to_string(),
+            "Invalid PRD: Stories depend on each other in a cycle: US-001 → US-003 → US-002 → US-001"
+        );
+
+        let own = vec![story("US-001", 1, &["US-001"])];
+        assert!(validate_dependencies(&own).is_err());
+    }
+
+    #[test]
+    fn test_dependencies_met() {
+        let mut stories = vec![story("US-001", 1, &[]), story("US-002", 2, &["US-001"])];
+        assert!(dependencies_met(&stories[0], &stories));
+        assert!(!dependencies_met(&stories[1], &stories));
+
+        stories[0].status = StoryStatus::Done;
+        assert!(dependencies_met(&stories[1], &stories));
+    }
+
+    #[test]
+    fn test_dependency_layers() {
+        let stories = vec![
+            story("US-001", 3, &[]),
+            story("US-002", 1, &["US-001"]),
+            story("US-003", 2, &[]),
+            story("US-004", 4, &["US-002", "US-003"]),
+        ];
+
+        let layers: Vec<Vec<&str>> = dependency_layers(&stories)
+            .iter()
+            .map(|layer| layer.iter().map(|s| s.id.as_str()).collect())
+            .collect();
+        assert_eq!(layers, vec![vec!["US-003", "US-001"], vec!["US-002"], vec!["US-004"]]);
+    }
+}
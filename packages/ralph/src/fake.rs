@@ -0,0 +1,323 @@
+use crate::backend::{AgentBackend, BackendFactory, OutputParser};
+use crate::command::{CommandEvent, CommandOutputParser};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+
+/// One scripted action of the fake agent.
+///
+/// A transcript is JSON lines of these, tagged by `step`; `end_iteration`
+/// closes the script for one iteration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum FakeStep {
+    /// Print assistant text, e.g. `<ralph>COMPLETE</ralph>`.
+    Say { text: String },
+    /// Write `content` to `path` (relative to the project).
+    Write { path: String, content: String },
+    /// Run a shell command and report its real exit code.
+    Shell { command: String },
+    /// Stage everything and commit.
+    Commit { message: String },
+    /// Set `passes: true` for the story in `prd.json`.
+    MarkPassing { story_id: String },
+    /// Report token usage.
+    Usage(ReportedUsage),
+    /// Report an agent-side error.
+    Error { message: String },
+    /// Block for `secs` seconds without printing anything.
+    Hang {
+        #[serde(default = "default_hang_secs")]
+        secs: u64,
+    },
+    /// Exit with `code` right away.
+    Exit { code: i32 },
+    /// End of this iteration's script.
+    EndIteration,
+}
+
+fn default_hang_secs() -> u64 {
+    3600
+}
+
+/// Agent backend that replays scripted iterations instead of calling a model.
+///
+/// Each iteration pops the next script and runs it as a `sh` process that
+/// really edits files and commits, printing the JSON-lines activity format of
+/// `CommandBackend`. Once the scripts run out the agent does nothing and exits
+/// cleanly. Clones share the same scripts, so a `SessionManager` can build a
+/// fresh backend per iteration through `factory()`.
+#[derive(Clone)]
+pub struct FakeAgentBackend {
+    project_path: String,
+    iterations: Arc<Mutex<VecDeque<Vec<FakeStep>>>>,
+    prompts: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeAgentBackend {
+    pub fn new(project_path: String) -> Self {
+        Self {
+            project_path,
+            iterations: Arc::new(Mutex::new(VecDeque::new())),
+            prompts: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Parse a JSON-lines transcript, one iteration per `end_iteration`.
+    pub fn from_transcript(project_path: String, transcript: &str) -> Result<Self, RalphError> {
+        let backend = Self::new(project_path);
+        let mut steps = Vec::new();
+
+        for (number, line) in transcript.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let step: FakeStep = serde_json::from_str(line).map_err(|e| {
+                RalphError::Parse(format!("Invalid fake agent step on line {}: {}", number + 1, e))
+            })?;
+            let ends_iteration = step == FakeStep::EndIteration;
+            steps.push(step);
+            if ends_iteration {
+                backend.push_iteration(std::mem::take(&mut steps));
+            }
+        }
+        if !steps.is_empty() {
+            backend.push_iteration(steps);
+        }
+
+        Ok(backend)
+    }
+
+    /// Queue the script for the next unscripted iteration.
+    pub fn with_iteration(self, steps: Vec<FakeStep>) -> Self {
+        self.push_iteration(steps);
+        self
+    }
+
+    fn push_iteration(&self, steps: Vec<FakeStep>) {
+        lock(&self.iterations).push_back(steps);
+    }
+
+    /// Prompts received so far, in order.
+    pub fn prompts(&self) -> Vec<String> {
+        lock(&self.prompts).clone()
+    }
+
+    /// Scripted iterations not yet run.
+    pub fn remaining_iterations(&self) -> usize {
+        lock(&self.iterations).len()
+    }
+
+    /// A factory handing out clones of this backend, for
+    /// `SessionManager::with_backend_factory`. Each clone works in the
+    /// session's working directory, which may be a worktree.
+    pub fn factory(&self) -> BackendFactory {
+        let backend = self.clone();
+        Arc::new(move |session: &Session| {
+            let mut backend = backend.clone();
+            backend.project_path = session.work_dir().to_string();
+            Box::new(backend) as Box<dyn AgentBackend>
+        })
+    }
+
+    fn next_iteration(&self, prompt: &str) -> Vec<FakeStep> {
+        lock(&self.prompts).push(prompt.to_string());
+        lock(&self.iterations).pop_front().unwrap_or_default()
+    }
+
+    fn render(&self, steps: &[FakeStep]) -> String {
+        let mut script = String::new();
+        for step in steps {
+            script.push_str(&self.render_step(step));
+            script.push('\n');
+        }
+        script
+    }
+
+    fn render_step(&self, step: &FakeStep) -> String {
+        match step {
+            FakeStep::Say { text } => emit(&CommandEvent::Assistant { text: text.clone() }),
+            FakeStep::Write { path, content } => render_write(path, content),
+            FakeStep::Shell { command } => render_shell(command),
+            FakeStep::Commit { message } => {
+                render_shell(&format!("git add -A && git commit -q -m {}", quote(message)))
+            }
+            FakeStep::MarkPassing { story_id } => match self.mark_passing(story_id) {
+                Ok(json) => render_write("prd.json", &json),
+                Err(e) => emit(&CommandEvent::Error {
+                    message: e.to_string(),
+                }),
+            },
+            FakeStep::Usage(usage) => emit(&CommandEvent::Usage(usage.clone())),
+            FakeStep::Error { message } => emit(&CommandEvent::Error {
+                message: message.clone(),
+            }),
+            // Sleep in the background so a signal to the shell ends the hang
+            // without leaving a child holding stdout open.
+            FakeStep::Hang { secs } => format!(
+                "sleep {} >/dev/null 2>&1 & pid=$!; trap 'kill $pid 2>/dev/null; exit 143' TERM INT; wait $pid; trap - TERM INT",
+                secs
+            ),
+            FakeStep::Exit { code } => format!("exit {}", code),
+            FakeStep::EndIteration => "exit 0".to_string(),
+        }
+    }
+
+    /// `prd.json` with `story_id` marked as passing, as read when the
+    /// iteration starts. Only `passes` is flipped, the way an agent does it.
+    fn mark_passing(&self, story_id: &str) -> Result<String, RalphError> {
+        let path = Path::new(&self.project_path).join("prd.json");
+        let content = std::fs::read_to_string(&path)?;
+        let mut prd: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| RalphError::Parse(format!("Invalid prd.json: {}", e)))?;
+        let story = prd["stories"]
+            .as_array_mut()
+            .and_then(|stories| stories.iter_mut().find(|s| s["id"] == story_id))
+            .ok_or_else(|| RalphError::InvalidState(format!("Story {} not found in PRD", story_id)))?;
+        story["passes"] = serde_json::Value::Bool(true);
+        serde_json::to_string_pretty(&prd).map_err(|e| RalphError::Parse(e.to_string()))
+    }
+}
+
+impl AgentBackend for FakeAgentBackend {
+    fn name(&self) -> &str {
+        "fake-agent"
+    }
+
+    fn models(&self) -> Vec<AgentModel> {
+        vec![AgentModel::new("scripted", "Scripted transcript")]
+    }
+
+    fn iteration_command(&self, prompt: &str) -> Command {
+        let steps = self.next_iteration(prompt);
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(self.render(&steps));
+        command
+    }
+
+    fn chat_command(&self, prompt: &str) -> Command {
+        // Chat answers are whatever the next iteration says, as plain text.
+        let said: Vec<String> = self
+            .next_iteration(prompt)
+            .into_iter()
+            .filter_map(|step| match step {
+                FakeStep::Say { text } => Some(format!("printf '%s\\n' {}", quote(&text))),
+                _ => None,
+            })
+            .collect();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(said.join("\n"));
+        command
+    }
+
+    fn output_parser(&self) -> Box<dyn OutputParser> {
+        Box::new(CommandOutputParser)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Single-quote `text` for `sh`.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+fn emit(event: &CommandEvent) -> String {
+    let json = serde_json::to_string(event).unwrap_or_default();
+    format!("printf '%s\\n' {}", quote(&json))
+}
+
+fn render_write(path: &str, content: &str) -> String {
+    let dir = Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    format!(
+        "mkdir -p {} && printf '%s' {} > {}\n{}",
+        quote(&dir),
+        quote(content),
+        quote(path),
+        emit(&CommandEvent::Write {
+            path: path.to_string(),
+            lines: content.lines().count() as u32,
+            bytes: content.len() as u32,
+        })
+    )
+}
+
+/// Run `command`, then report it with its real exit code and output size.
+fn render_shell(command: &str) -> String {
+    let prefix = serde_json::to_string(command).unwrap_or_default();
+    format!(
+        "out=$( {{ {} ; }} 2>&1 ); code=$?\nprintf '%s%s%s%s}}\\n' {} \"$code\" ',\"output_bytes\":' \"${{#out}}\"",
+        command,
+        quote(&format!("{{\"type\":\"shell\",\"command\":{},\"exit_code\":", prefix))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{AgentControl, AgentRunner};
+
+    #[tokio::test]
+    async fn test_fake_agent_replays_transcript() {
+        let dir = std::env::temp_dir().join(format!("ralph-fake-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let project_path = dir.to_string_lossy().to_string();
+
+        let transcript = r#"{"step":"write","path":"src/hello.txt","content":"hi\nthere\n"}
+{"step":"shell","command":"cat src/hello.txt"}
+{"step":"shell","command":"exit 3"}
+{"step":"say","text":"it's done <ralph>COMPLETE</ralph>"}
+{"step":"end_iteration"}
+{"step":"say","text":"second"}
+"#;
+        let fake = FakeAgentBackend::from_transcript(project_path.clone(), transcript).unwrap();
+        assert_eq!(fake.remaining_iterations(), 2);
+
+        let runner = AgentRunner::new(project_path, Box::new(fake.clone()));
+        let (_tx, rx) = tokio::sync::watch::channel(None);
+        let mut kinds = Vec::new();
+        runner
+            .run_iteration("prompt one", rx, |entry| {
+                kinds.push(entry.kind);
+                AgentControl::Continue
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ActivityKind::Write {
+                    path: "src/hello.txt".into(),
+                    lines: 2,
+                    bytes: 9,
+                },
+                ActivityKind::Shell {
+                    command: "cat src/hello.txt".into(),
+                    exit_code: 0,
+                    output_bytes: 8,
+                },
+                ActivityKind::Shell {
+                    command: "exit 3".into(),
+                    exit_code: 3,
+                    output_bytes: 0,
+                },
+                ActivityKind::Assistant("it's done <ralph>COMPLETE</ralph>".into()),
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(dir.join("src/hello.txt")).unwrap(), "hi\nthere\n");
+        assert_eq!(fake.prompts(), vec!["prompt one".to_string()]);
+        assert_eq!(fake.remaining_iterations(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::forge::{run_cli, ForgeFactory, ForgeFuture, ForgeProvider, NewPullRequest, PullRequestUpdate};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A pull request as stored by `FakeForge`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FakePullRequest {
+    pub number: u64,
+    pub head: String,
+    pub base: Option<String>,
+    pub title: String,
+    pub body: String,
+    pub state: PrState,
+    pub comments: Vec<String>,
+}
+
+/// Forge that keeps pull requests as JSON files under a directory, for tests.
+///
+/// Each pull request is `pulls/<number>.json`. When bound to a project
+/// checkout, opening a pull request fails unless its head branch has been
+/// pushed to `origin`, like a real forge; pair it with a local bare repository
+/// as the remote to test push and PR flows offline.
+#[derive(Clone)]
+pub struct FakeForge {
+    dir: PathBuf,
+    project_path: Option<String>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl FakeForge {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            project_path: None,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Check head branches against the `origin` of `project_path`.
+    pub fn with_project(mut self, project_path: String) -> Self {
+        self.project_path = Some(project_path);
+        self
+    }
+
+    /// Build this forge for every session, bound to the session's checkout.
+    /// All of them share the same pull requests.
+    pub fn factory(&self) -> ForgeFactory {
+        let forge = self.clone();
+        Arc::new(move |session: &Session| {
+            Box::new(forge.clone().with_project(session.work_dir().to_string())) as Box<dyn ForgeProvider>
+        })
+    }
+
+    /// Every stored pull request, by number.
+    pub fn pulls(&self) -> Vec<FakePullRequest> {
+        let mut pulls: Vec<FakePullRequest> = std::fs::read_dir(self.pulls_dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect();
+        pulls.sort_by_key(|pr| pr.number);
+        pulls
+    }
+
+    /// Mark a pull request merged, as if a reviewer had merged it.
+    pub fn merge(&self, number: u64) -> Result<(), RalphError> {
+        self.modify(number, |pr| pr.state = PrState::Merged).map(|_| ())
+    }
+
+    /// Mark a pull request closed without merging.
+    pub fn close(&self, number: u64) -> Result<(), RalphError> {
+        self.modify(number, |pr| pr.state = PrState::Closed).map(|_| ())
+    }
+
+    fn pulls_dir(&self) -> PathBuf {
+        self.dir.join("pulls")
+    }
+
+    fn path(&self, number: u64) -> PathBuf {
+        self.pulls_dir().join(format!("{}.json", number))
+    }
+
+    fn url(&self, number: u64) -> String {
+        format!("file://{}", self.path(number).display())
+    }
+
+    fn read(&self, number: u64) -> Result<FakePullRequest, RalphError> {
+        let json = std::fs::read_to_string(self.path(number))
+            .map_err(|_| RalphError::Forge(format!("No pull request #{}", number)))?;
+        serde_json::from_str(&json).map_err(|e| RalphError::Forge(format!("Corrupt pull request #{}: {}", number, e)))
+    }
+
+    fn write(&self, pr: &FakePullRequest) -> Result<(), RalphError> {
+        std::fs::create_dir_all(self.pulls_dir())?;
+        let json = serde_json::to_string_pretty(pr)
+            .map_err(|e| RalphError::Forge(format!("Failed to serialize pull request: {}", e)))?;
+        write_atomic(&self.path(pr.number), &json)
+    }
+
+    fn modify(
+        &self,
+        number: u64,
+        change: impl FnOnce(&mut FakePullRequest),
+    ) -> Result<FakePullRequest, RalphError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pr = self.read(number)?;
+        change(&mut pr);
+        self.write(&pr)?;
+        Ok(pr)
+    }
+
+    fn summary(&self, pr: &FakePullRequest) -> PullRequest {
+        PullRequest {
+            number: pr.number,
+            url: self.url(pr.number),
+            title: pr.title.clone(),
+            state: pr.state,
+        }
+    }
+
+    /// Fail unless `branch` exists on the project's `origin`.
+    async fn check_pushed(&self, branch: &str) -> Result<(), RalphError> {
+        let Some(project_path) = &self.project_path else {
+            return Ok(());
+        };
+        run_cli("git", &["ls-remote", "--exit-code", "--heads", "origin", branch], project_path)
+            .await
+            .map(|_| ())
+            .map_err(|_| RalphError::Forge(format!("Branch {} has not been pushed to origin", branch)))
+    }
+}
+
+impl ForgeProvider for FakeForge {
+    fn name(&self) -> &str {
+        "Fake"
+    }
+
+    fn create_pr<'a>(&'a self, pr: &'a NewPullRequest) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            self.check_pushed(&pr.head).await?;
+
+            let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+            let pulls = self.pulls();
+            if pulls.iter().any(|p| p.head == pr.head && p.state == PrState::Open) {
+                return Err(RalphError::Forge(format!(
+                    "A pull request for {} is already open",
+                    pr.head
+                )));
+            }
+
+            let created = FakePullRequest {
+                number: pulls.last().map_or(1, |p| p.number + 1),
+                head: pr.head.clone(),
+                base: pr.base.clone(),
+                title: pr.title.clone(),
+                body: pr.body.clone(),
+                state: PrState::Open,
+                comments: Vec::new(),
+            };
+            self.write(&created)?;
+            Ok(self.summary(&created))
+        })
+    }
+
+    fn update_pr<'a>(&'a self, number: u64, update: &'a PullRequestUpdate) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            let pr = self.modify(number, |pr| {
+                if let Some(title) = &update.title {
+                    pr.title = title.clone();
+                }
+                if let Some(body) = &update.body {
+                    pr.body = body.clone();
+                }
+            })?;
+            Ok(self.summary(&pr))
+        })
+    }
+
+    fn pr_status(&self, number: u64) -> ForgeFuture<'_, PullRequest> {
+        Box::pin(async move { Ok(self.summary(&self.read(number)?)) })
+    }
+
+    fn comment<'a>(&'a self, number: u64, body: &'a str) -> ForgeFuture<'a, ()> {
+        Box::pin(async move { self.modify(number, |pr| pr.comments.push(body.to_string())).map(|_| ()) })
+    }
+}
+
+fn write_atomic(path: &Path, contents: &str) -> Result<(), RalphError> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ralph-fake-forge-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_lifecycle() {
+        let dir = temp_dir("lifecycle");
+        let forge = FakeForge::new(&dir);
+
+        let new = NewPullRequest {
+            head: "ralph/auth".to_string(),
+            base: Some("main".to_string()),
+            title: "Auth".to_string(),
+            body: "First draft".to_string(),
+        };
+        let pr = forge.create_pr(&new).await.unwrap();
+        assert_eq!(pr.number, 1);
+        assert_eq!(pr.state, PrState::Open);
+        assert!(forge.create_pr(&new).await.is_err(), "one open PR per head branch");
+
+        let update = PullRequestUpdate {
+            title: None,
+            body: Some("Second draft".to_string()),
+        };
+        assert_eq!(forge.update_pr(1, &update).await.unwrap().title, "Auth");
+        forge.comment(1, "Looks good").await.unwrap();
+        forge.merge(1).unwrap();
+
+        assert_eq!(forge.pr_status(1).await.unwrap().state, PrState::Merged);
+        let stored = &forge.pulls()[0];
+        assert_eq!(stored.body, "Second draft");
+        assert_eq!(stored.comments, vec!["Looks good"]);
+        assert!(forge.pr_status(2).await.is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_create_requires_pushed_branch() {
+        let dir = temp_dir("unpushed");
+        let run = |args: &[&str]| {
+            let status = std::process::Command::new("git").args(args).current_dir(&dir).output().unwrap();
+            assert!(status.status.success(), "git {:?} failed", args);
+        };
+        run(&["init", "--quiet", "--bare", "origin.git"]);
+        run(&["init", "--quiet", "work"]);
+
+        let work = dir.join("work");
+        let forge = FakeForge::new(dir.join("forge")).with_project(work.to_string_lossy().to_string());
+        let new = NewPullRequest {
+            head: "ralph/auth".to_string(),
+            base: None,
+            title: "Auth".to_string(),
+            body: String::new(),
+        };
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(["-c", "user.name=Ralph", "-c", "user.email=ralph@example.com"])
+                .args(args)
+                .current_dir(&work)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+        };
+        git(&["remote", "add", "origin", "../origin.git"]);
+        git(&["checkout", "--quiet", "-b", "ralph/auth"]);
+        git(&["commit", "--quiet", "--allow-empty", "-m", "init"]);
+
+        assert!(forge.create_pr(&new).await.is_err());
+        git(&["push", "--quiet", "origin", "ralph/auth"]);
+        assert_eq!(forge.create_pr(&new).await.unwrap().number, 1);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
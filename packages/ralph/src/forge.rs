@@ -0,0 +1,176 @@
+use crate::gitea::GiteaForge;
+use crate::github::GitHubForge;
+use crate::gitlab::GitLabForge;
+use crate::types::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Future returned by `ForgeProvider` methods.
+pub type ForgeFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, RalphError>> + Send + 'a>>;
+
+/// A git forge that hosts pull requests for a project's `origin`.
+///
+/// A provider is bound to one project directory; branches must already be
+/// pushed before a pull request can be opened for them.
+pub trait ForgeProvider: Send + Sync {
+    /// Short name used in logs and errors.
+    fn name(&self) -> &str;
+
+    /// Open a pull request.
+    fn create_pr<'a>(&'a self, pr: &'a NewPullRequest) -> ForgeFuture<'a, PullRequest>;
+
+    /// Change an open pull request's title and/or body.
+    fn update_pr<'a>(&'a self, number: u64, update: &'a PullRequestUpdate) -> ForgeFuture<'a, PullRequest>;
+
+    /// Current state of a pull request.
+    fn pr_status(&self, number: u64) -> ForgeFuture<'_, PullRequest>;
+
+    /// Post a comment on a pull request.
+    fn comment<'a>(&'a self, number: u64, body: &'a str) -> ForgeFuture<'a, ()>;
+}
+
+/// A pull request to open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPullRequest {
+    /// Branch with the changes.
+    pub head: String,
+    /// Branch to merge into; the forge's default branch if `None`.
+    pub base: Option<String>,
+    pub title: String,
+    pub body: String,
+}
+
+/// Fields of a pull request to change; `None` leaves a field as it is.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PullRequestUpdate {
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Builds the forge for a session, overriding the one selected in its config.
+pub type ForgeFactory = Arc<dyn Fn(&Session) -> Box<dyn ForgeProvider> + Send + Sync>;
+
+/// Build the forge selected in a session's config.
+pub fn forge_for(kind: &ForgeKind, project_path: String) -> Box<dyn ForgeProvider> {
+    match kind {
+        ForgeKind::GitHub => Box::new(GitHubForge::new(project_path)),
+        ForgeKind::GitLab => Box::new(GitLabForge::new(project_path)),
+        ForgeKind::Gitea { url } => Box::new(GiteaForge::new(project_path, url.clone())),
+    }
+}
+
+/// Run a forge CLI in `project_path` and return its stdout.
+pub(crate) async fn run_cli(program: &str, args: &[&str], project_path: &str) -> Result<String, RalphError> {
+    run_cli_with_input(program, args, project_path, None).await
+}
+
+/// Run a forge CLI in `project_path` with `input` on its stdin, for what
+/// shouldn't show up in its arguments, and return its stdout.
+pub(crate) async fn run_cli_with_input(
+    program: &str,
+    args: &[&str],
+    project_path: &str,
+    input: Option<&str>,
+) -> Result<String, RalphError> {
+    tracing::debug!("🔧 {} {} (in {})", program, args.first().unwrap_or(&""), project_path);
+
+    let failed_to_run = |e: std::io::Error| RalphError::Forge(format!("Failed to run {}: {}", program, e));
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(project_path)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(failed_to_run)?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes()).await.map_err(failed_to_run)?;
+    }
+    let output = child.wait_with_output().await.map_err(failed_to_run)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(RalphError::Forge(format!(
+            "{} {} failed: {}",
+            program,
+            args.iter().take(2).copied().collect::<Vec<_>>().join(" "),
+            if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() }
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse JSON printed by a forge CLI or API.
+pub(crate) fn parse_json(source: &str, json: &str) -> Result<serde_json::Value, RalphError> {
+    serde_json::from_str(json).map_err(|e| RalphError::Forge(format!("Invalid JSON from {}: {}", source, e)))
+}
+
+/// Number at the end of a pull request URL, e.g. `.../pull/42`.
+pub(crate) fn number_from_url(url: &str) -> Result<u64, RalphError> {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .and_then(|last| last.parse().ok())
+        .ok_or_else(|| RalphError::Forge(format!("No pull request number in {}", url)))
+}
+
+/// `owner/name` of a repository from its remote URL, for HTTPS, SSH and
+/// scp-style (`git@host:owner/name.git`) remotes.
+pub(crate) fn repo_slug(remote_url: &str) -> Option<(String, String)> {
+    let url = remote_url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+
+    let mut parts = path.rsplitn(2, '/');
+    let name = parts.next()?;
+    let owner = parts.next()?.rsplit('/').next()?;
+    if owner.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some((owner.to_string(), name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forge_for_each_kind() {
+        let kinds = vec![
+            ForgeKind::GitHub,
+            ForgeKind::GitLab,
+            ForgeKind::Gitea {
+                url: "https://gitea.example.com".to_string(),
+            },
+        ];
+        for kind in kinds {
+            let forge = forge_for(&kind, "/tmp/project".to_string());
+            assert!(!forge.name().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_number_from_url() {
+        assert_eq!(number_from_url("https://github.com/o/r/pull/42\n".trim()).unwrap(), 42);
+        assert_eq!(number_from_url("https://gitlab.com/o/r/-/merge_requests/7/").unwrap(), 7);
+        assert!(number_from_url("https://github.com/o/r").is_err());
+    }
+
+    #[test]
+    fn test_repo_slug() {
+        let expected = Some(("acme".to_string(), "widgets".to_string()));
+        assert_eq!(repo_slug("https://gitea.example.com/acme/widgets.git"), expected);
+        assert_eq!(repo_slug("git@gitea.example.com:acme/widgets.git"), expected);
+        assert_eq!(repo_slug("ssh://git@gitea.example.com:2222/acme/widgets"), expected);
+        assert_eq!(repo_slug("/srv/git/widgets.git"), None);
+    }
+}
@@ -109,7 +109,9 @@ impl GitOperations {
         Ok(())
     }
 
-    pub async fn create_branch(&self, branch_name: &str) -> Result<(), RalphError> {
+    /// Check out `branch_name`, creating it from `base` (or the current
+    /// `HEAD`) if it doesn't exist yet.
+    pub async fn create_branch(&self, branch_name: &str, base: Option<&str>) -> Result<(), RalphError> {
         tracing::info!("🌿 Creating/checking out branch: {}", branch_name);
         // Check if branch exists
         let output = self
@@ -119,8 +121,9 @@ impl GitOperations {
         if output.trim().is_empty() {
             // Branch doesn't exist, create it
             tracing::debug!("   Branch doesn't exist, creating new branch");
-            self.run_git_command(&["checkout", "-b", branch_name])
-                .await?;
+            let mut args = vec!["checkout", "-b", branch_name];
+            args.extend(base);
+            self.run_git_command(&args).await?;
             tracing::info!("✓ Created and checked out new branch {}", branch_name);
         } else {
             // Branch exists, checkout
@@ -132,6 +135,46 @@ impl GitOperations {
         Ok(())
     }
 
+    /// Check out `branch` in a new worktree at `path`, creating the branch from
+    /// `base` (or `HEAD`) if it doesn't exist yet.
+    pub async fn add_worktree(
+        &self,
+        path: &Path,
+        branch: &str,
+        base: Option<&str>,
+    ) -> Result<(), RalphError> {
+        tracing::info!("🌳 Adding worktree for {} at {:?}", branch, path);
+        let path = path.to_string_lossy();
+
+        // Forget worktrees whose directories were deleted behind git's back.
+        self.run_git_command(&["worktree", "prune"]).await?;
+
+        let existing = self
+            .run_git_command(&["branch", "--list", branch])
+            .await?;
+        if existing.trim().is_empty() {
+            let mut args = vec!["worktree", "add", "-b", branch, &path];
+            args.extend(base);
+            self.run_git_command(&args).await?;
+        } else {
+            self.run_git_command(&["worktree", "add", &path, branch])
+                .await?;
+        }
+
+        tracing::info!("✓ Worktree ready at {}", path);
+        Ok(())
+    }
+
+    /// Remove the worktree at `path`. Its branch and commits are kept.
+    pub async fn remove_worktree(&self, path: &Path) -> Result<(), RalphError> {
+        tracing::info!("🧹 Removing worktree at {:?}", path);
+        let path = path.to_string_lossy();
+        self.run_git_command(&["worktree", "remove", "--force", &path])
+            .await?;
+        tracing::info!("✓ Worktree removed");
+        Ok(())
+    }
+
     pub async fn push(&self, branch: Option<&str>) -> Result<(), RalphError> {
         if let Some(b) = branch {
             tracing::info!("⬆️  Pushing branch {} to origin...", b);
@@ -150,41 +193,126 @@ impl GitOperations {
         Ok(())
     }
 
-    pub async fn create_pr(
-        &self,
-        branch: &str,
-        title: &str,
-        body: &str,
-    ) -> Result<String, RalphError> {
-        tracing::info!("📝 Creating PR for branch {}", branch);
-        tracing::info!("   Title: {}", title);
-        // Use gh CLI to create PR
-        let output = Command::new("gh")
-            .args(&[
-                "pr", "create", "--head", branch, "--title", title, "--body", body,
-            ])
+    pub async fn get_current_branch(&self) -> Result<String, RalphError> {
+        let output = self.run_git_command(&["branch", "--show-current"]).await?;
+        Ok(output.trim().to_string())
+    }
+
+    pub async fn has_changes(&self) -> Result<bool, RalphError> {
+        let output = self.run_git_command(&["status", "--porcelain"]).await?;
+        Ok(!output.trim().is_empty())
+    }
+
+    /// Paths with uncommitted changes, untracked files included, outside the
+    /// `excluded` paths. A rename lists both its old and new path.
+    pub async fn uncommitted_changes(&self, excluded: &[&str]) -> Result<Vec<String>, RalphError> {
+        let pathspecs: Vec<String> = excluded
+            .iter()
+            .map(|path| format!(":(exclude){}", path))
+            .collect();
+        let mut args = vec!["status", "--porcelain", "-z", "--untracked-files=all", "--", "."];
+        args.extend(pathspecs.iter().map(String::as_str));
+        let output = self.run_git_command(&args).await?;
+
+        // Entries are `XY path`, NUL-terminated; renames and copies are
+        // followed by their source path as a separate entry.
+        let mut paths = Vec::new();
+        let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+        while let Some(entry) = entries.next() {
+            let (status, path) = entry.split_at(entry.len().min(3));
+            paths.push(path.to_string());
+            if status.starts_with('R') || status.starts_with('C') {
+                paths.extend(entries.next().map(String::from));
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Stash the uncommitted changes to `paths`, untracked files included.
+    pub async fn stash(&self, message: &str, paths: &[String]) -> Result<(), RalphError> {
+        tracing::info!("📦 Stashing changes: {}", message);
+        let mut args = vec!["stash", "push", "--include-untracked", "-m", message, "--"];
+        args.extend(paths.iter().map(String::as_str));
+        self.run_git_command(&args).await?;
+        tracing::info!("✓ Changes stashed");
+        Ok(())
+    }
+
+    /// Reset the checked-out branch, index and working tree to `commit`.
+    pub async fn reset_hard(&self, commit: &str) -> Result<(), RalphError> {
+        tracing::info!("⏪ Resetting to {}", commit);
+        self.run_git_command(&["reset", "--hard", "-q", commit]).await?;
+        Ok(())
+    }
+
+    /// Delete untracked files, leaving ignored ones and `excluded` paths.
+    pub async fn clean(&self, excluded: &[&str]) -> Result<(), RalphError> {
+        let mut args = vec!["clean", "-fdq"];
+        for path in excluded {
+            args.extend(["-e", path]);
+        }
+        self.run_git_command(&args).await?;
+        Ok(())
+    }
+
+    /// Point `branch` at `commit`, creating it if needed. Git refuses to move
+    /// a branch that is checked out.
+    pub async fn force_branch(&self, branch: &str, commit: &str) -> Result<(), RalphError> {
+        tracing::info!("🌿 Pointing {} at {}", branch, commit);
+        self.run_git_command(&["branch", "-f", branch, commit]).await?;
+        Ok(())
+    }
+
+    /// Whether `branch` exists locally.
+    pub async fn branch_exists(&self, branch: &str) -> Result<bool, RalphError> {
+        let refname = format!("refs/heads/{}", branch);
+        let output = Command::new("git")
+            .args(["show-ref", "--verify", "-q", &refname])
             .current_dir(&self.project_path)
             .output()
             .await
-            .map_err(|e| RalphError::Git(format!("Failed to create PR: {}", e)))?;
+            .map_err(|e| RalphError::Git(format!("Failed to run git command: {}", e)))?;
+        Ok(output.status.success())
+    }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(RalphError::Git(format!("gh pr create failed: {}", stderr)));
-        }
+    /// Full hash of `rev`.
+    pub async fn rev_parse(&self, rev: &str) -> Result<String, RalphError> {
+        let output = self.run_git_command(&["rev-parse", "--verify", rev]).await?;
+        Ok(output.trim().to_string())
+    }
 
-        let pr_url = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        Ok(pr_url)
+    /// Commits on `HEAD` since `base`, oldest first.
+    pub async fn commits_since(&self, base: &str) -> Result<Vec<CommitSummary>, RalphError> {
+        let range = format!("{}..HEAD", base);
+        let output = self
+            .run_git_command(&["log", "--reverse", "--format=%h%x09%s", &range])
+            .await?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(hash, subject)| CommitSummary {
+                hash: hash.to_string(),
+                subject: subject.to_string(),
+            })
+            .collect())
     }
 
-    pub async fn get_current_branch(&self) -> Result<String, RalphError> {
-        let output = self.run_git_command(&["branch", "--show-current"]).await?;
+    /// Whether a remote called `name` is configured.
+    pub async fn has_remote(&self, name: &str) -> Result<bool, RalphError> {
+        let output = self.run_git_command(&["remote"]).await?;
+        Ok(output.lines().any(|remote| remote.trim() == name))
+    }
+
+    /// Fetch URL of a remote.
+    pub async fn remote_url(&self, name: &str) -> Result<String, RalphError> {
+        let output = self.run_git_command(&["remote", "get-url", name]).await?;
         Ok(output.trim().to_string())
     }
 
-    pub async fn has_changes(&self) -> Result<bool, RalphError> {
-        let output = self.run_git_command(&["status", "--porcelain"]).await?;
-        Ok(!output.trim().is_empty())
+    /// Best common ancestor of two commits.
+    pub async fn merge_base(&self, a: &str, b: &str) -> Result<String, RalphError> {
+        let output = self.run_git_command(&["merge-base", a, b]).await?;
+        Ok(output.trim().to_string())
     }
 
     pub async fn get_last_commit_message(&self) -> Result<String, RalphError> {
@@ -192,6 +320,55 @@ impl GitOperations {
         Ok(output.trim().to_string())
     }
 
+    /// One-line summaries of the most recent `count` commits, newest first.
+    pub async fn recent_commits(&self, count: usize) -> Result<Vec<String>, RalphError> {
+        let count = format!("-{}", count);
+        let output = self
+            .run_git_command(&["log", &count, "--oneline", "--no-decorate"])
+            .await?;
+        Ok(output.lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Changes from `from` to `to`, one entry per file. Renames show up as a
+    /// deletion and an addition.
+    pub async fn diff(&self, from: &str, to: &str) -> Result<Diff, RalphError> {
+        let range = format!("{}..{}", from, to);
+        let numstat = self
+            .run_git_command(&["diff", "--no-renames", "--no-ext-diff", "--numstat", "-z", &range])
+            .await?;
+        let patch = self
+            .run_git_command(&["diff", "--no-renames", "--no-ext-diff", "--no-color", &range])
+            .await?;
+        Ok(Diff {
+            from: from.to_string(),
+            to: to.to_string(),
+            files: parse_diff(&numstat, &patch),
+        })
+    }
+
+    /// Files changed by `commit`, as `git show --stat` prints them.
+    pub async fn show_stat(&self, commit: &str) -> Result<String, RalphError> {
+        self.run_git_command(&["show", "--stat", "--format=", commit])
+            .await
+    }
+
+    /// Commit `HEAD` points at, or `None` in a repository with no commits yet.
+    pub async fn head_commit(&self) -> Result<Option<String>, RalphError> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--verify", "-q", "HEAD"])
+            .current_dir(&self.project_path)
+            .output()
+            .await
+            .map_err(|e| RalphError::Git(format!("Failed to run git command: {}", e)))?;
+
+        let head = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !head.is_empty() {
+            Ok(Some(head))
+        } else {
+            Ok(None)
+        }
+    }
+
     async fn run_git_command(&self, args: &[&str]) -> Result<String, RalphError> {
         tracing::debug!("🔧 Git: {} (in {})", args.join(" "), self.project_path);
         
@@ -248,6 +425,40 @@ impl GitOperations {
     }
 }
 
+/// Pair `git diff --numstat -z` records with the per-file sections of the
+/// matching patch; both list files in the same order.
+fn parse_diff(numstat: &str, patch: &str) -> Vec<FileDiff> {
+    let mut sections = Vec::new();
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("diff --git ") || sections.is_empty() {
+            sections.push(String::new());
+        }
+        if let Some(section) = sections.last_mut() {
+            section.push_str(line);
+        }
+    }
+    let mut sections = sections.into_iter();
+
+    numstat
+        .split('\0')
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            let mut fields = record.splitn(3, '\t');
+            let additions = fields.next()?;
+            let deletions = fields.next()?;
+            let path = fields.next()?.trim_start_matches('\n');
+            let binary = additions == "-";
+            Some(FileDiff {
+                path: path.to_string(),
+                additions: additions.parse().unwrap_or(0),
+                deletions: deletions.parse().unwrap_or(0),
+                binary,
+                patch: sections.next().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,4 +468,27 @@ mod tests {
         let git = GitOperations::new("/tmp/test-project".to_string());
         assert_eq!(git.project_path, "/tmp/test-project");
     }
+
+    #[test]
+    fn test_parse_diff_splits_files() {
+        let numstat = "2\t1\tsrc/a b.rs\0-\t-\tlogo.png\0";
+        let patch = "diff --git a/src/a b.rs b/src/a b.rs
+--- a/src/a b.rs
++++ b/src/a b.rs
+@@ -1 +1,2 @@
+-old
++new
++more
+diff --git a/logo.png b/logo.png
+Binary files a/logo.png and b/logo.png differ
+";
+        let files = parse_diff(numstat, patch);
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/a b.rs");
+        assert_eq!((files[0].additions, files[0].deletions), (2, 1));
+        assert!(files[0].patch.ends_with("+more\n"));
+        assert!(files[1].binary);
+        assert!(files[1].patch.starts_with("diff --git a/logo.png"));
+    }
 }
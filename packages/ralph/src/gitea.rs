@@ -0,0 +1,194 @@
+use crate::forge::{parse_json, repo_slug, run_cli_with_input, ForgeFuture, ForgeProvider, NewPullRequest, PullRequestUpdate};
+use crate::git::GitOperations;
+use crate::types::*;
+use serde_json::json;
+
+/// Gitea pull requests through its REST API.
+///
+/// Requests go through `curl` and authenticate with the `GITEA_TOKEN`
+/// environment variable. The repository is taken from the `origin` remote.
+pub struct GiteaForge {
+    project_path: String,
+    url: String,
+}
+
+impl GiteaForge {
+    pub fn new(project_path: String, url: String) -> Self {
+        Self {
+            project_path,
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// `{url}/api/v1/repos/{owner}/{name}` for the `origin` remote.
+    async fn repo_api(&self) -> Result<String, RalphError> {
+        let remote = GitOperations::new(self.project_path.clone()).remote_url("origin").await?;
+        let (owner, name) = repo_slug(&remote)
+            .ok_or_else(|| RalphError::Forge(format!("Can't tell the Gitea repository from {}", remote)))?;
+        Ok(format!("{}/api/v1/repos/{}/{}", self.url, owner, name))
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, RalphError> {
+        let token = std::env::var("GITEA_TOKEN")
+            .map_err(|_| RalphError::Forge("GITEA_TOKEN is not set".to_string()))?;
+        let body = body.map(|b| b.to_string());
+
+        let mut args = vec![
+            "--silent",
+            "--show-error",
+            "--fail-with-body",
+            "--config",
+            "-",
+            "--request",
+            method,
+            "--header",
+            "Accept: application/json",
+        ];
+        if body.is_some() {
+            args.extend(["--header", "Content-Type: application/json"]);
+        }
+        args.push(url);
+
+        let config = curl_config(&token, body.as_deref());
+        let output = run_cli_with_input("curl", &args, &self.project_path, Some(&config)).await?;
+        parse_json("Gitea", &output)
+    }
+}
+
+impl ForgeProvider for GiteaForge {
+    fn name(&self) -> &str {
+        "Gitea"
+    }
+
+    fn create_pr<'a>(&'a self, pr: &'a NewPullRequest) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            tracing::info!("📝 Creating Gitea PR for branch {}", pr.head);
+            let repo = self.repo_api().await?;
+            let base = match &pr.base {
+                Some(base) => base.clone(),
+                None => self.request("GET", &repo, None).await?["default_branch"]
+                    .as_str()
+                    .ok_or_else(|| RalphError::Forge("Gitea repository has no default branch".to_string()))?
+                    .to_string(),
+            };
+
+            let body = json!({ "head": pr.head, "base": base, "title": pr.title, "body": pr.body });
+            let created = parse_pull(&self.request("POST", &format!("{}/pulls", repo), Some(body)).await?)?;
+            tracing::info!("✓ Opened PR #{}: {}", created.number, created.url);
+            Ok(created)
+        })
+    }
+
+    fn update_pr<'a>(&'a self, number: u64, update: &'a PullRequestUpdate) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            let repo = self.repo_api().await?;
+            let mut body = json!({});
+            if let Some(title) = &update.title {
+                body["title"] = json!(title);
+            }
+            if let Some(text) = &update.body {
+                body["body"] = json!(text);
+            }
+
+            let updated = self
+                .request("PATCH", &format!("{}/pulls/{}", repo, number), Some(body))
+                .await?;
+            tracing::info!("✓ Updated PR #{}", number);
+            parse_pull(&updated)
+        })
+    }
+
+    fn pr_status(&self, number: u64) -> ForgeFuture<'_, PullRequest> {
+        Box::pin(async move {
+            let repo = self.repo_api().await?;
+            parse_pull(&self.request("GET", &format!("{}/pulls/{}", repo, number), None).await?)
+        })
+    }
+
+    fn comment<'a>(&'a self, number: u64, body: &'a str) -> ForgeFuture<'a, ()> {
+        Box::pin(async move {
+            let repo = self.repo_api().await?;
+            // Pull requests share their number space with issues in Gitea.
+            self.request(
+                "POST",
+                &format!("{}/issues/{}/comments", repo, number),
+                Some(json!({ "body": body })),
+            )
+            .await?;
+            tracing::info!("💬 Commented on PR #{}", number);
+            Ok(())
+        })
+    }
+}
+
+/// curl config for the token and any request body, passed on stdin so
+/// neither shows up in the process list.
+fn curl_config(token: &str, body: Option<&str>) -> String {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut config = format!("header = {}\n", quote(&format!("Authorization: token {}", token)));
+    if let Some(body) = body {
+        config.push_str(&format!("data = {}\n", quote(body)));
+    }
+    config
+}
+
+/// Parse a Gitea `PullRequest` object.
+fn parse_pull(value: &serde_json::Value) -> Result<PullRequest, RalphError> {
+    let state = match (value["state"].as_str(), value["merged"].as_bool()) {
+        (_, Some(true)) => PrState::Merged,
+        (Some("open"), _) => PrState::Open,
+        (Some("closed"), _) => PrState::Closed,
+        (other, _) => return Err(RalphError::Forge(format!("Unknown Gitea PR state: {:?}", other))),
+    };
+
+    Ok(PullRequest {
+        number: value["number"]
+            .as_u64()
+            .ok_or_else(|| RalphError::Forge("Gitea returned a pull request without a number".to_string()))?,
+        url: value["html_url"].as_str().unwrap_or_default().to_string(),
+        title: value["title"].as_str().unwrap_or_default().to_string(),
+        state,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pull() {
+        let merged = json!({
+            "number": 3,
+            "state": "closed",
+            "merged": true,
+            "title": "Auth: Login form",
+            "html_url": "https://gitea.example.com/acme/app/pulls/3",
+        });
+        let pr = parse_pull(&merged).unwrap();
+        assert_eq!(pr.number, 3);
+        assert_eq!(pr.state, PrState::Merged);
+        assert_eq!(pr.url, "https://gitea.example.com/acme/app/pulls/3");
+
+        let closed = json!({ "number": 4, "state": "closed", "merged": false });
+        assert_eq!(parse_pull(&closed).unwrap().state, PrState::Closed);
+    }
+
+    #[test]
+    fn test_curl_config_quotes_the_body() {
+        let body = json!({ "body": "Fixes \"login\"\nC:\\app" }).to_string();
+        assert_eq!(
+            curl_config("s3cret", Some(&body)),
+            concat!(
+                "header = \"Authorization: token s3cret\"\n",
+                r#"data = "{\"body\":\"Fixes \\\"login\\\"\\nC:\\\\app\"}""#,
+                "\n",
+            )
+        );
+        assert_eq!(curl_config("s3cret", None), "header = \"Authorization: token s3cret\"\n");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::forge::{number_from_url, parse_json, run_cli, ForgeFuture, ForgeProvider, NewPullRequest, PullRequestUpdate};
+use crate::types::*;
+
+/// GitHub pull requests through the `gh` CLI, which must be installed and
+/// logged in.
+pub struct GitHubForge {
+    project_path: String,
+}
+
+impl GitHubForge {
+    pub fn new(project_path: String) -> Self {
+        Self { project_path }
+    }
+
+    async fn gh(&self, args: &[&str]) -> Result<String, RalphError> {
+        run_cli("gh", args, &self.project_path).await
+    }
+}
+
+impl ForgeProvider for GitHubForge {
+    fn name(&self) -> &str {
+        "GitHub"
+    }
+
+    fn create_pr<'a>(&'a self, pr: &'a NewPullRequest) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            tracing::info!("📝 Creating GitHub PR for branch {}", pr.head);
+            let mut args = vec!["pr", "create", "--head", &pr.head, "--title", &pr.title, "--body", &pr.body];
+            if let Some(base) = &pr.base {
+                args.extend(["--base", base.as_str()]);
+            }
+
+            let url = self.gh(&args).await?.trim().to_string();
+            let number = number_from_url(&url)?;
+            tracing::info!("✓ Opened PR #{}: {}", number, url);
+            Ok(PullRequest {
+                number,
+                url,
+                title: pr.title.clone(),
+                state: PrState::Open,
+            })
+        })
+    }
+
+    fn update_pr<'a>(&'a self, number: u64, update: &'a PullRequestUpdate) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            let number_arg = number.to_string();
+            let mut args = vec!["pr", "edit", number_arg.as_str()];
+            if let Some(title) = &update.title {
+                args.extend(["--title", title.as_str()]);
+            }
+            if let Some(body) = &update.body {
+                args.extend(["--body", body.as_str()]);
+            }
+
+            self.gh(&args).await?;
+            tracing::info!("✓ Updated PR #{}", number);
+            self.pr_status(number).await
+        })
+    }
+
+    fn pr_status(&self, number: u64) -> ForgeFuture<'_, PullRequest> {
+        Box::pin(async move {
+            let number_arg = number.to_string();
+            let output = self
+                .gh(&["pr", "view", &number_arg, "--json", "number,url,title,state"])
+                .await?;
+            parse_pr_view(&output)
+        })
+    }
+
+    fn comment<'a>(&'a self, number: u64, body: &'a str) -> ForgeFuture<'a, ()> {
+        Box::pin(async move {
+            let number_arg = number.to_string();
+            self.gh(&["pr", "comment", &number_arg, "--body", body]).await?;
+            tracing::info!("💬 Commented on PR #{}", number);
+            Ok(())
+        })
+    }
+}
+
+/// Parse `gh pr view --json number,url,title,state`.
+fn parse_pr_view(json: &str) -> Result<PullRequest, RalphError> {
+    let value = parse_json("gh", json)?;
+    let state = match value["state"].as_str() {
+        Some("OPEN") => PrState::Open,
+        Some("CLOSED") => PrState::Closed,
+        Some("MERGED") => PrState::Merged,
+        other => return Err(RalphError::Forge(format!("Unknown GitHub PR state: {:?}", other))),
+    };
+
+    Ok(PullRequest {
+        number: value["number"]
+            .as_u64()
+            .ok_or_else(|| RalphError::Forge("gh pr view returned no number".to_string()))?,
+        url: value["url"].as_str().unwrap_or_default().to_string(),
+        title: value["title"].as_str().unwrap_or_default().to_string(),
+        state,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pr_view() {
+        let pr = parse_pr_view(
+            r#"{"number":12,"state":"MERGED","title":"Auth: Login form","url":"https://github.com/acme/app/pull/12"}"#,
+        )
+        .unwrap();
+        assert_eq!(pr.number, 12);
+        assert_eq!(pr.state, PrState::Merged);
+        assert_eq!(pr.url, "https://github.com/acme/app/pull/12");
+
+        assert!(parse_pr_view(r#"{"number":12,"state":"DRAFT"}"#).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::forge::{number_from_url, parse_json, run_cli, ForgeFuture, ForgeProvider, NewPullRequest, PullRequestUpdate};
+use crate::types::*;
+
+/// GitLab merge requests through the `glab` CLI, which must be installed and
+/// logged in.
+pub struct GitLabForge {
+    project_path: String,
+}
+
+impl GitLabForge {
+    pub fn new(project_path: String) -> Self {
+        Self { project_path }
+    }
+
+    async fn glab(&self, args: &[&str]) -> Result<String, RalphError> {
+        run_cli("glab", args, &self.project_path).await
+    }
+}
+
+impl ForgeProvider for GitLabForge {
+    fn name(&self) -> &str {
+        "GitLab"
+    }
+
+    fn create_pr<'a>(&'a self, pr: &'a NewPullRequest) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            tracing::info!("📝 Creating GitLab MR for branch {}", pr.head);
+            let mut args = vec![
+                "mr",
+                "create",
+                "--source-branch",
+                &pr.head,
+                "--title",
+                &pr.title,
+                "--description",
+                &pr.body,
+                "--yes",
+            ];
+            if let Some(base) = &pr.base {
+                args.extend(["--target-branch", base.as_str()]);
+            }
+
+            // glab prints progress before the MR URL; the URL is the last line.
+            let output = self.glab(&args).await?;
+            let url = output
+                .lines()
+                .map(str::trim)
+                .rfind(|line| line.starts_with("http"))
+                .ok_or_else(|| RalphError::Forge(format!("glab mr create printed no URL: {}", output.trim())))?
+                .to_string();
+            let number = number_from_url(&url)?;
+            tracing::info!("✓ Opened MR !{}: {}", number, url);
+            Ok(PullRequest {
+                number,
+                url,
+                title: pr.title.clone(),
+                state: PrState::Open,
+            })
+        })
+    }
+
+    fn update_pr<'a>(&'a self, number: u64, update: &'a PullRequestUpdate) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(async move {
+            let number_arg = number.to_string();
+            let mut args = vec!["mr", "update", number_arg.as_str()];
+            if let Some(title) = &update.title {
+                args.extend(["--title", title.as_str()]);
+            }
+            if let Some(body) = &update.body {
+                args.extend(["--description", body.as_str()]);
+            }
+
+            self.glab(&args).await?;
+            tracing::info!("✓ Updated MR !{}", number);
+            self.pr_status(number).await
+        })
+    }
+
+    fn pr_status(&self, number: u64) -> ForgeFuture<'_, PullRequest> {
+        Box::pin(async move {
+            let number_arg = number.to_string();
+            let output = self.glab(&["mr", "view", &number_arg, "--output", "json"]).await?;
+            parse_mr_view(&output)
+        })
+    }
+
+    fn comment<'a>(&'a self, number: u64, body: &'a str) -> ForgeFuture<'a, ()> {
+        Box::pin(async move {
+            let number_arg = number.to_string();
+            self.glab(&["mr", "note", &number_arg, "--message", body]).await?;
+            tracing::info!("💬 Commented on MR !{}", number);
+            Ok(())
+        })
+    }
+}
+
+/// Parse `glab mr view --output json`.
+fn parse_mr_view(json: &str) -> Result<PullRequest, RalphError> {
+    let value = parse_json("glab", json)?;
+    let state = match value["state"].as_str() {
+        Some("opened") => PrState::Open,
+        Some("closed") | Some("locked") => PrState::Closed,
+        Some("merged") => PrState::Merged,
+        other => return Err(RalphError::Forge(format!("Unknown GitLab MR state: {:?}", other))),
+    };
+
+    Ok(PullRequest {
+        number: value["iid"]
+            .as_u64()
+            .ok_or_else(|| RalphError::Forge("glab mr view returned no iid".to_string()))?,
+        url: value["web_url"].as_str().unwrap_or_default().to_string(),
+        title: value["title"].as_str().unwrap_or_default().to_string(),
+        state,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mr_view() {
+        let pr = parse_mr_view(
+            r#"{"id":9001,"iid":7,"state":"opened","title":"Auth: Login form","web_url":"https://gitlab.com/acme/app/-/merge_requests/7"}"#,
+        )
+        .unwrap();
+        assert_eq!(pr.number, 7);
+        assert_eq!(pr.state, PrState::Open);
+        assert_eq!(pr.url, "https://gitlab.com/acme/app/-/merge_requests/7");
+    }
+}
@@ -0,0 +1,599 @@
+use crate::types::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Watches the agent's activity for one way of getting stuck.
+///
+/// Detectors live for a whole run of the session loop. Ones that judge a
+/// single iteration forget what they saw in `end_iteration`. `observe` may
+/// block on files or git; the session loop calls it off the async runtime.
+pub trait GutterDetector: Send {
+    /// Name reported in `Signal::Gutter` when this detector fires.
+    fn rule(&self) -> &str;
+
+    /// Look at one activity; returns why the agent is stuck, if it is.
+    fn observe(&mut self, activity: &ActivityKind, at: SystemTime) -> Option<String>;
+
+    /// Called when an iteration ends, with whether it changed any files.
+    fn end_iteration(&mut self, _changed_files: bool) -> Option<String> {
+        None
+    }
+}
+
+/// Build the detector for `rule`. `work_dir` is where the agent edits files.
+pub fn detector_for(rule: &GutterRule, work_dir: &str) -> Box<dyn GutterDetector> {
+    match rule {
+        GutterRule::RepeatedCommandFailure { count } => Box::new(RepeatedCommandFailure {
+            rule: rule.clone(),
+            count: *count,
+            failures: HashMap::new(),
+        }),
+        GutterRule::FileThrashing { count, window_secs } => Box::new(FileThrashing {
+            rule: rule.clone(),
+            count: *count,
+            window: Duration::from_secs(*window_secs),
+            writes: HashMap::new(),
+        }),
+        GutterRule::NoCommit { minutes } => Box::new(NoCommit {
+            rule: rule.clone(),
+            limit: Duration::from_secs(minutes * 60),
+            work_dir: work_dir.to_string(),
+            last_commit: None,
+            head: None,
+        }),
+        GutterRule::RepeatedError { count } => Box::new(RepeatedError {
+            rule: rule.clone(),
+            count: *count,
+            errors: HashMap::new(),
+        }),
+        GutterRule::RevertedEdits { count } => Box::new(RevertedEdits {
+            rule: rule.clone(),
+            count: *count,
+            work_dir: work_dir.to_string(),
+            versions: HashMap::new(),
+            reverts: 0,
+        }),
+        GutterRule::NoFileChanges { iterations } => Box::new(NoFileChanges {
+            rule: rule.clone(),
+            iterations: *iterations,
+            idle: 0,
+        }),
+    }
+}
+
+/// Runs every detector of a session and reports the first that fires.
+#[derive(Default)]
+pub struct GutterMonitor {
+    detectors: Vec<Box<dyn GutterDetector>>,
+}
+
+impl GutterMonitor {
+    pub fn new(rules: &[GutterRule], work_dir: &str) -> Self {
+        Self {
+            detectors: rules.iter().map(|rule| detector_for(rule, work_dir)).collect(),
+        }
+    }
+
+    /// Also run `detector`, for ways of getting stuck no `GutterRule` covers.
+    pub fn with_detector(mut self, detector: Box<dyn GutterDetector>) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    pub fn observe(&mut self, activity: &ActivityKind, at: SystemTime) -> Option<Signal> {
+        // Every detector sees every activity, even once one has fired.
+        let mut signal = None;
+        for detector in &mut self.detectors {
+            if let Some(reason) = detector.observe(activity, at) {
+                signal.get_or_insert_with(|| gutter(detector.as_ref(), reason));
+            }
+        }
+        signal
+    }
+
+    pub fn end_iteration(&mut self, changed_files: bool) -> Option<Signal> {
+        let mut signal = None;
+        for detector in &mut self.detectors {
+            if let Some(reason) = detector.end_iteration(changed_files) {
+                signal.get_or_insert_with(|| gutter(detector.as_ref(), reason));
+            }
+        }
+        signal
+    }
+}
+
+fn gutter(detector: &dyn GutterDetector, reason: String) -> Signal {
+    Signal::Gutter {
+        rule: detector.rule().to_string(),
+        reason,
+    }
+}
+
+/// `command` without its flags and paths, so reruns with other options or
+/// targets compare equal.
+pub fn normalize_command(command: &str) -> String {
+    let words: Vec<&str> = command
+        .split_whitespace()
+        .filter(|word| !word.starts_with('-') && !looks_like_path(word))
+        .collect();
+    if words.is_empty() {
+        command.trim().to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+fn looks_like_path(word: &str) -> bool {
+    let word = word.trim_matches(|c| c == '"' || c == '\'');
+    word.contains('/')
+        || word.contains('\\')
+        || word.rsplit_once('.').is_some_and(|(stem, ext)| {
+            !stem.is_empty() && !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+struct RepeatedCommandFailure {
+    rule: GutterRule,
+    count: u32,
+    failures: HashMap<String, u32>,
+}
+
+impl GutterDetector for RepeatedCommandFailure {
+    fn rule(&self) -> &str {
+        self.rule.name()
+    }
+
+    fn observe(&mut self, activity: &ActivityKind, _at: SystemTime) -> Option<String> {
+        let ActivityKind::Shell { command, exit_code, .. } = activity else {
+            return None;
+        };
+        if *exit_code == 0 {
+            return None;
+        }
+
+        let failures = self.failures.entry(normalize_command(command)).or_insert(0);
+        *failures += 1;
+        (*failures >= self.count).then(|| format!("Command failed {} times: {}", failures, command))
+    }
+
+    fn end_iteration(&mut self, _changed_files: bool) -> Option<String> {
+        self.failures.clear();
+        None
+    }
+}
+
+struct FileThrashing {
+    rule: GutterRule,
+    count: u32,
+    window: Duration,
+    writes: HashMap<String, Vec<SystemTime>>,
+}
+
+impl GutterDetector for FileThrashing {
+    fn rule(&self) -> &str {
+        self.rule.name()
+    }
+
+    fn observe(&mut self, activity: &ActivityKind, at: SystemTime) -> Option<String> {
+        let ActivityKind::Write { path, .. } = activity else {
+            return None;
+        };
+
+        let writes = self.writes.entry(path.clone()).or_default();
+        writes.push(at);
+        let window_start = at.checked_sub(self.window).unwrap_or(SystemTime::UNIX_EPOCH);
+        writes.retain(|&t| t > window_start);
+
+        (writes.len() >= self.count as usize).then(|| {
+            format!(
+                "File thrashing detected: {} ({} writes in {})",
+                path,
+                writes.len(),
+                describe_duration(self.window)
+            )
+        })
+    }
+
+    fn end_iteration(&mut self, _changed_files: bool) -> Option<String> {
+        self.writes.clear();
+        None
+    }
+}
+
+/// Commits are seen as a `git commit` shell command or, for commits made
+/// some other way, as `HEAD` having moved by the time the limit is up. The
+/// limit is only checked when an activity arrives, so a silent agent is left
+/// to the iteration's own timeouts.
+struct NoCommit {
+    rule: GutterRule,
+    limit: Duration,
+    work_dir: String,
+    last_commit: Option<SystemTime>,
+    /// `HEAD` when the clock last started.
+    head: Option<String>,
+}
+
+impl NoCommit {
+    fn current_head(&self) -> Option<String> {
+        let output = std::process::Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(&self.work_dir)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl GutterDetector for NoCommit {
+    fn rule(&self) -> &str {
+        self.rule.name()
+    }
+
+    fn observe(&mut self, activity: &ActivityKind, at: SystemTime) -> Option<String> {
+        if let ActivityKind::Shell { command, exit_code: 0, .. } = activity {
+            if command.contains("git commit") {
+                self.last_commit = Some(at);
+                return None;
+            }
+        }
+
+        // The clock starts with the first activity of the run.
+        if self.last_commit.is_none() {
+            self.last_commit = Some(at);
+            self.head = self.current_head();
+        }
+        let since = self.last_commit.unwrap_or(at);
+        let idle = at.duration_since(since).unwrap_or_default();
+        if idle < self.limit {
+            return None;
+        }
+
+        let head = self.current_head();
+        if head != self.head {
+            self.head = head;
+            self.last_commit = Some(at);
+            return None;
+        }
+        Some(format!("No commit in {}", describe_duration(idle)))
+    }
+}
+
+struct RepeatedError {
+    rule: GutterRule,
+    count: u32,
+    errors: HashMap<String, u32>,
+}
+
+impl GutterDetector for RepeatedError {
+    fn rule(&self) -> &str {
+        self.rule.name()
+    }
+
+    fn observe(&mut self, activity: &ActivityKind, _at: SystemTime) -> Option<String> {
+        let ActivityKind::Error(message) = activity else {
+            return None;
+        };
+
+        let seen = self.errors.entry(normalize_error(message)).or_insert(0);
+        *seen += 1;
+        (*seen >= self.count).then(|| {
+            let first_line = message.lines().next().unwrap_or_default();
+            format!("Same error {} times: {}", seen, first_line)
+        })
+    }
+
+    fn end_iteration(&mut self, _changed_files: bool) -> Option<String> {
+        self.errors.clear();
+        None
+    }
+}
+
+/// `message` with whitespace collapsed and every number replaced by `N`.
+fn normalize_error(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut in_number = false;
+    for c in message.split_whitespace().collect::<Vec<_>>().join(" ").chars() {
+        if c.is_ascii_digit() {
+            if !in_number {
+                normalized.push('N');
+            }
+            in_number = true;
+        } else {
+            normalized.push(c);
+            in_number = false;
+        }
+    }
+    normalized
+}
+
+struct RevertedEdits {
+    rule: GutterRule,
+    count: u32,
+    work_dir: String,
+    /// Content hashes each written file has had this iteration, oldest first.
+    versions: HashMap<String, Vec<u64>>,
+    reverts: u32,
+}
+
+impl GutterDetector for RevertedEdits {
+    fn rule(&self) -> &str {
+        self.rule.name()
+    }
+
+    fn observe(&mut self, activity: &ActivityKind, _at: SystemTime) -> Option<String> {
+        let ActivityKind::Write { path, .. } = activity else {
+            return None;
+        };
+        // A deleted or unreadable file has no version to compare.
+        let content = std::fs::read(Path::new(&self.work_dir).join(path)).ok()?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let versions = self.versions.entry(path.clone()).or_default();
+        if versions.last() == Some(&hash) {
+            return None;
+        }
+        let reverted = versions.contains(&hash);
+        versions.push(hash);
+        if !reverted {
+            return None;
+        }
+
+        self.reverts += 1;
+        (self.reverts >= self.count).then(|| {
+            format!(
+                "Edits reverted {} times, last to an earlier version of {}",
+                self.reverts, path
+            )
+        })
+    }
+
+    fn end_iteration(&mut self, _changed_files: bool) -> Option<String> {
+        self.versions.clear();
+        self.reverts = 0;
+        None
+    }
+}
+
+struct NoFileChanges {
+    rule: GutterRule,
+    iterations: u32,
+    idle: u32,
+}
+
+impl GutterDetector for NoFileChanges {
+    fn rule(&self) -> &str {
+        self.rule.name()
+    }
+
+    fn observe(&mut self, _activity: &ActivityKind, _at: SystemTime) -> Option<String> {
+        None
+    }
+
+    fn end_iteration(&mut self, changed_files: bool) -> Option<String> {
+        self.idle = if changed_files { 0 } else { self.idle + 1 };
+        (self.idle >= self.iterations).then(|| match self.idle {
+            1 => "The iteration changed no files".to_string(),
+            idle => format!("{} iterations in a row changed no files", idle),
+        })
+    }
+}
+
+fn describe_duration(duration: Duration) -> String {
+    match (duration.as_secs() / 60, duration.as_secs() % 60) {
+        (0, secs) => format!("{}s", secs),
+        (mins, 0) => format!("{}min", mins),
+        (mins, secs) => format!("{}min {}s", mins, secs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(command: &str, exit_code: i32) -> ActivityKind {
+        ActivityKind::Shell {
+            command: command.to_string(),
+            exit_code,
+            output_bytes: 0,
+        }
+    }
+
+    fn write(path: &str) -> ActivityKind {
+        ActivityKind::Write {
+            path: path.to_string(),
+            lines: 1,
+            bytes: 10,
+        }
+    }
+
+    fn rule_of(signal: Option<Signal>) -> Option<String> {
+        match signal {
+            Some(Signal::Gutter { rule, .. }) => Some(rule),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_command() {
+        assert_eq!(normalize_command("cargo test --lib"), "cargo test");
+        assert_eq!(normalize_command("cargo test -q src/auth.rs"), "cargo test");
+        assert_eq!(normalize_command("npm run build"), "npm run build");
+        assert_eq!(normalize_command("./check.sh"), "./check.sh");
+    }
+
+    #[test]
+    fn test_repeated_command_failure_ignores_flags_and_paths() {
+        let now = SystemTime::now();
+        let mut monitor = GutterMonitor::new(&[GutterRule::RepeatedCommandFailure { count: 3 }], ".");
+
+        assert!(monitor.observe(&shell("cargo test", 101), now).is_none());
+        assert!(monitor.observe(&shell("cargo test --lib", 101), now).is_none());
+        assert!(monitor.observe(&shell("cargo build", 101), now).is_none());
+        match monitor.observe(&shell("cargo test tests/auth.rs", 101), now) {
+            Some(Signal::Gutter { rule, reason }) => {
+                assert_eq!(rule, "repeated_command_failure");
+                assert_eq!(reason, "Command failed 3 times: cargo test tests/auth.rs");
+            }
+            other => panic!("expected a gutter signal, got {:?}", other),
+        }
+
+        // Failures don't carry over into the next iteration.
+        monitor.end_iteration(true);
+        assert!(monitor.observe(&shell("cargo test", 101), now).is_none());
+    }
+
+    #[test]
+    fn test_file_thrashing_window() {
+        let start = SystemTime::now();
+        let rule = GutterRule::FileThrashing {
+            count: 3,
+            window_secs: 60,
+        };
+        let mut monitor = GutterMonitor::new(&[rule], ".");
+
+        monitor.observe(&write("src/a.rs"), start);
+        monitor.observe(&write("src/a.rs"), start + Duration::from_secs(70));
+        // The first write has left the window by now.
+        assert!(monitor.observe(&write("src/a.rs"), start + Duration::from_secs(80)).is_none());
+        let signal = monitor.observe(&write("src/a.rs"), start + Duration::from_secs(90));
+        assert_eq!(rule_of(signal).as_deref(), Some("file_thrashing"));
+    }
+
+    #[test]
+    fn test_no_commit() {
+        let start = SystemTime::now();
+        let mut monitor = GutterMonitor::new(&[GutterRule::NoCommit { minutes: 10 }], ".");
+
+        monitor.observe(&shell("cargo build", 0), start);
+        monitor.observe(&shell("git add -A && git commit -m wip", 0), start + Duration::from_secs(300));
+        assert!(monitor
+            .observe(&shell("cargo build", 0), start + Duration::from_secs(600))
+            .is_none());
+        // The clock keeps running across iterations.
+        monitor.end_iteration(true);
+        match monitor.observe(&shell("cargo build", 0), start + Duration::from_secs(900)) {
+            Some(Signal::Gutter { rule, reason }) => {
+                assert_eq!(rule, "no_commit");
+                assert_eq!(reason, "No commit in 10min");
+            }
+            other => panic!("expected a gutter signal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_repeated_error_ignores_numbers() {
+        let now = SystemTime::now();
+        let mut monitor = GutterMonitor::new(&[GutterRule::RepeatedError { count: 2 }], ".");
+
+        let error = |line: u32| ActivityKind::Error(format!("Edit src/a.rs failed: no match at line {}", line));
+        assert!(monitor.observe(&error(12), now).is_none());
+        assert!(monitor
+            .observe(&ActivityKind::Error("Read failed".to_string()), now)
+            .is_none());
+        assert_eq!(rule_of(monitor.observe(&error(40), now)).as_deref(), Some("repeated_error"));
+    }
+
+    #[test]
+    fn test_reverted_edits() {
+        let dir = std::env::temp_dir().join(format!("ralph-gutter-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        let mut monitor = GutterMonitor::new(
+            &[GutterRule::RevertedEdits { count: 2 }],
+            dir.to_str().unwrap(),
+        );
+        let mut save = |content: &str| {
+            std::fs::write(dir.join("a.rs"), content).unwrap();
+            monitor.observe(&write("a.rs"), now)
+        };
+
+        assert!(save("fn a() {}").is_none());
+        assert!(save("fn a() { todo!() }").is_none());
+        assert!(save("fn a() {}").is_none(), "one revert is under the threshold");
+        assert!(save("fn a() {}").is_none(), "rewriting the same content is not a revert");
+        match save("fn a() { todo!() }") {
+            Some(Signal::Gutter { rule, reason }) => {
+                assert_eq!(rule, "reverted_edits");
+                assert_eq!(reason, "Edits reverted 2 times, last to an earlier version of a.rs");
+            }
+            other => panic!("expected a gutter signal, got {:?}", other),
+        }
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_no_file_changes() {
+        let mut monitor = GutterMonitor::new(&[GutterRule::NoFileChanges { iterations: 2 }], ".");
+
+        assert!(monitor.end_iteration(false).is_none());
+        assert!(monitor.end_iteration(true).is_none());
+        assert!(monitor.end_iteration(false).is_none());
+        match monitor.end_iteration(false) {
+            Some(Signal::Gutter { rule, reason }) => {
+                assert_eq!(rule, "no_file_changes");
+                assert_eq!(reason, "2 iterations in a row changed no files");
+            }
+            other => panic!("expected a gutter signal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_no_commit_sees_head_move() {
+        let dir = std::env::temp_dir().join(format!("ralph-gutter-head-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=Ralph", "-c", "user.email=ralph@example.com"])
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "Start"]);
+
+        let start = SystemTime::now();
+        let mut monitor = GutterMonitor::new(&[GutterRule::NoCommit { minutes: 10 }], dir.to_str().unwrap());
+        assert!(monitor.observe(&shell("cargo test", 0), start).is_none());
+        // Committed without a `git commit` shell command the monitor can see.
+        git(&["commit", "-q", "--allow-empty", "-m", "US-001"]);
+        let later = start + Duration::from_secs(600);
+        assert!(monitor.observe(&shell("cargo test", 0), later).is_none());
+        let signal = monitor.observe(&shell("cargo test", 0), later + Duration::from_secs(600));
+        assert_eq!(rule_of(signal).as_deref(), Some("no_commit"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    struct Marker;
+
+    impl GutterDetector for Marker {
+        fn rule(&self) -> &str {
+            "marker"
+        }
+
+        fn observe(&mut self, activity: &ActivityKind, _at: SystemTime) -> Option<String> {
+            matches!(activity, ActivityKind::Assistant(text) if text.contains("I give up"))
+                .then(|| "The agent gave up".to_string())
+        }
+    }
+
+    #[test]
+    fn test_custom_detector() {
+        let mut monitor = GutterMonitor::new(&[], ".").with_detector(Box::new(Marker));
+
+        let signal = monitor.observe(&ActivityKind::Assistant("I give up".to_string()), SystemTime::now());
+        assert_eq!(rule_of(signal).as_deref(), Some("marker"));
+    }
+}
@@ -1,34 +1,114 @@
+pub mod dependencies;
+pub mod gutter;
 pub mod parser;
+pub mod prd;
+pub mod prd_sync;
 pub mod signals;
+pub mod stream;
+pub mod tokens;
 pub mod types;
 
+#[cfg(feature = "server")]
+pub mod activity;
+#[cfg(feature = "server")]
+pub mod aider;
+#[cfg(feature = "server")]
+pub mod backend;
+#[cfg(feature = "server")]
+pub mod claude;
+#[cfg(feature = "server")]
+pub mod codex;
+#[cfg(feature = "server")]
+pub mod command;
 #[cfg(feature = "server")]
 pub mod conversation;
 #[cfg(feature = "server")]
 pub mod cursor;
 #[cfg(feature = "server")]
+pub mod fake;
+#[cfg(feature = "server")]
+pub mod fake_forge;
+#[cfg(feature = "server")]
+pub mod forge;
+#[cfg(feature = "server")]
 pub mod git;
 #[cfg(feature = "server")]
+pub mod gitea;
+#[cfg(feature = "server")]
+pub mod github;
+#[cfg(feature = "server")]
+pub mod gitlab;
+#[cfg(feature = "server")]
 pub mod guardrails;
 #[cfg(feature = "server")]
 pub mod memory;
 #[cfg(feature = "server")]
+pub mod pr;
+#[cfg(feature = "server")]
+pub mod prompt;
+#[cfg(feature = "server")]
+pub mod runner;
+#[cfg(feature = "server")]
+pub mod scheduler;
+#[cfg(feature = "server")]
 pub mod session;
+#[cfg(feature = "server")]
+pub mod store;
+#[cfg(feature = "server")]
+pub mod verify;
 
+#[cfg(feature = "server")]
+pub use activity::ActivityJournal;
+#[cfg(feature = "server")]
+pub use aider::AiderBackend;
+#[cfg(feature = "server")]
+pub use backend::{backend_for, models_for, AgentBackend, BackendFactory, OutputParser};
+#[cfg(feature = "server")]
+pub use claude::ClaudeCodeBackend;
+#[cfg(feature = "server")]
+pub use codex::CodexBackend;
+#[cfg(feature = "server")]
+pub use command::CommandBackend;
 #[cfg(feature = "server")]
 pub use conversation::PrdConversationManager;
 #[cfg(feature = "server")]
 pub use cursor::CursorRunner;
 #[cfg(feature = "server")]
+pub use fake::{FakeAgentBackend, FakeStep};
+#[cfg(feature = "server")]
+pub use fake_forge::{FakeForge, FakePullRequest};
+#[cfg(feature = "server")]
+pub use forge::{forge_for, ForgeFactory, ForgeFuture, ForgeProvider, NewPullRequest, PullRequestUpdate};
+#[cfg(feature = "server")]
 pub use git::GitOperations;
 #[cfg(feature = "server")]
+pub use gitea::GiteaForge;
+#[cfg(feature = "server")]
+pub use github::GitHubForge;
+#[cfg(feature = "server")]
+pub use gitlab::GitLabForge;
+#[cfg(feature = "server")]
 pub use guardrails::GuardrailManager;
 #[cfg(feature = "server")]
 pub use memory::{run_memory_monitor, run_health_watchdog, MemoryMonitor, MemorySnapshot, MemoryStatus};
+pub use dependencies::{dependencies_met, dependency_layers, parse_dependencies, validate_dependencies};
+pub use gutter::{detector_for, GutterDetector, GutterMonitor};
 pub use parser::StreamParser;
+pub use prd::{check_prd, is_valid_branch_name, parse_prd, validate_prd, PRD_SCHEMA};
+pub use prd_sync::{merge_prd, PrdMerge};
+#[cfg(feature = "server")]
+pub use runner::{AgentControl, AgentRunner};
+#[cfg(feature = "server")]
+pub use scheduler::{Scheduler, DEFAULT_MAX_CONCURRENT_SESSIONS};
 #[cfg(feature = "server")]
 pub use session::SessionManager;
 pub use signals::SignalHandler;
+pub use stream::StreamEvent;
+pub use tokens::{HeuristicTokenizer, TokenEstimator};
 #[cfg(feature = "server")]
 pub use signals::shutdown_signal;
+#[cfg(feature = "server")]
+pub use store::SessionStore;
+#[cfg(feature = "server")]
+pub use verify::StoryVerifier;
 pub use types::*;
@@ -1,18 +1,20 @@
+use crate::gutter::GutterMonitor;
+use crate::tokens::{HeuristicTokenizer, TokenEstimator};
 use crate::types::*;
-use std::collections::HashMap;
 use std::time::SystemTime;
 
+/// Marker the agent prints once every story passes.
+const COMPLETE_MARKER: &str = "<ralph>COMPLETE</ralph>";
+
 pub struct StreamParser {
     iteration: u32,
     token_usage: TokenUsage,
     warn_threshold: u32,
     rotate_threshold: u32,
-
-    // Gutter detection
-    command_failures: HashMap<String, u32>,
-    file_writes: HashMap<String, Vec<SystemTime>>,
-    gutter_fail_count: u32,
-    gutter_thrash_count: u32,
+    estimator: Box<dyn TokenEstimator>,
+    gutter: GutterMonitor,
+    /// The agent has reported real usage during this iteration.
+    reported_usage: bool,
 }
 
 impl StreamParser {
@@ -27,73 +29,78 @@ impl StreamParser {
             token_usage,
             warn_threshold,
             rotate_threshold,
-            command_failures: HashMap::new(),
-            file_writes: HashMap::new(),
-            gutter_fail_count: 3,
-            gutter_thrash_count: 5,
+            estimator: Box::new(HeuristicTokenizer),
+            gutter: GutterMonitor::new(&GutterRule::defaults(), "."),
+            reported_usage: false,
         }
     }
 
+    /// Use `estimator` for activities the agent reports no usage for.
+    pub fn with_estimator(mut self, estimator: Box<dyn TokenEstimator>) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    /// Detect the gutter with `gutter` instead of the default rules.
+    pub fn with_gutter(mut self, gutter: GutterMonitor) -> Self {
+        self.gutter = gutter;
+        self
+    }
+
+    /// Hand the gutter detectors back, so their state can outlive this parser.
+    pub fn take_gutter(&mut self) -> GutterMonitor {
+        std::mem::take(&mut self.gutter)
+    }
+
     pub fn parse_activity(&mut self, kind: ActivityKind) -> (ActivityEntry, Option<Signal>) {
-        // Update token usage
-        match &kind {
+        // Update token usage: reported usage replaces the running total, anything
+        // else is estimated on top of it.
+        let tokens = match &kind {
+            ActivityKind::Usage(usage) => {
+                self.token_usage.total = usage.context_tokens();
+                self.token_usage.source = TokenSource::Reported;
+                self.reported_usage = true;
+                Some(TokenCount {
+                    tokens: usage.context_tokens(),
+                    source: TokenSource::Reported,
+                })
+            }
             ActivityKind::Read { bytes, .. } => {
-                self.token_usage.read += bytes;
-                self.token_usage.total += bytes;
+                let tokens = self.estimator.estimate_bytes(*bytes);
+                self.token_usage.read += tokens;
+                Some(self.add_estimate(tokens))
             }
             ActivityKind::Write { bytes, .. } => {
-                self.token_usage.write += bytes;
-                self.token_usage.total += bytes;
+                let tokens = self.estimator.estimate_bytes(*bytes);
+                self.token_usage.write += tokens;
+                Some(self.add_estimate(tokens))
             }
-            ActivityKind::Shell { .. } => {
-                // Shell commands contribute to token usage (estimate)
-                self.token_usage.shell += 100;
-                self.token_usage.total += 100;
+            ActivityKind::Shell {
+                command,
+                output_bytes,
+                ..
+            } => {
+                let tokens = self.estimator.estimate_text(command)
+                    + self.estimator.estimate_bytes(*output_bytes);
+                self.token_usage.shell += tokens;
+                Some(self.add_estimate(tokens))
             }
-            _ => {}
-        }
+            ActivityKind::Assistant(text) => {
+                let tokens = self.estimator.estimate_text(text);
+                self.token_usage.assistant += tokens;
+                Some(self.add_estimate(tokens))
+            }
+            _ => None,
+        };
 
         // Check for gutter conditions
-        let mut signal = None;
-
-        match &kind {
-            ActivityKind::Shell { command, exit_code } if *exit_code != 0 => {
-                // Track failed commands
-                let count = self.command_failures.entry(command.clone()).or_insert(0);
-                *count += 1;
-
-                if *count >= self.gutter_fail_count {
-                    signal = Some(Signal::Gutter(format!(
-                        "Command failed {} times: {}",
-                        count, command
-                    )));
-                }
-            }
-            ActivityKind::Write { path, .. } => {
-                // Track file thrashing
-                let writes = self
-                    .file_writes
-                    .entry(path.clone())
-                    .or_insert_with(Vec::new);
-                writes.push(SystemTime::now());
-
-                // Keep only writes from last 10 minutes
-                let ten_mins_ago = SystemTime::now()
-                    .checked_sub(std::time::Duration::from_secs(600))
-                    .unwrap_or(SystemTime::UNIX_EPOCH);
-
-                writes.retain(|&t| t > ten_mins_ago);
-
-                if writes.len() >= self.gutter_thrash_count as usize {
-                    signal = Some(Signal::Gutter(format!(
-                        "File thrashing detected: {} ({} writes in 10min)",
-                        path,
-                        writes.len()
-                    )));
-                }
+        let gutter = self.gutter.observe(&kind, SystemTime::now());
+        let mut signal = match &kind {
+            ActivityKind::Assistant(text) if text.contains(COMPLETE_MARKER) => {
+                Some(Signal::Complete)
             }
-            _ => {}
-        }
+            _ => gutter,
+        };
 
         // Check token thresholds
         if signal.is_none() {
@@ -104,18 +111,33 @@ impl StreamParser {
             }
         }
 
-        let health = self
-            .token_usage
-            .health(self.warn_threshold, self.rotate_threshold);
+        let mut entry = self.entry(kind);
+        entry.tokens = tokens;
+        (entry, signal)
+    }
 
-        let entry = ActivityEntry {
+    fn add_estimate(&mut self, tokens: u32) -> TokenCount {
+        let source = TokenSource::Estimated(self.estimator.name().to_string());
+        self.token_usage.total += tokens;
+        // Estimates on top of a reported total leave it measured.
+        if !self.reported_usage {
+            self.token_usage.source = source.clone();
+        }
+        TokenCount { tokens, source }
+    }
+
+    /// Build an entry for the current iteration and context health without
+    /// affecting token accounting or gutter detection.
+    pub fn entry(&self, kind: ActivityKind) -> ActivityEntry {
+        ActivityEntry {
             timestamp: SystemTime::now(),
             iteration: self.iteration,
             kind,
-            health,
-        };
-
-        (entry, signal)
+            health: self
+                .token_usage
+                .health(self.warn_threshold, self.rotate_threshold),
+            tokens: None,
+        }
     }
 
     pub fn token_usage(&self) -> &TokenUsage {
@@ -141,20 +163,89 @@ mod tests {
             bytes: 5000,
         });
 
-        assert_eq!(parser.token_usage().read, 5000);
-        assert_eq!(parser.token_usage().total, 5000);
+        assert_eq!(parser.token_usage().read, 1250);
+        assert_eq!(parser.token_usage().total, 1250);
+        assert_eq!(
+            entry.tokens,
+            Some(TokenCount {
+                tokens: 1250,
+                source: TokenSource::Estimated("heuristic-bpe".to_string()),
+            })
+        );
         assert!(signal.is_none());
     }
 
     #[test]
-    fn test_warn_threshold() {
+    fn test_reported_usage_replaces_estimate() {
         let mut parser = StreamParser::new(0, TokenUsage::default(), 70_000, 80_000);
 
-        let (_, signal) = parser.parse_activity(ActivityKind::Read {
+        parser.parse_activity(ActivityKind::Write {
             path: "test.rs".to_string(),
-            lines: 1000,
-            bytes: 70_000,
+            lines: 10,
+            bytes: 4000,
         });
+        assert_eq!(parser.token_usage().total, 1000);
+
+        let (entry, _) = parser.parse_activity(ActivityKind::Usage(ReportedUsage {
+            input_tokens: 2000,
+            output_tokens: 500,
+            cache_read_tokens: 10_000,
+            cache_write_tokens: 0,
+        }));
+        assert_eq!(parser.token_usage().total, 12_500);
+        assert_eq!(parser.token_usage().source, TokenSource::Reported);
+        assert_eq!(entry.tokens.unwrap().source, TokenSource::Reported);
+        // The per-kind breakdown stays estimated.
+        assert_eq!(parser.token_usage().write, 1000);
+
+        // Later estimates add to the total without relabelling it.
+        let (entry, _) = parser.parse_activity(ActivityKind::Assistant("All done".to_string()));
+        assert_eq!(parser.token_usage().source, TokenSource::Reported);
+        assert_eq!(
+            entry.tokens.unwrap().source,
+            TokenSource::Estimated("heuristic-bpe".to_string())
+        );
+    }
+
+    struct WordCount;
+
+    impl TokenEstimator for WordCount {
+        fn name(&self) -> &str {
+            "words"
+        }
+
+        fn estimate_text(&self, text: &str) -> u32 {
+            text.split_whitespace().count() as u32
+        }
+    }
+
+    #[test]
+    fn test_custom_estimator() {
+        let mut parser = StreamParser::new(0, TokenUsage::default(), 70_000, 80_000)
+            .with_estimator(Box::new(WordCount));
+
+        let (entry, _) = parser.parse_activity(ActivityKind::Shell {
+            command: "cargo test --workspace".to_string(),
+            exit_code: 0,
+            output_bytes: 400,
+        });
+
+        assert_eq!(parser.token_usage().shell, 103);
+        assert_eq!(
+            parser.token_usage().source,
+            TokenSource::Estimated("words".to_string())
+        );
+        assert_eq!(entry.tokens.unwrap().tokens, 103);
+    }
+
+    #[test]
+    fn test_warn_threshold() {
+        let mut parser = StreamParser::new(0, TokenUsage::default(), 70_000, 80_000);
+
+        let (_, signal) = parser.parse_activity(ActivityKind::Usage(ReportedUsage {
+            input_tokens: 70_000,
+            ..Default::default()
+        }));
 
         assert!(matches!(signal, Some(Signal::Warn)));
     }
@@ -166,12 +257,27 @@ mod tests {
         let (_, signal) = parser.parse_activity(ActivityKind::Read {
             path: "test.rs".to_string(),
             lines: 1000,
-            bytes: 80_000,
+            bytes: 320_000,
         });
 
         assert!(matches!(signal, Some(Signal::Rotate)));
     }
 
+    #[test]
+    fn test_complete_marker() {
+        let mut parser = StreamParser::new(0, TokenUsage::default(), 70_000, 80_000);
+
+        let (_, signal) =
+            parser.parse_activity(ActivityKind::Assistant("Working on it".to_string()));
+        assert!(signal.is_none());
+        assert_eq!(parser.token_usage().assistant, 4);
+
+        let (_, signal) = parser.parse_activity(ActivityKind::Assistant(
+            "Done.\n<ralph>COMPLETE</ralph>".to_string(),
+        ));
+        assert_eq!(signal, Some(Signal::Complete));
+    }
+
     #[test]
     fn test_gutter_detection() {
         let mut parser = StreamParser::new(0, TokenUsage::default(), 70_000, 80_000);
@@ -181,10 +287,11 @@ mod tests {
             let (_, signal) = parser.parse_activity(ActivityKind::Shell {
                 command: "npm test".to_string(),
                 exit_code: 1,
+                output_bytes: 0,
             });
 
             if signal.is_some() {
-                assert!(matches!(signal, Some(Signal::Gutter(_))));
+                assert!(matches!(signal, Some(Signal::Gutter { .. })));
                 break;
             }
         }
@@ -0,0 +1,198 @@
+use crate::types::*;
+
+/// Title for the pull request of a completed PRD.
+pub fn pr_title(prd: &Prd) -> String {
+    match prd.stories.as_slice() {
+        [] => prd.project.clone(),
+        [story] => format!("{}: {}", prd.project, story.title),
+        [first, rest @ ..] => format!("{}: {} and {} more", prd.project, first.title, rest.len()),
+    }
+}
+
+/// Pull request body: each completed story with its commits and what the
+/// agent wrote about it in `.ralph/progress.md`, then any skipped stories.
+///
+/// `commits` are the branch's commits since its base, oldest first; a commit
+/// belongs to every story whose id its subject mentions.
+pub fn pr_body(prd: &Prd, commits: &[CommitSummary], progress: &str) -> String {
+    let mut body = String::new();
+    if !prd.description.trim().is_empty() {
+        body.push_str(prd.description.trim());
+        body.push_str("\n\n");
+    }
+
+    body.push_str("## Stories\n");
+    for story in prd.stories.iter().filter(|s| s.passes()) {
+        body.push_str(&format!("\n### {} - {}\n", story.id, story.title));
+
+        let story_commits: Vec<&CommitSummary> = commits
+            .iter()
+            .filter(|c| mentions(&c.subject, &story.id))
+            .collect();
+        if !story_commits.is_empty() {
+            body.push_str("\n**Commits:**\n");
+            for commit in story_commits {
+                body.push_str(&format!("- {} {}\n", commit.hash, commit.subject));
+            }
+        }
+
+        let entries = progress_entries(progress, &story.id);
+        if !entries.is_empty() {
+            body.push_str("\n**Progress notes:**\n");
+            for entry in entries {
+                body.push('\n');
+                for line in entry.lines() {
+                    if line.trim().is_empty() {
+                        body.push_str(">\n");
+                    } else {
+                        body.push_str(&format!("> {}\n", line));
+                    }
+                }
+            }
+        }
+    }
+
+    let skipped: Vec<&Story> = prd.stories.iter().filter(|s| s.status == StoryStatus::Skipped).collect();
+    if !skipped.is_empty() {
+        body.push_str("\n## Skipped\n\n");
+        for story in skipped {
+            body.push_str(&format!("- {} - {}\n", story.id, story.title));
+        }
+    }
+
+    let other: Vec<&CommitSummary> = commits
+        .iter()
+        .filter(|c| !prd.stories.iter().any(|s| mentions(&c.subject, &s.id)))
+        .collect();
+    if !other.is_empty() {
+        body.push_str("\n## Other commits\n\n");
+        for commit in other {
+            body.push_str(&format!("- {} {}\n", commit.hash, commit.subject));
+        }
+    }
+
+    body.push_str("\n---\nOpened by Ralph.\n");
+    body
+}
+
+/// Sections of `progress.md` whose `## ` heading names `story_id`, without
+/// the heading and the trailing `---`.
+pub fn progress_entries<'a>(progress: &'a str, story_id: &str) -> Vec<&'a str> {
+    let mut entries = Vec::new();
+    let mut rest = progress;
+
+    while let Some(start) = find_heading(rest) {
+        let section = &rest[start..];
+        let heading_end = section.find('\n').unwrap_or(section.len());
+        let after_heading = &section[heading_end..];
+        let end = find_heading(after_heading).unwrap_or(after_heading.len());
+
+        if mentions(&section[..heading_end], story_id) {
+            let entry = after_heading[..end].trim();
+            let entry = entry.strip_suffix("---").unwrap_or(entry).trim();
+            if !entry.is_empty() {
+                entries.push(entry);
+            }
+        }
+        rest = &after_heading[end..];
+    }
+
+    entries
+}
+
+/// Byte offset of the next line starting with `## `.
+fn find_heading(text: &str) -> Option<usize> {
+    if text.starts_with("## ") {
+        return Some(0);
+    }
+    text.find("\n## ").map(|i| i + 1)
+}
+
+/// Whether `text` contains `id` as a whole word, so `US-1` doesn't match
+/// `US-10`.
+fn mentions(text: &str, id: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    text.match_indices(id).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + id.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story(id: &str, title: &str, passes: bool) -> Story {
+        Story {
+            id: id.to_string(),
+            title: title.to_string(),
+            description: String::new(),
+            acceptance_criteria: vec![],
+            priority: 1,
+            status: if passes { StoryStatus::Done } else { StoryStatus::Pending },
+            notes: String::new(),
+            depends_on: Vec::new(),
+            attempts: Vec::new(),
+        }
+    }
+
+    fn commit(hash: &str, subject: &str) -> CommitSummary {
+        CommitSummary {
+            hash: hash.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+
+    const PROGRESS: &str = "# Ralph Progress Log
+
+## 2026-01-21 14:30 - US-1
+
+Added the login form.
+
+**Learnings for future iterations:**
+- Forms use the shared validator
+---
+
+## 2026-01-21 15:10 - US-10
+
+Unrelated.
+---
+";
+
+    #[test]
+    fn test_progress_entries_match_whole_story_ids() {
+        assert_eq!(
+            progress_entries(PROGRESS, "US-1"),
+            vec!["Added the login form.\n\n**Learnings for future iterations:**\n- Forms use the shared validator"]
+        );
+        assert_eq!(progress_entries(PROGRESS, "US-10"), vec!["Unrelated."]);
+        assert!(progress_entries(PROGRESS, "US-2").is_empty());
+    }
+
+    #[test]
+    fn test_pr_body_groups_commits_by_story() {
+        let prd = Prd {
+            project: "Auth".to_string(),
+            branch_name: "ralph/auth".to_string(),
+            description: "Let users sign in.".to_string(),
+            stories: vec![story("US-1", "Login form", true), story("US-10", "Logout", false)],
+        };
+        let commits = vec![
+            commit("abc1234", "feat: US-1 - Login form"),
+            commit("def5678", "chore: bump deps"),
+        ];
+
+        assert_eq!(pr_title(&prd), "Auth: Login form and 1 more");
+        let body = pr_body(&prd, &commits, PROGRESS);
+        assert!(body.starts_with("Let users sign in.\n\n## Stories\n\n### US-1 - Login form\n"));
+        assert!(body.contains("- abc1234 feat: US-1 - Login form\n"));
+        assert!(body.contains("> Added the login form.\n>\n> **Learnings"));
+        assert!(!body.contains("US-10 - Logout"));
+        assert!(body.contains("## Other commits\n\n- def5678 chore: bump deps\n"));
+
+        let mut skipped = prd.clone();
+        skipped.stories[1].status = StoryStatus::Skipped;
+        assert!(pr_body(&skipped, &commits, PROGRESS).contains("## Skipped\n\n- US-10 - Logout\n"));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::dependencies::validate_dependencies;
+use crate::types::*;
+use std::collections::BTreeMap;
+
+/// JSON Schema for `prd.json`.
+pub const PRD_SCHEMA: &str = include_str!("../assets/schema/prd.schema.json");
+
+/// Stories with more acceptance criteria than this rarely fit in one iteration.
+const MAX_CRITERIA: usize = 8;
+
+/// Stories with a longer description than this (in characters) rarely fit in
+/// one iteration.
+const MAX_DESCRIPTION_CHARS: usize = 600;
+
+/// Words that mark a story as changing the UI.
+const UI_WORDS: &[&str] = &[
+    "ui", "page", "screen", "button", "form", "modal", "dialog", "dropdown", "component", "layout",
+    "frontend", "css", "click",
+];
+
+/// Parse `json` as `prd.json` and check it is a PRD Ralph can run.
+pub fn parse_prd(json: &str) -> Result<Prd, RalphError> {
+    let prd: Prd = serde_json::from_str(json).map_err(|e| RalphError::InvalidPrd(e.to_string()))?;
+    validate_prd(&prd)?;
+    Ok(prd)
+}
+
+/// Refuse a PRD `check_prd` finds errors in; warnings don't stop it.
+pub fn validate_prd(prd: &Prd) -> Result<(), RalphError> {
+    let errors: Vec<String> = check_prd(prd)
+        .into_iter()
+        .filter(|issue| issue.severity == IssueSeverity::Error)
+        .map(|issue| issue.message)
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(RalphError::InvalidPrd(errors.join("; ")))
+    }
+}
+
+/// Everything worth telling the author about `prd`, errors first.
+///
+/// Errors are what Ralph can't run: duplicate story ids, stories without
+/// acceptance criteria, a branch name git rejects, stories sharing a priority
+/// and broken dependencies. Warnings point at stories that look too large for
+/// one iteration, lack a "Typecheck passes" criterion, or change the UI
+/// without a criterion to check it in a browser.
+pub fn check_prd(prd: &Prd) -> Vec<PrdIssue> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    if prd.stories.is_empty() {
+        errors.push(issue(IssueSeverity::Error, None, "The PRD has no stories".to_string()));
+    }
+    if !is_valid_branch_name(&prd.branch_name) {
+        let message = format!("\"{}\" is not a valid git branch name", prd.branch_name);
+        errors.push(issue(IssueSeverity::Error, None, message));
+    }
+
+    let mut by_id: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut by_priority: BTreeMap<u32, Vec<&str>> = BTreeMap::new();
+    for story in &prd.stories {
+        *by_id.entry(&story.id).or_default() += 1;
+        by_priority.entry(story.priority).or_default().push(&story.id);
+    }
+    for (id, count) in by_id.into_iter().filter(|(_, count)| *count > 1) {
+        let message = format!("{} is used by {} stories", id, count);
+        errors.push(issue(IssueSeverity::Error, Some(id), message));
+    }
+    for (priority, ids) in by_priority.into_iter().filter(|(_, ids)| ids.len() > 1) {
+        let message = format!("{} share priority {}", ids.join(", "), priority);
+        errors.push(issue(IssueSeverity::Error, None, message));
+    }
+    if let Err(e) = validate_dependencies(&prd.stories) {
+        let message = match e {
+            RalphError::InvalidPrd(message) => message,
+            other => other.to_string(),
+        };
+        errors.push(issue(IssueSeverity::Error, None, message));
+    }
+
+    for story in &prd.stories {
+        let id = Some(story.id.as_str());
+        let criteria: Vec<String> = story
+            .acceptance_criteria
+            .iter()
+            .map(|c| c.trim().to_lowercase())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if criteria.is_empty() {
+            errors.push(issue(IssueSeverity::Error, id, format!("{} has no acceptance criteria", story.id)));
+            continue;
+        }
+
+        let description_chars = story.description.chars().count();
+        if criteria.len() > MAX_CRITERIA || description_chars > MAX_DESCRIPTION_CHARS {
+            let size = if criteria.len() > MAX_CRITERIA {
+                format!("{} acceptance criteria", criteria.len())
+            } else {
+                format!("a {}-character description", description_chars)
+            };
+            let message = format!("{} has {} and may not fit in one iteration; consider splitting it", story.id, size);
+            warnings.push(issue(IssueSeverity::Warning, id, message));
+        }
+        if !criteria.iter().any(|c| c.contains("typecheck")) {
+            let message = format!("{} has no \"Typecheck passes\" criterion", story.id);
+            warnings.push(issue(IssueSeverity::Warning, id, message));
+        }
+        if is_ui_story(story) && !criteria.iter().any(|c| c.contains("browser")) {
+            let message = format!("{} changes the UI but has no \"Verify in browser\" criterion", story.id);
+            warnings.push(issue(IssueSeverity::Warning, id, message));
+        }
+    }
+
+    errors.extend(warnings);
+    errors
+}
+
+/// Whether git accepts `name` as a branch name, following the rules of
+/// `git check-ref-format --branch`.
+pub fn is_valid_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "@"
+        && !name.starts_with(['/', '-'])
+        && !name.ends_with(['/', '.'])
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && !name
+            .chars()
+            .any(|c| c.is_ascii_control() || c.is_whitespace() || "~^:?*[\\".contains(c))
+        && name
+            .split('/')
+            .all(|part| !part.starts_with('.') && !part.ends_with(".lock"))
+}
+
+/// Whether the story's title, description or criteria talk about the UI.
+fn is_ui_story(story: &Story) -> bool {
+    std::iter::once(&story.title)
+        .chain(std::iter::once(&story.description))
+        .chain(story.acceptance_criteria.iter())
+        .flat_map(|text| text.split(|c: char| !c.is_alphanumeric()))
+        .any(|word| UI_WORDS.iter().any(|ui| word.eq_ignore_ascii_case(ui)))
+}
+
+fn issue(severity: IssueSeverity, story_id: Option<&str>, message: String) -> PrdIssue {
+    PrdIssue {
+        severity,
+        story_id: story_id.map(String::from),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story(id: &str, priority: u32) -> Story {
+        Story {
+            id: id.to_string(),
+            title: format!("Add the {} endpoint", id),
+            description: "As a user, I want the endpoint so that I can call it".to_string(),
+            acceptance_criteria: vec!["Returns 200".to_string(), "Typecheck passes".to_string()],
+            priority,
+            status: StoryStatus::Pending,
+            notes: String::new(),
+            depends_on: Vec::new(),
+            attempts: Vec::new(),
+        }
+    }
+
+    fn prd(stories: Vec<Story>) -> Prd {
+        Prd {
+            project: "Test".to_string(),
+            branch_name: "ralph/test".to_string(),
+            description: String::new(),
+            stories,
+        }
+    }
+
+    fn messages(prd: &Prd, severity: IssueSeverity) -> Vec<String> {
+        check_prd(prd)
+            .into_iter()
+            .filter(|issue| issue.severity == severity)
+            .map(|issue| issue.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_prd_has_no_issues() {
+        let prd = prd(vec![story("US-001", 1), story("US-002", 2)]);
+        assert!(check_prd(&prd).is_empty());
+        assert!(validate_prd(&prd).is_ok());
+    }
+
+    #[test]
+    fn test_check_prd_errors() {
+        let mut bad = prd(vec![story("US-001", 1), story("US-001", 2), story("US-003", 2)]);
+        bad.branch_name = "ralph/new feature".to_string();
+        bad.stories[2].acceptance_criteria = vec![" ".to_string()];
+
+        assert_eq!(
+            messages(&bad, IssueSeverity::Error),
+            vec![
+                "\"ralph/new feature\" is not a valid git branch name",
+                "US-001 is used by 2 stories",
+                "US-001, US-003 share priority 2",
+                "US-003 has no acceptance criteria",
+            ]
+        );
+        let error = validate_prd(&bad).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid PRD: \"ralph/new feature\" is not a valid git branch name; US-001"));
+
+        let mut cyclic = prd(vec![story("US-001", 1)]);
+        cyclic.stories[0].depends_on = vec!["US-001".to_string()];
+        assert!(validate_prd(&cyclic).is_err());
+        assert!(validate_prd(&prd(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_check_prd_warnings() {
+        let mut large = story("US-001", 1);
+        large.acceptance_criteria = (0..10).map(|n| format!("Criterion {}", n)).collect();
+        let mut ui = story("US-002", 2);
+        ui.title = "Add a status dropdown to the task page".to_string();
+        let mut verified_ui = ui.clone();
+        verified_ui.id = "US-003".to_string();
+        verified_ui.priority = 3;
+        verified_ui.acceptance_criteria.push("Verify in browser using dev-browser skill".to_string());
+        let prd = prd(vec![large, ui, verified_ui]);
+
+        assert_eq!(
+            messages(&prd, IssueSeverity::Warning),
+            vec![
+                "US-001 has 10 acceptance criteria and may not fit in one iteration; consider splitting it",
+                "US-001 has no \"Typecheck passes\" criterion",
+                "US-002 changes the UI but has no \"Verify in browser\" criterion",
+            ]
+        );
+        assert!(validate_prd(&prd).is_ok(), "warnings don't reject a PRD");
+    }
+
+    #[test]
+    fn test_branch_names() {
+        for valid in ["ralph/task-status", "feature/US-001_fix", "main"] {
+            assert!(is_valid_branch_name(valid), "{}", valid);
+        }
+        for invalid in ["", "@", "/ralph", "ralph/", "ralph..x", "ralph//x", "ralph/.hidden", "x.lock", "a:b", "-x", "a@{1}"] {
+            assert!(!is_valid_branch_name(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_schema_matches_prd_json() {
+        let schema: serde_json::Value = serde_json::from_str(PRD_SCHEMA).unwrap();
+        let written = serde_json::to_value(prd(vec![story("US-001", 1)])).unwrap();
+
+        let keys = |value: &serde_json::Value| -> Vec<String> {
+            let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        let required = |value: &serde_json::Value| -> Vec<String> {
+            value["required"].as_array().unwrap().iter().map(|k| k.as_str().unwrap().to_string()).collect()
+        };
+
+        assert_eq!(keys(&schema["properties"]), keys(&written));
+        assert!(required(&schema).iter().all(|k| written.get(k).is_some()));
+        let story_schema = &schema["$defs"]["story"];
+        let written_story = &written["stories"][0];
+        assert_eq!(keys(&story_schema["properties"]), keys(written_story));
+        assert!(required(story_schema).iter().all(|k| written_story.get(k).is_some()));
+    }
+}
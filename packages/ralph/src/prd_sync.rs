@@ -0,0 +1,224 @@
+use crate::types::*;
+
+/// `theirs` merged into `ours`, with what was taken and what clashed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrdMerge {
+    pub prd: Prd,
+    /// Changes taken from `theirs`, e.g. "US-001 notes".
+    pub changes: Vec<String>,
+    /// Changes in `theirs` that clashed with changes in `ours`; `ours` won.
+    pub conflicts: Vec<String>,
+    /// Stories marked done in `theirs` but not in `ours`, left for
+    /// verification to confirm.
+    pub claimed_done: Vec<String>,
+}
+
+/// Three-way merge of `theirs` (prd.json as edited on disk) into `ours` (the
+/// session's PRD), where `base` is the PRD both last agreed on.
+///
+/// A field changed on one side only takes that side's value; a field changed
+/// differently on both sides keeps `ours` and is reported as a conflict.
+/// Attempts belong to the session and are never taken from disk, and a story
+/// only becomes done through verification, so a story marked done on disk
+/// keeps the session's status.
+pub fn merge_prd(base: &Prd, ours: &Prd, theirs: &Prd) -> PrdMerge {
+    let mut merger = Merger::default();
+    let project = merger.field("project", &base.project, &ours.project, &theirs.project);
+    let branch_name = merger.field("branch name", &base.branch_name, &ours.branch_name, &theirs.branch_name);
+    let description = merger.field("description", &base.description, &ours.description, &theirs.description);
+
+    let find = |prd: &'_ Prd, id: &str| prd.stories.iter().find(|s| s.id == id).cloned();
+    let mut stories = Vec::new();
+    for story in &ours.stories {
+        match (find(base, &story.id), find(theirs, &story.id)) {
+            (Some(b), Some(t)) => stories.push(merger.story(&b, story, &t)),
+            (Some(b), None) if same_story(&b, story) => merger.changes.push(format!("{} removed", story.id)),
+            (Some(_), None) => {
+                merger.conflicts.push(format!("{} removed", story.id));
+                stories.push(story.clone());
+            }
+            (None, Some(t)) => {
+                if !same_story(story, &t) {
+                    merger.conflicts.push(format!("{} added", story.id));
+                }
+                stories.push(story.clone());
+            }
+            (None, None) => stories.push(story.clone()),
+        }
+    }
+    for story in &theirs.stories {
+        if ours.stories.iter().any(|s| s.id == story.id) {
+            continue;
+        }
+        match find(base, &story.id) {
+            // Removed by the session; an edit on disk doesn't bring it back.
+            Some(b) if !same_story(&b, story) => merger.conflicts.push(format!("{} removed", story.id)),
+            Some(_) => {}
+            None => {
+                merger.changes.push(format!("{} added", story.id));
+                stories.push(story.clone());
+            }
+        }
+    }
+
+    PrdMerge {
+        prd: Prd {
+            project,
+            branch_name,
+            description,
+            stories,
+        },
+        changes: merger.changes,
+        conflicts: merger.conflicts,
+        claimed_done: merger.claimed_done,
+    }
+}
+
+/// Whether two versions of a story agree on everything but their attempts.
+fn same_story(a: &Story, b: &Story) -> bool {
+    Story {
+        attempts: Vec::new(),
+        ..a.clone()
+    } == Story {
+        attempts: Vec::new(),
+        ..b.clone()
+    }
+}
+
+#[derive(Default)]
+struct Merger {
+    changes: Vec<String>,
+    conflicts: Vec<String>,
+    claimed_done: Vec<String>,
+}
+
+impl Merger {
+    fn field<T: PartialEq + Clone>(&mut self, name: impl Into<String>, base: &T, ours: &T, theirs: &T) -> T {
+        if theirs == base || theirs == ours {
+            ours.clone()
+        } else if ours == base {
+            self.changes.push(name.into());
+            theirs.clone()
+        } else {
+            self.conflicts.push(name.into());
+            ours.clone()
+        }
+    }
+
+    fn story(&mut self, base: &Story, ours: &Story, theirs: &Story) -> Story {
+        let id = &ours.id;
+        let status = if theirs.passes() && !ours.passes() && !base.passes() {
+            self.claimed_done.push(id.clone());
+            ours.status
+        } else {
+            self.field(format!("{} status", id), &base.status, &ours.status, &theirs.status)
+        };
+        Story {
+            id: id.clone(),
+            title: self.field(format!("{} title", id), &base.title, &ours.title, &theirs.title),
+            description: self.field(
+                format!("{} description", id),
+                &base.description,
+                &ours.description,
+                &theirs.description,
+            ),
+            acceptance_criteria: self.field(
+                format!("{} acceptance criteria", id),
+                &base.acceptance_criteria,
+                &ours.acceptance_criteria,
+                &theirs.acceptance_criteria,
+            ),
+            priority: self.field(format!("{} priority", id), &base.priority, &ours.priority, &theirs.priority),
+            status,
+            notes: self.field(format!("{} notes", id), &base.notes, &ours.notes, &theirs.notes),
+            depends_on: self.field(
+                format!("{} dependencies", id),
+                &base.depends_on,
+                &ours.depends_on,
+                &theirs.depends_on,
+            ),
+            attempts: ours.attempts.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story(id: &str, priority: u32) -> Story {
+        Story {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            acceptance_criteria: Vec::new(),
+            priority,
+            status: StoryStatus::Pending,
+            notes: String::new(),
+            depends_on: Vec::new(),
+            attempts: Vec::new(),
+        }
+    }
+
+    fn prd(stories: Vec<Story>) -> Prd {
+        Prd {
+            project: "Test".to_string(),
+            branch_name: "ralph/test".to_string(),
+            description: String::new(),
+            stories,
+        }
+    }
+
+    #[test]
+    fn test_merge_takes_changes_from_either_side() {
+        let base = prd(vec![story("US-001", 1), story("US-002", 2)]);
+        let mut ours = base.clone();
+        ours.stories[0].status = StoryStatus::InProgress;
+        let mut theirs = base.clone();
+        theirs.stories[1].notes = "Uses the existing auth helper".to_string();
+        theirs.stories.push(story("US-003", 3));
+
+        let merge = merge_prd(&base, &ours, &theirs);
+        assert_eq!(merge.changes, vec!["US-002 notes", "US-003 added"]);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.prd.stories[0].status, StoryStatus::InProgress);
+        assert_eq!(merge.prd.stories[1].notes, "Uses the existing auth helper");
+        assert_eq!(merge.prd.stories.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_reports_conflicts_and_keeps_ours() {
+        let base = prd(vec![story("US-001", 1), story("US-002", 2)]);
+        let mut ours = base.clone();
+        ours.stories[0].notes = "Ours".to_string();
+        ours.stories[1].priority = 5;
+        let mut theirs = base.clone();
+        theirs.stories[0].notes = "Theirs".to_string();
+        theirs.stories.remove(1);
+
+        let merge = merge_prd(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts, vec!["US-001 notes", "US-002 removed"]);
+        assert_eq!(merge.prd, ours);
+    }
+
+    #[test]
+    fn test_merge_leaves_done_to_verification() {
+        let base = prd(vec![story("US-001", 1)]);
+        let mut ours = base.clone();
+        ours.stories[0].status = StoryStatus::InProgress;
+        let mut theirs = base.clone();
+        theirs.stories[0].status = StoryStatus::Done;
+
+        let merge = merge_prd(&base, &ours, &theirs);
+        assert_eq!(merge.prd.stories[0].status, StoryStatus::InProgress);
+        assert!(merge.changes.is_empty() && merge.conflicts.is_empty());
+        assert_eq!(merge.claimed_done, vec!["US-001"]);
+
+        // Reopening by hand on disk is taken.
+        let mut done = base.clone();
+        done.stories[0].status = StoryStatus::Done;
+        let merge = merge_prd(&done, &done, &base);
+        assert_eq!(merge.prd.stories[0].status, StoryStatus::Pending);
+        assert_eq!(merge.changes, vec!["US-001 status"]);
+    }
+}
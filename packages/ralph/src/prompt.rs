@@ -0,0 +1,153 @@
+use crate::types::*;
+use std::collections::BTreeMap;
+
+/// Minimal `{{name}}` templating for the prompt assets.
+///
+/// Placeholders without a value are left in place so a missing variable is
+/// visible in the rendered prompt instead of silently vanishing.
+#[derive(Debug, Default, Clone)]
+pub struct PromptTemplate {
+    vars: BTreeMap<String, String>,
+}
+
+impl PromptTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<String>) -> &mut Self {
+        self.vars.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn render(&self, template: &str) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+
+            let Some(end) = after.find("}}") else {
+                output.push_str(&rest[start..]);
+                return output;
+            };
+
+            let name = after[..end].trim();
+            match self.vars.get(name) {
+                Some(value) => output.push_str(value),
+                None => output.push_str(&rest[start..start + 2 + end + 2]),
+            }
+            rest = &after[end + 2..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+
+    /// Fill in the PRD and story variables used by `iteration.md`.
+    pub fn with_story(&mut self, prd: &Prd, story: &Story) -> &mut Self {
+        self.set("project", prd.project.clone())
+            .set("branch_name", prd.branch_name.clone())
+            .set("prd_description", non_empty(&prd.description, "(no description)"))
+            .set("story_id", story.id.clone())
+            .set("story_title", story.title.clone())
+            .set("story", format_story(story))
+    }
+}
+
+/// Render a story as the markdown block injected into the iteration prompt.
+pub fn format_story(story: &Story) -> String {
+    let mut output = format!("### {}: {}\n\n", story.id, story.title);
+
+    if !story.description.is_empty() {
+        output.push_str(&story.description);
+        output.push_str("\n\n");
+    }
+
+    output.push_str("**Acceptance Criteria:**\n");
+    for criterion in &story.acceptance_criteria {
+        output.push_str(&format!("- [ ] {}\n", criterion));
+    }
+
+    if !story.notes.is_empty() {
+        output.push_str(&format!("\n**Notes:** {}\n", story.notes));
+    }
+
+    output.trim_end().to_string()
+}
+
+/// Last `max_lines` lines of `content`.
+pub fn tail_lines(content: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(max_lines);
+    lines[start..].join("\n")
+}
+
+fn non_empty(value: &str, fallback: &str) -> String {
+    if value.trim().is_empty() {
+        fallback.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story() -> Story {
+        Story {
+            id: "US-002".to_string(),
+            title: "Add login form".to_string(),
+            description: "As a user I want to log in".to_string(),
+            acceptance_criteria: vec!["Form renders".to_string(), "Typecheck passes".to_string()],
+            priority: 1,
+            status: StoryStatus::Pending,
+            notes: "Reuse the existing Button".to_string(),
+            depends_on: Vec::new(),
+            attempts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_render_replaces_known_and_keeps_unknown() {
+        let mut template = PromptTemplate::new();
+        template.set("name", "Ralph");
+
+        assert_eq!(
+            template.render("Hi {{name}}, {{ name }}! {{missing}} {{"),
+            "Hi Ralph, Ralph! {{missing}} {{"
+        );
+    }
+
+    #[test]
+    fn test_with_story_fills_iteration_template() {
+        let prd = Prd {
+            project: "Auth".to_string(),
+            branch_name: "ralph/auth".to_string(),
+            description: "Login support".to_string(),
+            stories: vec![story()],
+        };
+
+        let mut template = PromptTemplate::new();
+        template
+            .with_story(&prd, &prd.stories[0])
+            .set("progress_tail", "")
+            .set("recent_commits", "");
+        let prompt = template.render(include_str!("../assets/prompts/iteration.md"));
+
+        assert!(prompt.contains("Work on story **US-002**"));
+        assert!(prompt.contains("- [ ] Form renders"));
+        assert!(prompt.contains("**Notes:** Reuse the existing Button"));
+        assert!(prompt.contains("Login support"));
+        assert!(prompt.contains("`ralph/auth`"));
+        assert!(!prompt.contains("{{"));
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\nd", 2), "c\nd");
+        assert_eq!(tail_lines("a", 5), "a");
+    }
+}
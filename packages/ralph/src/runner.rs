@@ -0,0 +1,629 @@
+use crate::backend::{backend_for, AgentBackend};
+use crate::types::*;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::watch;
+
+/// Retry subprocess spawn with exponential backoff for transient failures
+pub(crate) async fn spawn_with_retry(
+    mut command: Command,
+    max_retries: u32,
+) -> Result<tokio::process::Child, std::io::Error> {
+    let mut last_error = None;
+
+    for attempt in 0..=max_retries {
+        match command.spawn() {
+            Ok(child) => return Ok(child),
+            Err(e) => {
+                // Check if error is transient (too many open files, resource unavailable, etc.)
+                let is_transient = matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock
+                        | std::io::ErrorKind::ResourceBusy
+                        | std::io::ErrorKind::Interrupted
+                );
+
+                // Also check raw OS error codes
+                let is_transient = is_transient || matches!(
+                    e.raw_os_error(),
+                    Some(11) | // EAGAIN
+                    Some(24) | // EMFILE (too many open files)
+                    Some(23)   // ENFILE (system file table overflow)
+                );
+
+                if !is_transient || attempt == max_retries {
+                    return Err(e);
+                }
+
+                last_error = Some(e);
+                let delay_ms = 100 * 2u64.pow(attempt);
+                tracing::warn!(
+                    "Transient error spawning subprocess (attempt {}/{}): {:?}. Retrying in {}ms...",
+                    attempt + 1,
+                    max_retries + 1,
+                    last_error.as_ref().unwrap(),
+                    delay_ms
+                );
+                tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+            }
+        }
+    }
+
+    Err(last_error.unwrap())
+}
+
+/// How long a stopped agent gets to commit and exit on its own before it is killed.
+const STOP_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long one iteration may run before the agent is killed.
+const ITERATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// How long a plain-text chat response may take.
+const CHAT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// What the activity callback wants the runner to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentControl {
+    Continue,
+    /// Let the agent finish up within the grace period, then end the process.
+    Stop,
+}
+
+/// Asks a running agent to end early, from outside the activity callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    /// Let the current tool call finish, then stop the agent gracefully.
+    Pause,
+    /// Kill the agent right away.
+    Stop,
+    /// The server is going down; kill the agent right away.
+    Shutdown,
+}
+
+/// Drives an `AgentBackend` process: spawning, reading output, graceful stop
+/// and shutdown.
+pub struct AgentRunner {
+    project_path: String,
+    backend: Box<dyn AgentBackend>,
+    stop_grace_period: std::time::Duration,
+}
+
+impl AgentRunner {
+    pub fn new(project_path: String, backend: Box<dyn AgentBackend>) -> Self {
+        Self {
+            project_path,
+            backend,
+            stop_grace_period: STOP_GRACE_PERIOD,
+        }
+    }
+
+    /// Runner for the agent selected in a session's config.
+    pub fn for_config(project_path: String, model: String, kind: &AgentBackendKind) -> Self {
+        let backend = backend_for(kind, project_path.clone(), model);
+        Self::new(project_path, backend)
+    }
+
+    pub fn with_stop_grace_period(mut self, grace: std::time::Duration) -> Self {
+        self.stop_grace_period = grace;
+        self
+    }
+
+    pub fn backend(&self) -> &dyn AgentBackend {
+        self.backend.as_ref()
+    }
+
+    async fn spawn(&self, mut command: Command) -> Result<tokio::process::Child, RalphError> {
+        let name = self.backend.name().to_string();
+        tracing::debug!("Spawning {} in {}", name, self.project_path);
+
+        command
+            .current_dir(&self.project_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = match spawn_with_retry(command, 3).await {
+            Ok(child) => {
+                tracing::info!("{} spawned successfully (PID: {:?})", name, child.id());
+                child
+            }
+            Err(e) => {
+                tracing::error!("Failed to spawn {}: {}", name, e);
+                tracing::error!("Error kind: {:?}", e.kind());
+                tracing::error!("Working directory: {}", self.project_path);
+
+                return Err(RalphError::Agent(format!(
+                    "Failed to spawn {}: {} (kind: {:?})", name, e, e.kind()
+                )));
+            }
+        };
+
+        // Drain stderr to prevent buffer deadlock
+        if let Some(stderr) = child.stderr.take() {
+            let pid = child.id();
+            tokio::spawn(async move {
+                tracing::debug!("Started stderr reader for {} PID {:?}", name, pid);
+                let mut reader = BufReader::new(stderr).lines();
+                let mut line_count = 0;
+                while let Ok(Some(line)) = reader.next_line().await {
+                    if !line.is_empty() {
+                        line_count += 1;
+                        tracing::warn!("{}[{:?}] stderr line {}: {}", name, pid, line_count, line);
+                    }
+                }
+                tracing::debug!("Stderr reader completed for PID {:?} ({} lines)", pid, line_count);
+            });
+        }
+
+        Ok(child)
+    }
+
+    /// Run one agent iteration, feeding parsed activity to `activity_callback`.
+    ///
+    /// Returning `AgentControl::Stop` from the callback starts a graceful stop:
+    /// output keeps being read so the agent can commit and exit, and once the
+    /// grace period expires the process is terminated. An `Interrupt` sent on
+    /// `interrupt_rx` cuts the run short (see `Interrupt`). Neither a requested
+    /// stop nor an interrupt is an error.
+    pub async fn run_iteration(
+        &self,
+        prompt: &str,
+        mut interrupt_rx: watch::Receiver<Option<Interrupt>>,
+        mut activity_callback: impl FnMut(ActivityEntry) -> AgentControl,
+    ) -> Result<(), RalphError> {
+        let name = self.backend.name();
+        if let Some(interrupt) = *interrupt_rx.borrow() {
+            tracing::info!("{:?} requested before {} started, not spawning", interrupt, name);
+            return Ok(());
+        }
+
+        tracing::info!("=== Starting {} iteration ===", name);
+        tracing::info!("Project path: {}", self.project_path);
+        tracing::info!("Prompt length: {} chars", prompt.len());
+        tracing::debug!("First 200 chars of prompt: {}", &prompt.chars().take(200).collect::<String>());
+
+        let mut child = self.spawn(self.backend.iteration_command(prompt)).await?;
+
+        let stdout = child.stdout.take().ok_or_else(|| {
+            tracing::error!("Failed to capture {} stdout", name);
+            RalphError::Agent("Failed to capture stdout".into())
+        })?;
+
+        let mut reader = BufReader::new(stdout).lines();
+        let mut parser = self.backend.output_parser();
+
+        // Read output line by line
+        tracing::info!("📖 Reading {} output stream...", name);
+        let mut line_count = 0;
+        let mut stop_deadline: Option<tokio::time::Instant> = None;
+        let mut interrupt_open = true;
+        let mut pausing = false;
+        loop {
+            tokio::select! {
+                changed = interrupt_rx.changed(), if interrupt_open => {
+                    if changed.is_err() {
+                        interrupt_open = false;
+                        continue;
+                    }
+                    let requested = *interrupt_rx.borrow_and_update();
+                    match requested {
+                        Some(Interrupt::Pause) if !pausing => {
+                            tracing::info!("Pause requested, letting {} finish its current tool call", name);
+                            pausing = true;
+                            let deadline = tokio::time::Instant::now() + self.stop_grace_period;
+                            stop_deadline = Some(stop_deadline.map_or(deadline, |d| d.min(deadline)));
+                        }
+                        Some(interrupt @ (Interrupt::Stop | Interrupt::Shutdown)) => {
+                            tracing::info!("{:?} requested, terminating {}", interrupt, name);
+                            self.terminate(&mut child).await?;
+                            return Ok(());
+                        }
+                        _ => {}
+                    }
+                }
+                _ = sleep_until_deadline(stop_deadline) => {
+                    tracing::info!("Stop grace period expired, ending {}", name);
+                    self.stop_gracefully(&mut child).await?;
+                    return Ok(());
+                }
+                line = reader.next_line() => {
+                    let Ok(line) = line else {
+                        tracing::debug!("stdout reader: end of stream or error");
+                        break
+                    };
+                    let Some(line) = line else {
+                        tracing::debug!("stdout reader: no more lines");
+                        break
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    line_count += 1;
+                    if line_count % 10 == 0 {
+                        tracing::debug!("Processed {} lines from {}", line_count, name);
+                    }
+
+                    let activities = match parser.parse_line(&line) {
+                        Ok(activities) => activities,
+                        Err(e) => {
+                            tracing::warn!("Failed to parse line #{}: {} - {}", line_count, e, line);
+                            continue;
+                        }
+                    };
+
+                    let mut tool_call_finished = false;
+                    for kind in activities {
+                        if let ActivityKind::Error(message) = &kind {
+                            tracing::warn!("Error from {}: {}", name, message);
+                        }
+                        tracing::debug!("Activity #{}: {:?}", line_count, kind);
+                        tool_call_finished |= kind.is_tool_call();
+
+                        let activity = ActivityEntry {
+                            timestamp: std::time::SystemTime::now(),
+                            iteration: 0, // Will be set by caller
+                            kind,
+                            health: ContextHealth::Healthy, // Will be updated by caller
+                            tokens: None,
+                        };
+                        let control = activity_callback(activity);
+                        if control == AgentControl::Stop && stop_deadline.is_none() {
+                            tracing::info!(
+                                "Stop requested, giving {} {:?} to finish",
+                                name,
+                                self.stop_grace_period
+                            );
+                            stop_deadline =
+                                Some(tokio::time::Instant::now() + self.stop_grace_period);
+                        }
+                    }
+
+                    if pausing && tool_call_finished {
+                        tracing::info!("Tool call finished, pausing {}", name);
+                        self.stop_gracefully(&mut child).await?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        // Wait for process to complete
+        tracing::info!("📥 Processed {} output lines, waiting for {} to complete", line_count, name);
+        let status = loop {
+            tokio::select! {
+                changed = interrupt_rx.changed(), if interrupt_open => {
+                    if changed.is_err() {
+                        interrupt_open = false;
+                        continue;
+                    }
+                    let requested = *interrupt_rx.borrow_and_update();
+                    if let Some(interrupt) = requested {
+                        // Output is closed, so there is no tool call left to finish.
+                        tracing::info!("{:?} requested while waiting, ending {}", interrupt, name);
+                        self.terminate(&mut child).await?;
+                        return Ok(());
+                    }
+                }
+                _ = sleep_until_deadline(stop_deadline) => {
+                    tracing::info!("Stop grace period expired while waiting, ending {}", name);
+                    self.stop_gracefully(&mut child).await?;
+                    return Ok(());
+                }
+                result = tokio::time::timeout(ITERATION_TIMEOUT, child.wait()) => {
+                    match result {
+                        Ok(status) => {
+                            break status.map_err(|e| {
+                                tracing::error!("Failed to wait for {}: {}", name, e);
+                                RalphError::Agent(format!("Failed to wait for {}: {}", name, e))
+                            })?;
+                        }
+                        Err(_) => {
+                            tracing::error!("{} timed out after {:?}", name, ITERATION_TIMEOUT);
+                            self.terminate(&mut child).await?;
+                            return Err(RalphError::Agent(format!("{} timed out after {:?}", name, ITERATION_TIMEOUT)));
+                        }
+                    }
+                }
+            }
+        };
+
+        if !status.success() && (stop_deadline.is_some() || interrupt_rx.borrow().is_some()) {
+            // The agent was asked to stop; how it exited does not matter.
+            tracing::info!("{} exited with {} after stop request", name, status);
+            return Ok(());
+        }
+
+        if !status.success() {
+            tracing::error!("❌ {} exited with non-zero status: {}", name, status);
+            if let Some(code) = status.code() {
+                tracing::error!("Exit code: {}", code);
+            }
+            return Err(RalphError::Agent(format!(
+                "{} exited with status: {}",
+                name, status
+            )));
+        }
+
+        tracing::info!("✅ {} iteration completed successfully ({} lines processed)", name, line_count);
+        Ok(())
+    }
+
+    /// Ask the agent a question and return its plain-text answer.
+    pub async fn chat(&self, prompt: &str) -> Result<String, RalphError> {
+        let name = self.backend.name();
+        tracing::info!("💬 Asking {} ({} chars of prompt)", name, prompt.len());
+
+        let mut child = self.spawn(self.backend.chat_command(prompt)).await?;
+        let mut stdout = child.stdout.take().ok_or_else(|| {
+            tracing::error!("Failed to capture {} stdout", name);
+            RalphError::Agent("Failed to capture stdout".into())
+        })?;
+
+        let read_and_wait = async {
+            let mut response = String::new();
+            stdout
+                .read_to_string(&mut response)
+                .await
+                .map_err(|e| RalphError::Agent(format!("Failed to read response: {}", e)))?;
+            let status = child
+                .wait()
+                .await
+                .map_err(|e| RalphError::Agent(format!("Failed to wait for {}: {}", name, e)))?;
+            Ok::<_, RalphError>((response, status))
+        };
+
+        let (response, status) = match tokio::time::timeout(CHAT_TIMEOUT, read_and_wait).await {
+            Ok(result) => result?,
+            Err(_) => {
+                tracing::error!("{} timed out after {:?}", name, CHAT_TIMEOUT);
+                self.terminate(&mut child).await?;
+                return Err(RalphError::Agent(format!("{} timed out after {:?}", name, CHAT_TIMEOUT)));
+            }
+        };
+
+        if !status.success() {
+            tracing::error!("❌ {} exited with non-zero status: {}", name, status);
+            return Err(RalphError::Agent(format!(
+                "{} exited with status: {}",
+                name, status
+            )));
+        }
+
+        tracing::info!("📥 Received {} chars from {}", response.len(), name);
+        Ok(response.trim().to_string())
+    }
+
+    /// Ask the agent to exit with its stop signal, escalating to a kill if it
+    /// does not exit within a few seconds.
+    pub async fn stop_gracefully(
+        &self,
+        child: &mut tokio::process::Child,
+    ) -> Result<(), RalphError> {
+        #[cfg(unix)]
+        if let Some(pid) = child.id() {
+            let signal = self.backend.stop_signal();
+            let sent = Command::new("kill")
+                .arg(format!("-{}", signal))
+                .arg(pid.to_string())
+                .status()
+                .await
+                .map(|s| s.success())
+                .unwrap_or(false);
+
+            if sent {
+                match tokio::time::timeout(std::time::Duration::from_secs(5), child.wait()).await {
+                    Ok(Ok(status)) => {
+                        tracing::info!("{} exited after SIG{}: {}", self.backend.name(), signal, status);
+                        return Ok(());
+                    }
+                    Ok(Err(e)) => tracing::warn!("Failed waiting for {}: {}", self.backend.name(), e),
+                    Err(_) => tracing::warn!("{} ignored SIG{}, killing", self.backend.name(), signal),
+                }
+            }
+        }
+
+        self.terminate(child).await
+    }
+
+    pub async fn terminate(&self, child: &mut tokio::process::Child) -> Result<(), RalphError> {
+        // Best-effort termination of the running agent process.
+        if let Err(e) = child.kill().await {
+            tracing::warn!("Failed to kill {} process: {}", self.backend.name(), e);
+            return Err(RalphError::Agent(format!(
+                "Failed to kill {} process: {}",
+                self.backend.name(),
+                e
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Resolves at `deadline`, or never when there is none.
+async fn sleep_until_deadline(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::OutputParser;
+
+    /// Runs a shell script and reports every stdout line as assistant text.
+    struct ScriptBackend {
+        script: String,
+    }
+
+    struct LineParser;
+
+    impl OutputParser for LineParser {
+        fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError> {
+            Ok(vec![ActivityKind::Assistant(line.to_string())])
+        }
+    }
+
+    impl AgentBackend for ScriptBackend {
+        fn name(&self) -> &str {
+            "script"
+        }
+
+        fn models(&self) -> Vec<AgentModel> {
+            vec![AgentModel::new("default", "Default")]
+        }
+
+        fn iteration_command(&self, _prompt: &str) -> Command {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(&self.script);
+            command
+        }
+
+        fn chat_command(&self, prompt: &str) -> Command {
+            let mut command = Command::new("echo");
+            command.arg(prompt);
+            command
+        }
+
+        fn output_parser(&self) -> Box<dyn OutputParser> {
+            Box::new(LineParser)
+        }
+    }
+
+    fn runner(script: &str) -> AgentRunner {
+        AgentRunner::new(
+            std::env::temp_dir().to_string_lossy().to_string(),
+            Box::new(ScriptBackend {
+                script: script.to_string(),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_run_iteration_reports_lines() {
+        let (_tx, rx) = watch::channel(None);
+        let mut seen = Vec::new();
+
+        runner("echo one; echo; echo two")
+            .run_iteration("prompt", rx, |entry| {
+                seen.push(entry.kind);
+                AgentControl::Continue
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            seen,
+            vec![
+                ActivityKind::Assistant("one".into()),
+                ActivityKind::Assistant("two".into())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_ends_agent_after_grace_period() {
+        let (_tx, rx) = watch::channel(None);
+
+        let started = std::time::Instant::now();
+        runner("echo working; sleep 30")
+            .with_stop_grace_period(std::time::Duration::from_millis(100))
+            .run_iteration("prompt", rx, |_| AgentControl::Stop)
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_stop_interrupt_kills_agent_immediately() {
+        let (tx, rx) = watch::channel(None);
+        let mut seen = 0;
+
+        let started = std::time::Instant::now();
+        runner("echo working; sleep 30")
+            .run_iteration("prompt", rx, |_| {
+                seen += 1;
+                let _ = tx.send(Some(Interrupt::Stop));
+                AgentControl::Continue
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(seen, 1);
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_pause_waits_for_current_tool_call() {
+        /// Reports `tool` lines as finished shell commands.
+        struct ToolParser;
+
+        impl OutputParser for ToolParser {
+            fn parse_line(&mut self, line: &str) -> Result<Vec<ActivityKind>, RalphError> {
+                Ok(vec![match line.strip_prefix("tool ") {
+                    Some(command) => ActivityKind::Shell {
+                        command: command.to_string(),
+                        exit_code: 0,
+                        output_bytes: 0,
+                    },
+                    None => ActivityKind::Assistant(line.to_string()),
+                }])
+            }
+        }
+
+        struct ToolBackend(ScriptBackend);
+
+        impl AgentBackend for ToolBackend {
+            fn name(&self) -> &str {
+                self.0.name()
+            }
+            fn models(&self) -> Vec<AgentModel> {
+                self.0.models()
+            }
+            fn iteration_command(&self, prompt: &str) -> Command {
+                self.0.iteration_command(prompt)
+            }
+            fn chat_command(&self, prompt: &str) -> Command {
+                self.0.chat_command(prompt)
+            }
+            fn output_parser(&self) -> Box<dyn OutputParser> {
+                Box::new(ToolParser)
+            }
+        }
+
+        let (tx, rx) = watch::channel(None);
+        let mut seen = Vec::new();
+        let runner = AgentRunner::new(
+            std::env::temp_dir().to_string_lossy().to_string(),
+            Box::new(ToolBackend(ScriptBackend {
+                script: "echo thinking; sleep 0.3; echo 'tool cargo build'; echo 'tool cargo test'; sleep 30"
+                    .to_string(),
+            })),
+        );
+
+        runner
+            .run_iteration("prompt", rx, |entry| {
+                // Pause while the first tool call is still running.
+                if seen.is_empty() {
+                    let _ = tx.send(Some(Interrupt::Pause));
+                }
+                seen.push(entry.kind);
+                AgentControl::Continue
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(seen.len(), 2, "agent kept running after pause: {:?}", seen);
+        assert!(matches!(&seen[1], ActivityKind::Shell { command, .. } if command == "cargo build"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_returns_trimmed_output() {
+        let response = runner("true").chat("  hello  ").await.unwrap();
+        assert_eq!(response, "hello");
+    }
+}
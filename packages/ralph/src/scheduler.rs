@@ -0,0 +1,155 @@
+use std::collections::{HashMap, VecDeque};
+
+/// How many sessions may run at once unless configured otherwise.
+pub const DEFAULT_MAX_CONCURRENT_SESSIONS: usize = 2;
+
+/// Decides which queued sessions may start.
+///
+/// At most `max_running` sessions run at once, and never two in the same
+/// checkout: a repository's own working tree, or one branch of it when
+/// sessions run in worktrees. Sessions start in the order they were queued;
+/// one whose checkout is busy keeps its place while later sessions for other
+/// checkouts go ahead.
+#[derive(Debug)]
+pub struct Scheduler {
+    max_running: usize,
+    queue: VecDeque<(String, String)>,
+    running: HashMap<String, String>,
+    closed: bool,
+}
+
+impl Scheduler {
+    pub fn new(max_running: usize) -> Self {
+        Self {
+            max_running: max_running.max(1),
+            queue: VecDeque::new(),
+            running: HashMap::new(),
+            closed: false,
+        }
+    }
+
+    pub fn max_running(&self) -> usize {
+        self.max_running
+    }
+
+    /// Add a session to the back of the queue. Queueing it twice is a no-op.
+    pub fn enqueue(&mut self, id: &str, checkout: &str) {
+        if !self.queue.iter().any(|(queued, _)| queued == id) {
+            self.queue.push_back((id.to_string(), checkout.to_string()));
+        }
+    }
+
+    /// Take a session out of the queue; false if it was not queued.
+    pub fn dequeue(&mut self, id: &str) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|(queued, _)| queued != id);
+        self.queue.len() != before
+    }
+
+    /// Release the slot and checkout held by a running session.
+    pub fn finish(&mut self, id: &str) {
+        self.running.remove(id);
+    }
+
+    /// Whether a running session occupies `checkout`.
+    pub fn is_busy(&self, checkout: &str) -> bool {
+        self.running.values().any(|c| c == checkout)
+    }
+
+    /// Stop starting sessions, e.g. during server shutdown.
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    /// Pop every queued session that may start now, in queue order, and count
+    /// them as running.
+    pub fn next_ready(&mut self) -> Vec<String> {
+        let mut ready = Vec::new();
+        if self.closed {
+            return ready;
+        }
+
+        let mut index = 0;
+        while index < self.queue.len() && self.running.len() < self.max_running {
+            let (id, checkout) = &self.queue[index];
+            let busy = self.running.contains_key(id) || self.is_busy(checkout);
+            if busy {
+                index += 1;
+                continue;
+            }
+            if let Some((id, checkout)) = self.queue.remove(index) {
+                self.running.insert(id.clone(), checkout);
+                ready.push(id);
+            }
+        }
+
+        ready
+    }
+
+    /// 1-based place in the queue.
+    pub fn position(&self, id: &str) -> Option<u32> {
+        self.queue
+            .iter()
+            .position(|(queued, _)| queued == id)
+            .map(|index| index as u32 + 1)
+    }
+
+    /// Queued session ids, front first.
+    pub fn queued(&self) -> Vec<String> {
+        self.queue.iter().map(|(id, _)| id.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_and_fifo_order() {
+        let mut scheduler = Scheduler::new(2);
+        scheduler.enqueue("a", "/repo/a");
+        scheduler.enqueue("b", "/repo/b");
+        scheduler.enqueue("c", "/repo/c");
+
+        assert_eq!(scheduler.next_ready(), vec!["a", "b"]);
+        assert_eq!(scheduler.position("c"), Some(1));
+        assert!(scheduler.next_ready().is_empty());
+
+        scheduler.finish("b");
+        assert_eq!(scheduler.next_ready(), vec!["c"]);
+        assert_eq!(scheduler.position("c"), None);
+    }
+
+    #[test]
+    fn test_one_session_per_checkout() {
+        let mut scheduler = Scheduler::new(3);
+        scheduler.enqueue("a", "/repo/shared");
+        scheduler.enqueue("b", "/repo/shared");
+        scheduler.enqueue("c", "/repo/other");
+
+        // "b" waits for the checkout but keeps its place ahead of later work.
+        assert_eq!(scheduler.next_ready(), vec!["a", "c"]);
+        assert_eq!(scheduler.queued(), vec!["b"]);
+        assert!(scheduler.is_busy("/repo/shared"));
+
+        scheduler.enqueue("d", "/repo/new");
+        scheduler.finish("a");
+        assert_eq!(scheduler.next_ready(), vec!["b", "d"]);
+    }
+
+    #[test]
+    fn test_dequeue_and_close() {
+        let mut scheduler = Scheduler::new(1);
+        scheduler.enqueue("a", "/repo/a");
+        scheduler.enqueue("a", "/repo/a");
+        scheduler.enqueue("b", "/repo/b");
+        assert_eq!(scheduler.queued(), vec!["a", "b"]);
+
+        assert!(scheduler.dequeue("a"));
+        assert!(!scheduler.dequeue("a"));
+        assert_eq!(scheduler.position("b"), Some(1));
+
+        scheduler.close();
+        assert!(scheduler.next_ready().is_empty());
+    }
+}
@@ -1,19 +1,90 @@
-use crate::cursor::CursorRunner;
+use crate::activity::ActivityJournal;
+use crate::backend::BackendFactory;
+use crate::forge::{forge_for, ForgeFactory, ForgeProvider, NewPullRequest, PullRequestUpdate};
+use crate::dependencies::dependencies_met;
+use crate::git::GitOperations;
 use crate::guardrails::GuardrailManager;
+use crate::gutter::GutterMonitor;
 use crate::parser::StreamParser;
+use crate::prd::{parse_prd, validate_prd};
+use crate::prd_sync::merge_prd;
+use crate::pr::{pr_body, pr_title};
+use crate::prompt::{tail_lines, PromptTemplate};
+use crate::runner::{AgentControl, AgentRunner, Interrupt};
+use crate::scheduler::{Scheduler, DEFAULT_MAX_CONCURRENT_SESSIONS};
+use crate::store::SessionStore;
 use crate::types::*;
-use std::path::PathBuf;
-use std::time::SystemTime;
-use tokio::sync::{broadcast, mpsc};
+use crate::verify::StoryVerifier;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Paths Ralph itself writes into a project checkout; changes to them are not
+/// the developer's.
+const RALPH_PATHS: &[&str] = &[".ralph", "prd.json"];
+
+/// How many lines of `.ralph/progress.md` to include in the iteration prompt.
+const PROGRESS_TAIL_LINES: usize = 60;
+
+/// How many recent commits to include in the iteration prompt.
+const RECENT_COMMITS: usize = 10;
+
+/// How often a running session's prd.json is checked for outside edits.
+const PRD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of recent activity events kept in memory per session so that
+/// reconnecting clients can catch up without missing anything.
+const ACTIVITY_BACKLOG: usize = 1000;
+
+/// Per-session activity fan-out state.
+#[derive(Default)]
+struct ActivityChannel {
+    /// `last_seq` has been seeded from the on-disk journal.
+    seeded: bool,
+    last_seq: u64,
+    backlog: VecDeque<ActivityEvent>,
+    subscribers: Vec<mpsc::UnboundedSender<ActivityEvent>>,
+}
+
+/// A session's running loop and the means to interrupt it.
+///
+/// The entry stays in place until the loop has recorded its final state, so
+/// the session keeps its scheduler slot and repository until then. `handle`
+/// is taken by whoever waits for the loop to end.
+struct SessionTask {
+    interrupt: watch::Sender<Option<Interrupt>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Watches a session's prd.json until dropped.
+struct PrdWatch(JoinHandle<()>);
+
+impl Drop for PrdWatch {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
 
 pub struct SessionManager {
     sessions: std::sync::Arc<tokio::sync::RwLock<std::collections::HashMap<String, Session>>>,
+    /// Each session's channel has its own lock, so writing one session's
+    /// activity to disk doesn't hold up another's.
     activity_channels: std::sync::Arc<
-        tokio::sync::RwLock<
-            std::collections::HashMap<String, Vec<mpsc::UnboundedSender<ActivityEntry>>>,
-        >,
+        tokio::sync::RwLock<std::collections::HashMap<String, std::sync::Arc<tokio::sync::Mutex<ActivityChannel>>>>,
     >,
     shutdown_tx: broadcast::Sender<()>,
+    store: Option<std::sync::Arc<SessionStore>>,
+    backend_factory: Option<BackendFactory>,
+    forge_factory: Option<ForgeFactory>,
+    tasks: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, SessionTask>>>,
+    scheduler: std::sync::Arc<std::sync::Mutex<Scheduler>>,
+    /// Managed directory holding the sessions' worktrees.
+    worktree_root: PathBuf,
+    /// The PRD each prd.json last agreed with the session on, by path; the
+    /// base for merging edits made on disk.
+    synced_prds: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<PathBuf, Prd>>>,
 }
 
 impl SessionManager {
@@ -27,6 +98,92 @@ impl SessionManager {
                 std::collections::HashMap::new(),
             )),
             shutdown_tx,
+            store: None,
+            backend_factory: None,
+            forge_factory: None,
+            tasks: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+            scheduler: std::sync::Arc::new(std::sync::Mutex::new(Scheduler::new(
+                DEFAULT_MAX_CONCURRENT_SESSIONS,
+            ))),
+            worktree_root: std::env::temp_dir().join("ralph-worktrees"),
+            synced_prds: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+        }
+    }
+
+    /// Create a manager backed by a durable store, rehydrating any sessions
+    /// persisted by a previous server process.
+    ///
+    /// Sessions that were queued or mid-run when the process died have no loop
+    /// driving them anymore, so they come back as `Paused` and can be resumed.
+    pub fn with_store(store: SessionStore) -> Self {
+        let mut sessions = std::collections::HashMap::new();
+
+        match store.load_all() {
+            Ok(loaded) => {
+                for mut session in loaded {
+                    if matches!(
+                        session.status,
+                        SessionStatus::Queued { .. }
+                            | SessionStatus::Running { .. }
+                            | SessionStatus::WaitingForRotation
+                    ) {
+                        tracing::info!(
+                            "Session {} was queued or running before restart, marking as paused",
+                            session.id
+                        );
+                        session.status = SessionStatus::Paused;
+                        session.updated_at = SystemTime::now();
+                    }
+                    sessions.insert(session.id.clone(), session);
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to load sessions from {:?}: {}",
+                    store.state_dir(),
+                    e
+                );
+            }
+        }
+
+        let mut manager = Self::new();
+        manager.sessions = std::sync::Arc::new(tokio::sync::RwLock::new(sessions));
+        manager.store = Some(std::sync::Arc::new(store));
+        manager
+    }
+
+    /// Build each iteration's agent backend with `factory` instead of from the
+    /// session config, e.g. to drive sessions with a `FakeAgentBackend`.
+    pub fn with_backend_factory(mut self, factory: BackendFactory) -> Self {
+        self.backend_factory = Some(factory);
+        self
+    }
+
+    /// Open pull requests through `factory` instead of the forge selected in
+    /// the session config, e.g. to record them with a `FakeForge`.
+    pub fn with_forge_factory(mut self, factory: ForgeFactory) -> Self {
+        self.forge_factory = Some(factory);
+        self
+    }
+
+    /// Create session worktrees under `root` instead of the temp directory.
+    pub fn with_worktree_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.worktree_root = root.into();
+        self
+    }
+
+    /// Run at most `max` sessions at once; further starts wait in the queue.
+    pub fn with_max_concurrent_sessions(self, max: usize) -> Self {
+        *self.lock_scheduler() = Scheduler::new(max);
+        self
+    }
+
+    async fn persist(&self, session: &Session) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(session).await {
+                // Persistence failures must not take down a running loop.
+                tracing::error!("Failed to persist session {}: {}", session.id, e);
+            }
         }
     }
 
@@ -35,10 +192,28 @@ impl SessionManager {
     }
 
     pub fn shutdown(&self) {
+        // Nothing new starts; running agents are killed and their sessions,
+        // like queued ones, come back as paused.
+        self.lock_scheduler().close();
+        for task in self.lock_tasks().values() {
+            let _ = task.interrupt.send(Some(Interrupt::Shutdown));
+        }
         // It's ok if there are no active receivers.
         let _ = self.shutdown_tx.send(());
     }
 
+    fn lock_tasks(&self) -> std::sync::MutexGuard<'_, std::collections::HashMap<String, SessionTask>> {
+        self.tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_scheduler(&self) -> std::sync::MutexGuard<'_, Scheduler> {
+        self.scheduler.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_synced_prds(&self) -> std::sync::MutexGuard<'_, std::collections::HashMap<PathBuf, Prd>> {
+        self.synced_prds.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     pub async fn create_session(
         &self,
         project_path: String,
@@ -77,12 +252,18 @@ impl SessionManager {
             prd: None,
             current_iteration: 0,
             token_usage: TokenUsage::default(),
+            handoff_notes: None,
+            handoff_reason: None,
+            worktree_path: None,
+            base_commit: None,
+            pr_url: None,
+            pr_number: None,
+            iterations: Vec::new(),
             created_at: SystemTime::now(),
             updated_at: SystemTime::now(),
         };
 
-        let mut sessions = self.sessions.write().await;
-        sessions.insert(id.clone(), session.clone());
+        self.update_session(session.clone()).await?;
 
         tracing::info!("Session {} created successfully", id);
 
@@ -103,7 +284,9 @@ impl SessionManager {
     }
 
     pub async fn update_session(&self, session: Session) -> Result<(), RalphError> {
+        // Persist while holding the lock so concurrent updates hit disk in order.
         let mut sessions = self.sessions.write().await;
+        self.persist(&session).await;
         sessions.insert(session.id.clone(), session);
         Ok(())
     }
@@ -135,6 +318,10 @@ impl SessionManager {
                     .to_string(),
             ));
         }
+        if let Err(e) = validate_prd(prd) {
+            tracing::error!("Cannot start session {}: {}", id, e);
+            return Err(e);
+        }
 
         tracing::info!(
             "Session {} has {} stories to process",
@@ -142,14 +329,86 @@ impl SessionManager {
             prd.stories.len()
         );
 
-        // Spawn the Ralph loop in a background task
-        let session_clone = session.clone();
+        // A session about to work in the project checkout must not trample
+        // the developer's changes. While another session holds the checkout
+        // this is checked once the session's turn comes.
+        let checkout = checkout_key(&session);
+        if !session.config.use_worktree && !self.lock_scheduler().is_busy(&checkout) {
+            self.clean_checkout(&session, false).await?;
+        }
+
+        // Queue first; the scheduler starts it right away if it can
+        self.lock_scheduler().enqueue(id, &checkout);
+        session.status = SessionStatus::Queued { position: 0 };
+        session.updated_at = SystemTime::now();
+        self.update_session(session).await?;
+
+        self.schedule().await;
+
+        let session = self.get_session(id).await?;
+        match &session.status {
+            SessionStatus::Queued { position } => {
+                tracing::info!("⏳ Session {} queued at position {}", id, position)
+            }
+            _ => tracing::info!("Session {} started successfully", id),
+        }
+
+        Ok(session)
+    }
+
+    /// Start every queued session the scheduler lets through, then refresh the
+    /// queue positions of those still waiting.
+    async fn schedule(&self) {
+        let ready = self.lock_scheduler().next_ready();
+        for id in ready {
+            if let Err(e) = self.launch(&id).await {
+                tracing::error!("Failed to launch session {}: {}", id, e);
+                self.lock_scheduler().finish(&id);
+            }
+        }
+
+        let queued = self.lock_scheduler().queued();
+        for (index, id) in queued.iter().enumerate() {
+            let position = index as u32 + 1;
+            let Ok(mut session) = self.get_session(id).await else {
+                continue;
+            };
+            if session.status != (SessionStatus::Queued { position }) {
+                session.status = SessionStatus::Queued { position };
+                session.updated_at = SystemTime::now();
+                let _ = self.update_session(session).await;
+            }
+        }
+    }
+
+    /// `schedule` behind a trait object: it can launch a session whose monitor
+    /// schedules again, and the compiler cannot prove that cycle `Send`.
+    fn schedule_boxed(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + '_>> {
+        Box::pin(self.schedule())
+    }
+
+    /// Spawn the Ralph loop for a session the scheduler has let through.
+    async fn launch(&self, id: &str) -> Result<(), RalphError> {
+        let mut session = self.get_session(id).await?;
+        if !matches!(session.status, SessionStatus::Queued { .. }) {
+            // Paused or stopped while waiting for its turn.
+            self.lock_scheduler().finish(id);
+            return Ok(());
+        }
+
+        // Mark the session running before the loop can observe it as queued
+        session.status = SessionStatus::Running {
+            story_id: "initializing".to_string(),
+        };
+        session.updated_at = SystemTime::now();
+        self.update_session(session.clone()).await?;
+
         let manager_clone = self.clone();
-        let shutdown_rx = self.subscribe_shutdown();
+        let (interrupt_tx, interrupt_rx) = watch::channel(None);
 
         tracing::debug!("Spawning Ralph loop for session {}", id);
         let handle = tokio::spawn(async move {
-            match manager_clone.run_loop(session_clone, shutdown_rx).await {
+            match manager_clone.run_loop(session, interrupt_rx).await {
                 Ok(()) => {
                     tracing::info!("Ralph loop completed successfully");
                 }
@@ -159,8 +418,11 @@ impl SessionManager {
             }
         });
 
-        // Spawn a monitoring task to detect if the main loop panics
-        tokio::spawn(async move {
+        // Spawn a monitoring task to detect if the main loop panics, and to
+        // hand the slot to the next queued session once it has ended
+        let manager_clone = self.clone();
+        let session_id = id.to_string();
+        let monitor = tokio::spawn(async move {
             if let Err(e) = handle.await {
                 if e.is_panic() {
                     tracing::error!("FATAL: Ralph loop panicked - this indicates a serious bug that should be fixed");
@@ -169,41 +431,88 @@ impl SessionManager {
                     tracing::warn!("Ralph loop was cancelled");
                 }
             }
-        });
 
-        session.status = SessionStatus::Running {
-            story_id: "initializing".to_string(),
-        };
-        session.updated_at = SystemTime::now();
-        self.update_session(session.clone()).await?;
+            manager_clone.lock_tasks().remove(&session_id);
+            manager_clone.lock_scheduler().finish(&session_id);
+            manager_clone.schedule_boxed().await;
+        });
 
-        tracing::info!("Session {} started successfully", id);
+        self.lock_tasks().insert(
+            id.to_string(),
+            SessionTask {
+                interrupt: interrupt_tx,
+                handle: Some(monitor),
+            },
+        );
 
-        Ok(session)
+        Ok(())
     }
 
+    /// Let the agent finish its current tool call, save its work and suspend
+    /// the session. Returns once the loop has stopped.
     pub async fn pause_session(&self, id: &str) -> Result<Session, RalphError> {
-        let mut session = self.get_session(id).await?;
-        session.status = SessionStatus::Paused;
-        session.updated_at = SystemTime::now();
-        self.update_session(session.clone()).await?;
-        Ok(session)
+        tracing::info!("Pausing session: {}", id);
+        self.interrupt_session(id, Interrupt::Pause, SessionStatus::Paused).await
     }
 
+    /// Kill the session's agent right away, commit what it left behind and
+    /// return the session to idle. Returns once the loop has stopped.
     pub async fn stop_session(&self, id: &str) -> Result<Session, RalphError> {
-        let mut session = self.get_session(id).await?;
-        session.status = SessionStatus::Idle;
-        session.updated_at = SystemTime::now();
-        self.update_session(session.clone()).await?;
-        Ok(session)
+        tracing::info!("Stopping session: {}", id);
+        self.interrupt_session(id, Interrupt::Stop, SessionStatus::Idle).await
+    }
+
+    /// Interrupt the session's loop and wait for it to record its final state.
+    /// A queued session leaves the queue; either way, without a running loop
+    /// the session is simply set to `status`.
+    async fn interrupt_session(
+        &self,
+        id: &str,
+        interrupt: Interrupt,
+        status: SessionStatus,
+    ) -> Result<Session, RalphError> {
+        let running = self.lock_tasks().get_mut(id).map(|task| {
+            let _ = task.interrupt.send(Some(interrupt));
+            task.handle.take()
+        });
+
+        match running {
+            Some(Some(handle)) => {
+                if let Err(e) = handle.await {
+                    tracing::error!("Session {} loop did not finish cleanly: {}", id, e);
+                }
+                self.get_session(id).await
+            }
+            // Someone else is already waiting for this loop to end.
+            Some(None) => self.get_session(id).await,
+            None => {
+                let dequeued = self.lock_scheduler().dequeue(id);
+                if dequeued {
+                    tracing::info!("Session {} removed from the queue", id);
+                }
+
+                let mut session = self.get_session(id).await?;
+                session.status = status;
+                session.updated_at = SystemTime::now();
+                self.update_session(session.clone()).await?;
+                if dequeued {
+                    self.schedule().await;
+                }
+                Ok(session)
+            }
+        }
     }
 
     pub async fn set_prd(&self, id: &str, prd: Prd) -> Result<Session, RalphError> {
         tracing::info!("Setting PRD for session {}", id);
+        validate_prd(&prd)?;
         let mut session = self.get_session(id).await?;
 
-        // Write PRD to disk
-        self.write_prd_to_disk(&session.project_path, &prd).await?;
+        // Write PRD to disk; a worktree session's PRD is written into its
+        // worktree once that exists, leaving the project checkout alone
+        if !session.config.use_worktree || session.worktree_path.is_some() {
+            self.write_prd_to_disk(session.work_dir(), &prd).await?;
+        }
 
         session.prd = Some(prd);
         session.updated_at = SystemTime::now();
@@ -212,48 +521,318 @@ impl SessionManager {
     }
 
     async fn write_prd_to_disk(&self, project_path: &str, prd: &Prd) -> Result<(), RalphError> {
-        use std::path::Path;
-
         tracing::debug!("Writing prd.json to {}", project_path);
         let prd_path = Path::new(project_path).join("prd.json");
         let json = serde_json::to_string_pretty(prd)
             .map_err(|e| RalphError::Io(format!("Failed to serialize PRD: {}", e)))?;
 
+        // Recorded first so the watcher never mistakes this write for an edit.
+        self.lock_synced_prds().insert(prd_path.clone(), prd.clone());
         tokio::fs::write(&prd_path, json).await?;
         tracing::info!("PRD written to {:?}", prd_path);
         Ok(())
     }
 
+    /// Merge edits made to the session's prd.json since Ralph last wrote or
+    /// read it into the session's PRD, reporting what was taken and what
+    /// clashed with the session's own changes.
+    async fn sync_prd(&self, session: &mut Session) {
+        let (Some(path), Some(ours)) = (prd_path(session), session.prd.clone()) else {
+            return;
+        };
+        let Ok(json) = tokio::fs::read_to_string(&path).await else {
+            return;
+        };
+        let theirs = match parse_prd(&json) {
+            Ok(prd) => prd,
+            Err(e) => {
+                tracing::warn!("Ignoring edits to {:?} for session {}: {}", path, session.id, e);
+                return;
+            }
+        };
+        let base = self.lock_synced_prds().get(&path).cloned().unwrap_or_else(|| ours.clone());
+        if theirs == base {
+            return;
+        }
+
+        let merge = merge_prd(&base, &ours, &theirs);
+        if let Err(e) = validate_prd(&merge.prd) {
+            tracing::warn!("Ignoring edits to {:?} for session {}: {}", path, session.id, e);
+            return;
+        }
+        self.lock_synced_prds().insert(path.clone(), merge.prd.clone());
+        // Put back what the session kept over the edits, or the next sync
+        // would take them as new edits against the merge.
+        if merge.prd != theirs {
+            if let Some(dir) = path.parent() {
+                if let Err(e) = self.write_prd_to_disk(&dir.to_string_lossy(), &merge.prd).await {
+                    tracing::warn!("Failed to write merged prd.json for session {}: {}", session.id, e);
+                }
+            }
+        }
+        session.prd = Some(merge.prd);
+        if merge.changes.is_empty() && merge.conflicts.is_empty() {
+            return;
+        }
+
+        tracing::info!("📝 Merged prd.json edits into session {}: {:?}", session.id, merge.changes);
+        if !merge.conflicts.is_empty() {
+            tracing::warn!("⚠️  prd.json edits clashed for session {}: {:?}", session.id, merge.conflicts);
+        }
+        let entry = ActivityEntry {
+            timestamp: SystemTime::now(),
+            iteration: session.current_iteration,
+            kind: ActivityKind::PrdEdited(PrdEdit {
+                changes: merge.changes,
+                conflicts: merge.conflicts,
+                merged: true,
+            }),
+            health: session.token_usage.health(
+                session.config.warn_threshold,
+                session.config.rotate_threshold,
+            ),
+            tokens: None,
+        };
+        self.broadcast_activity(&session.id, entry).await;
+    }
+
+    /// Write the session's PRD to its prd.json, merging in edits made there
+    /// first so neither side's changes are lost.
+    async fn save_prd(&self, session: &mut Session) -> Result<(), RalphError> {
+        self.sync_prd(session).await;
+        if let (Some(path), Some(prd)) = (prd_path(session), &session.prd) {
+            if let Some(dir) = path.parent() {
+                self.write_prd_to_disk(&dir.to_string_lossy(), prd).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Report edits to the session's prd.json as they happen while its loop
+    /// runs; the loop merges them the next time it syncs.
+    fn watch_prd(&self, session: &Session) -> Option<PrdWatch> {
+        let path = prd_path(session)?;
+        let manager = self.clone();
+        let id = session.id.clone();
+        let handle = tokio::spawn(async move {
+            let mut last = tokio::fs::read_to_string(&path).await.ok();
+            let mut ticker = tokio::time::interval(PRD_POLL_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let Ok(json) = tokio::fs::read_to_string(&path).await else {
+                    continue;
+                };
+                if last.as_deref() == Some(json.as_str()) {
+                    continue;
+                }
+                last = Some(json.clone());
+                let Ok(session) = manager.get_session(&id).await else {
+                    return;
+                };
+
+                let kind = match parse_prd(&json) {
+                    Ok(theirs) => {
+                        let base = manager.lock_synced_prds().get(&path).cloned();
+                        let Some(base) = base.or(session.prd.clone()) else {
+                            continue;
+                        };
+                        let merge = merge_prd(&base, &base, &theirs);
+                        let mut changes = merge.changes;
+                        changes.extend(merge.claimed_done.iter().map(|id| format!("{} marked done", id)));
+                        if changes.is_empty() {
+                            continue;
+                        }
+                        tracing::info!("📝 prd.json for session {} changed on disk: {:?}", id, changes);
+                        ActivityKind::PrdEdited(PrdEdit {
+                            changes,
+                            conflicts: Vec::new(),
+                            merged: false,
+                        })
+                    }
+                    Err(e) => {
+                        tracing::warn!("prd.json for session {} is not a valid PRD: {}", id, e);
+                        ActivityKind::Error(format!("prd.json is not a valid PRD, ignoring it: {}", e))
+                    }
+                };
+                let entry = ActivityEntry {
+                    timestamp: SystemTime::now(),
+                    iteration: session.current_iteration,
+                    kind,
+                    health: session.token_usage.health(
+                        session.config.warn_threshold,
+                        session.config.rotate_threshold,
+                    ),
+                    tokens: None,
+                };
+                manager.broadcast_activity(&id, entry).await;
+            }
+        });
+        Some(PrdWatch(handle))
+    }
+
+    fn activity_journal(session: Option<&Session>) -> Option<ActivityJournal> {
+        session.map(|s| ActivityJournal::new(&s.project_path))
+    }
+
+    /// Make sure the channel continues the sequence recorded on disk rather than
+    /// restarting at 1 after a server restart.
+    async fn seed_activity_channel(
+        channel: &mut ActivityChannel,
+        session_id: &str,
+        journal: Option<&ActivityJournal>,
+    ) {
+        if channel.seeded {
+            return;
+        }
+        channel.seeded = true;
+
+        if let Some(journal) = journal {
+            match journal.last_seq(session_id).await {
+                Ok(seq) => channel.last_seq = channel.last_seq.max(seq),
+                Err(e) => tracing::warn!("Failed to read activity journal for {}: {}", session_id, e),
+            }
+        }
+    }
+
+    /// Subscribe to a session's activity.
+    ///
+    /// When `since` is given, events with a greater sequence number are queued
+    /// on the receiver first, so a reconnecting client resumes exactly where it
+    /// left off. Recent events come from memory; older ones from the journal.
     pub async fn subscribe_to_activity(
         &self,
         session_id: &str,
-    ) -> mpsc::UnboundedReceiver<ActivityEntry> {
+        since: Option<u64>,
+    ) -> mpsc::UnboundedReceiver<ActivityEvent> {
+        let journal = Self::activity_journal(self.get_session(session_id).await.ok().as_ref());
+        let channel = self.activity_channel(session_id).await;
+
+        // Read older events from the journal before locking the channel, so a
+        // long history doesn't hold up the session's activity.
+        let mut replay = Vec::new();
+        if let (Some(since), Some(journal)) = (since, &journal) {
+            let backlog_covers = {
+                let mut channel = channel.lock().await;
+                Self::seed_activity_channel(&mut channel, session_id, Some(journal)).await;
+                channel
+                    .backlog
+                    .front()
+                    .map(|first| first.seq <= since.saturating_add(1))
+                    .unwrap_or(since >= channel.last_seq)
+            };
+            if !backlog_covers {
+                match journal
+                    .read(session_id, since, usize::MAX, &ActivityFilter::default())
+                    .await
+                {
+                    Ok(page) => replay = page.events,
+                    Err(e) => tracing::warn!(
+                        "Failed to replay activity journal for {}: {}",
+                        session_id,
+                        e
+                    ),
+                }
+            }
+        }
+
         let (tx, rx) = mpsc::unbounded_channel();
-        let mut channels = self.activity_channels.write().await;
-        channels
-            .entry(session_id.to_string())
-            .or_insert_with(Vec::new)
-            .push(tx);
+        let mut channel = channel.lock().await;
+        Self::seed_activity_channel(&mut channel, session_id, journal.as_ref()).await;
+        if let Some(since) = since {
+            // Events broadcast since the journal was read are in the backlog.
+            let replayed = replay.last().map_or(since, |event| event.seq.max(since));
+            for event in replay {
+                let _ = tx.send(event);
+            }
+            for event in channel.backlog.iter().filter(|e| e.seq > replayed) {
+                let _ = tx.send(event.clone());
+            }
+        }
+
+        channel.subscribers.push(tx);
         rx
     }
 
+    /// The activity channel of `session_id`, created on first use.
+    async fn activity_channel(&self, session_id: &str) -> std::sync::Arc<tokio::sync::Mutex<ActivityChannel>> {
+        if let Some(channel) = self.activity_channels.read().await.get(session_id) {
+            return channel.clone();
+        }
+        self.activity_channels
+            .write()
+            .await
+            .entry(session_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Page through a session's persisted activity history.
+    pub async fn get_activity(
+        &self,
+        session_id: &str,
+        since: u64,
+        limit: usize,
+        filter: &ActivityFilter,
+    ) -> Result<ActivityPage, RalphError> {
+        let session = self.get_session(session_id).await?;
+        ActivityJournal::new(&session.project_path)
+            .read(session_id, since, limit, filter)
+            .await
+    }
+
     async fn broadcast_activity(&self, session_id: &str, entry: ActivityEntry) {
-        let channels = self.activity_channels.read().await;
-        if let Some(senders) = channels.get(session_id) {
-            for sender in senders {
-                let _ = sender.send(entry.clone());
+        let journal = Self::activity_journal(self.get_session(session_id).await.ok().as_ref());
+
+        let channel = self.activity_channel(session_id).await;
+        let mut channel = channel.lock().await;
+        Self::seed_activity_channel(&mut channel, session_id, journal.as_ref()).await;
+
+        channel.last_seq += 1;
+        let event = ActivityEvent {
+            seq: channel.last_seq,
+            entry,
+        };
+
+        // Persist first so history survives even when nobody is subscribed.
+        if let Some(journal) = &journal {
+            if let Err(e) = journal.append(session_id, &event).await {
+                tracing::error!("Failed to append activity for {}: {}", session_id, e);
             }
         }
+
+        channel.backlog.push_back(event.clone());
+        while channel.backlog.len() > ACTIVITY_BACKLOG {
+            channel.backlog.pop_front();
+        }
+
+        // Drop subscribers whose receiving end has gone away.
+        channel
+            .subscribers
+            .retain(|sender| sender.send(event.clone()).is_ok());
     }
 
     async fn run_loop(
         &self,
         mut session: Session,
-        mut shutdown_rx: broadcast::Receiver<()>,
+        interrupt_rx: watch::Receiver<Option<Interrupt>>,
     ) -> Result<(), RalphError> {
         tracing::info!("=== Starting Ralph loop for session {} ===", session.id);
         tracing::info!("Max iterations: {}", session.config.max_iterations);
         tracing::info!("Execution model: {}", session.config.execution_model);
+
+        if let Err(e) = self.prepare_checkout(&mut session).await {
+            tracing::error!("❌ Failed to check out branch for session {}: {}", session.id, e);
+            session.status = SessionStatus::Failed {
+                error: e.to_string(),
+            };
+            session.updated_at = SystemTime::now();
+            let _ = self.update_session(session).await;
+            return Err(e);
+        }
+
+        // Gutter counters start fresh with every run, so resuming resets them.
+        let mut gutter = GutterMonitor::new(&session.config.gutter_rules, session.work_dir());
+        let _prd_watch = self.watch_prd(&session);
         
         while session.current_iteration < session.config.max_iterations {
             tracing::info!(
@@ -263,57 +842,53 @@ impl SessionManager {
                 session.id
             );
             
-            // Check for shutdown signal (non-blocking)
-            match shutdown_rx.try_recv() {
-                Ok(()) => {
-                    tracing::info!("Shutdown signal received, stopping session {}", session.id);
-                    session.status = SessionStatus::Paused;
-                    session.updated_at = SystemTime::now();
-                    let _ = self.update_session(session).await;
-                    return Ok(());
-                }
-                Err(broadcast::error::TryRecvError::Empty) => {}
-                Err(broadcast::error::TryRecvError::Closed) => {
-                    // Treat closed channel as a shutdown request.
-                    tracing::info!("Shutdown channel closed, stopping session {}", session.id);
-                    session.status = SessionStatus::Paused;
-                    session.updated_at = SystemTime::now();
-                    let _ = self.update_session(session).await;
-                    return Ok(());
-                }
-                Err(broadcast::error::TryRecvError::Lagged(_)) => {
-                    // If we missed signals, still treat it as shutdown.
-                    tracing::info!(
-                        "Shutdown signal lagged, stopping session {}",
-                        session.id
-                    );
-                    session.status = SessionStatus::Paused;
-                    session.updated_at = SystemTime::now();
-                    let _ = self.update_session(session).await;
-                    return Ok(());
-                }
+            // Pause, stop and shutdown requests take effect between iterations too
+            let requested = *interrupt_rx.borrow();
+            if let Some(interrupt) = requested {
+                return self.finish_interrupted(session, interrupt, None).await;
             }
 
-            // Check if paused or stopped
-            let current = self.get_session(&session.id).await?;
-            tracing::debug!("Current session status: {:?}", current.status);
-            if matches!(current.status, SessionStatus::Paused | SessionStatus::Idle) {
-                tracing::info!("Session {} is paused/idle, exiting loop", session.id);
-                return Ok(());
-            }
+            // Take in edits made to prd.json since the last iteration
+            self.sync_prd(&mut session).await;
 
-            // Pick next story
+            // Pick the next story whose dependencies have all passed
             tracing::debug!("Selecting next story to work on");
-            let story_id = session.prd.as_ref().and_then(|prd| {
-                prd.stories
-                    .iter()
-                    .filter(|s| !s.passes)
-                    .min_by_key(|s| s.priority)
-                    .map(|s| s.id.clone())
-            });
+            let stories = session.prd.as_ref().map(|prd| prd.stories.as_slice()).unwrap_or_default();
+            let story_id = stories
+                .iter()
+                .filter(|s| s.status.is_workable())
+                .filter(|s| dependencies_met(s, stories))
+                .min_by_key(|s| s.priority)
+                .map(|s| s.id.clone());
+
+            // Skipped stories are settled: the session completes without them.
+            let pending: Vec<String> = stories
+                .iter()
+                .filter(|s| !s.passes() && s.status != StoryStatus::Skipped)
+                .map(|s| s.id.clone())
+                .collect();
+            if story_id.is_none() && !pending.is_empty() {
+                let blocked = with_status(&session, StoryStatus::Blocked);
+                let error = if !blocked.is_empty() {
+                    format!("Stories ran out of attempts: {}", blocked.join(", "))
+                } else {
+                    format!("Stories wait on dependencies that can't pass: {}", pending.join(", "))
+                };
+                tracing::warn!("Session {} has no story it can work on: {}", session.id, error);
+                session.status = SessionStatus::Failed { error };
+                session.updated_at = SystemTime::now();
+                self.update_session(session).await?;
+                return Ok(());
+            }
 
             let Some(story_id) = story_id else {
                 tracing::info!("🎉 All stories completed for session {}!", session.id);
+                let skipped = with_status(&session, StoryStatus::Skipped);
+                if !skipped.is_empty() {
+                    tracing::info!("   Skipped: {}", skipped.join(", "));
+                }
+                self.publish(&mut session).await;
+                self.cleanup_worktree(&mut session).await;
                 session.status = SessionStatus::Complete;
                 session.updated_at = SystemTime::now();
                 self.update_session(session.clone()).await?;
@@ -326,6 +901,7 @@ impl SessionManager {
                         session.config.warn_threshold,
                         session.config.rotate_threshold,
                     ),
+                    tokens: None,
                 };
                 self.broadcast_activity(&session.id, entry).await;
 
@@ -343,28 +919,54 @@ impl SessionManager {
                 }
             }
             
+            set_story_status(&mut session, &story_id, StoryStatus::InProgress);
             session.status = SessionStatus::Running {
                 story_id: story_id.clone(),
             };
             session.updated_at = SystemTime::now();
             self.update_session(session.clone()).await?;
 
+            // Remember where HEAD was so the iteration's commits can be found later.
+            let start = IterationStart {
+                iteration: session.current_iteration,
+                story_id: story_id.clone(),
+                commit: self.head_commit(&session).await,
+                tokens: session.token_usage.total,
+                at: SystemTime::now(),
+            };
+
             // Run iteration
             tracing::info!("▶️  Starting iteration for session {}, story {}", session.id, story_id);
-            let result = self.run_iteration(&mut session).await?;
+            let result = match self
+                .run_iteration(&mut session, &story_id, start.commit.as_deref(), &mut gutter, &interrupt_rx)
+                .await
+            {
+                Ok(result) => result,
+                Err(e) if interrupt_rx.borrow().is_some() => {
+                    tracing::info!("Iteration for session {} ended by interrupt: {}", session.id, e);
+                    IterationResult::Interrupted
+                }
+                Err(e) => {
+                    tracing::error!("❌ Iteration failed for session {}: {}", session.id, e);
+                    let outcome = IterationOutcome::Failed { error: e.to_string() };
+                    self.record_iteration(&mut session, start, outcome).await;
+                    session.status = SessionStatus::Failed {
+                        error: e.to_string(),
+                    };
+                    session.updated_at = SystemTime::now();
+                    let _ = self.update_session(session).await;
+                    return Err(e);
+                }
+            };
             tracing::info!("✓ Iteration completed for session {}, story {}", session.id, story_id);
 
             match result {
                 IterationResult::StoryComplete => {
                     tracing::info!("✅ Story {} completed for session {}", story_id, session.id);
+                    self.record_iteration(&mut session, start, IterationOutcome::StoryComplete).await;
                     // Mark story as complete in PRD
-                    if let Some(prd) = &mut session.prd {
-                        if let Some(s) = prd.stories.iter_mut().find(|s| s.id == story_id) {
-                            s.passes = true;
-                        }
-                        // Write updated PRD to disk
-                        self.write_prd_to_disk(&session.project_path, prd).await?;
-                    }
+                    set_story_status(&mut session, &story_id, StoryStatus::Done);
+                    self.save_prd(&mut session).await?;
                     session.current_iteration += 1;
                     session.updated_at = SystemTime::now();
                     self.update_session(session.clone()).await?;
@@ -377,11 +979,30 @@ impl SessionManager {
                             session.config.warn_threshold,
                             session.config.rotate_threshold,
                         ),
+                        tokens: None,
                     };
                     self.broadcast_activity(&session.id, entry).await;
                 }
+                IterationResult::Unverified(reason) => {
+                    // Leave the story unmarked so the next iteration picks it up again.
+                    tracing::warn!("⚠️  {} (session {})", reason, session.id);
+                    let outcome = IterationOutcome::Unverified { reason: reason.clone() };
+                    self.record_iteration(&mut session, start, outcome).await;
+                    set_story_status(&mut session, &story_id, StoryStatus::NeedsReview);
+                    if session.config.rollback_failed {
+                        self.roll_back_failed(&mut session, &reason).await;
+                    }
+                    self.block_if_out_of_attempts(&mut session, &story_id).await;
+                    // Clear the agent's unverified `passes: true`, or the next
+                    // attempt would find the story already marked.
+                    self.save_prd(&mut session).await?;
+                    session.current_iteration += 1;
+                    session.updated_at = SystemTime::now();
+                    self.update_session(session.clone()).await?;
+                }
                 IterationResult::Rotate => {
                     tracing::info!("🔄 Rotating iteration for session {} due to token threshold", session.id);
+                    self.record_iteration(&mut session, start, IterationOutcome::Rotated).await;
                     session.current_iteration += 1;
                     session.token_usage = TokenUsage::default();
                     session.updated_at = SystemTime::now();
@@ -392,11 +1013,22 @@ impl SessionManager {
                         iteration: session.current_iteration,
                         kind: ActivityKind::Signal(Signal::Rotate),
                         health: ContextHealth::Critical,
+                        tokens: None,
                     };
                     self.broadcast_activity(&session.id, entry).await;
                 }
-                IterationResult::Gutter(reason) => {
-                    tracing::error!("🚨 Session {} entered gutter state: {}", session.id, reason);
+                IterationResult::Interrupted => {
+                    let interrupt = (*interrupt_rx.borrow()).unwrap_or(Interrupt::Stop);
+                    return self.finish_interrupted(session, interrupt, Some(start)).await;
+                }
+                IterationResult::Gutter { rule, reason } => {
+                    tracing::error!("🚨 Session {} entered gutter state ({}): {}", session.id, rule, reason);
+                    let outcome = IterationOutcome::Gutter { reason: reason.clone() };
+                    self.record_iteration(&mut session, start, outcome).await;
+                    if session.config.rollback_failed {
+                        self.roll_back_failed(&mut session, &reason).await;
+                    }
+                    self.block_if_out_of_attempts(&mut session, &story_id).await;
                     session.status = SessionStatus::Gutter {
                         reason: reason.clone(),
                     };
@@ -406,11 +1038,12 @@ impl SessionManager {
                     let entry = ActivityEntry {
                         timestamp: SystemTime::now(),
                         iteration: session.current_iteration,
-                        kind: ActivityKind::Signal(Signal::Gutter(reason)),
+                        kind: ActivityKind::Signal(Signal::Gutter { rule, reason }),
                         health: session.token_usage.health(
                             session.config.warn_threshold,
                             session.config.rotate_threshold,
                         ),
+                        tokens: None,
                     };
                     self.broadcast_activity(&session.id, entry).await;
 
@@ -432,124 +1065,995 @@ impl SessionManager {
         Ok(())
     }
 
-    async fn run_iteration(&self, session: &mut Session) -> Result<IterationResult, RalphError> {
+    async fn run_iteration(
+        &self,
+        session: &mut Session,
+        story_id: &str,
+        start_head: Option<&str>,
+        gutter: &mut GutterMonitor,
+        interrupt_rx: &watch::Receiver<Option<Interrupt>>,
+    ) -> Result<IterationResult, RalphError> {
         tracing::info!("Running iteration for session {}", session.id);
 
-        // Build prompt from iteration template
-        let prompt = self.build_iteration_prompt(session).await?;
-
-        // Create cursor runner
-        let runner = CursorRunner::new(
-            session.project_path.clone(),
-            session.config.execution_model.clone(),
-        );
+        // A steering file left over from a previous iteration must not leak into this one.
+        clear_steering(session.work_dir()).await;
+
+        // Build prompt from iteration template; handoff notes are consumed by it
+        let prompt = self.build_iteration_prompt(session, story_id).await?;
+        let steering = self.steering_prompts(session, story_id)?;
+        session.handoff_notes = None;
+        session.handoff_reason = None;
+
+        // Create a runner for the configured agent backend
+        let runner = match &self.backend_factory {
+            Some(factory) => AgentRunner::new(session.work_dir().to_string(), factory(session)),
+            None => AgentRunner::for_config(
+                session.work_dir().to_string(),
+                session.config.execution_model.clone(),
+                &session.config.agent,
+            ),
+        };
         
-        // Create stream parser for tracking
-        let parser = std::sync::Arc::new(tokio::sync::Mutex::new(StreamParser::new(
+        // Create stream parser for tracking; the forwarder below runs the
+        // gutter detectors instead, since they read files and run git.
+        let parser = std::sync::Arc::new(std::sync::Mutex::new(StreamParser::new(
             session.current_iteration,
             session.token_usage.clone(),
             session.config.warn_threshold,
             session.config.rotate_threshold,
-        )));
+        )
+        .with_gutter(GutterMonitor::default())));
 
         // Track signals
-        let saw_complete = std::sync::Arc::new(tokio::sync::Mutex::new(false));
-        let gutter_signal = std::sync::Arc::new(tokio::sync::Mutex::new(None));
-
+        let mut saw_complete = false;
+        let mut warned = false;
+        let mut rotating = false;
+
+        // Forward entries and steering writes through a single task so they
+        // happen in order, and are all done once it has finished.
+        let (activity_tx, mut activity_rx) = mpsc::unbounded_channel::<Forward>();
+        let manager = self.clone();
         let session_id = session.id.clone();
-        let manager_clone = self.clone();
-        let parser_clone = parser.clone();
-        let saw_complete_clone = saw_complete.clone();
-        let gutter_signal_clone = gutter_signal.clone();
-
-        // Run cursor-agent iteration
-        let shutdown_rx = self.subscribe_shutdown();
-        runner
-            .run_iteration(&prompt, shutdown_rx, move |activity| {
-                let parser = parser_clone.clone();
-                let saw_complete = saw_complete_clone.clone();
-                let gutter_signal = gutter_signal_clone.clone();
-                let session_id = session_id.clone();
-                let session_id_monitor = session_id.clone();
-                let manager = manager_clone.clone();
-
-                let handle = tokio::spawn(async move {
-                    // Parse activity
-                    let mut parser_guard = parser.lock().await;
-                    let (entry, signal) = parser_guard.parse_activity(activity.kind);
-                    drop(parser_guard);
-
-                    // Broadcast activity
-                    manager.broadcast_activity(&session_id, entry).await;
-
-                    // Check for signals
-                    if let Some(sig) = signal {
-                        match sig {
-                            Signal::Complete => {
-                                *saw_complete.lock().await = true;
-                            }
-                            Signal::Gutter(reason) => {
-                                *gutter_signal.lock().await = Some(reason);
+        let project_path = session.work_dir().to_string();
+        let mut monitor = std::mem::take(gutter);
+        let forwarder = tokio::spawn(async move {
+            let mut gutter_signal = None;
+            while let Some(forward) = activity_rx.recv().await {
+                match forward {
+                    Forward::Activity(entry) => {
+                        if !matches!(entry.kind, ActivityKind::Signal(_)) {
+                            let (kind, at) = (entry.kind.clone(), entry.timestamp);
+                            let observed = tokio::task::spawn_blocking(move || {
+                                let signal = monitor.observe(&kind, at);
+                                (monitor, signal)
+                            })
+                            .await;
+                            let signal;
+                            (monitor, signal) = observed.unwrap_or_else(|e| {
+                                tracing::error!("Gutter detection failed for session {}: {}", session_id, e);
+                                (GutterMonitor::default(), None)
+                            });
+                            if let Some(Signal::Gutter { rule, reason }) = signal {
+                                gutter_signal.get_or_insert((rule, reason));
                             }
-                            Signal::Warn => {
-                                tracing::warn!("Token usage warning for session {}", session_id);
-                            }
-                            Signal::Rotate => {
-                                tracing::info!("Rotation signal for session {}", session_id);
-                            }
-                            _ => {}
                         }
+                        manager.broadcast_activity(&session_id, entry).await;
                     }
-                });
+                    Forward::Steer(content) => write_steering(&project_path, content).await,
+                }
+            }
+            (monitor, gutter_signal)
+        });
 
-                // Monitor for panics in the activity parsing task
-                tokio::spawn(async move {
-                    if let Err(e) = handle.await {
-                        if e.is_panic() {
-                            tracing::error!("Activity parsing task panicked for session {}: {:?}", session_id_monitor, e);
-                        }
-                    }
-                });
-            })
-            .await?;
+        let session_id = session.id.clone();
+        let parser_clone = parser.clone();
 
-        // Update session token usage
-        let parser_guard = parser.lock().await;
-        session.token_usage = parser_guard.token_usage().clone();
-        drop(parser_guard);
+        // Run agent iteration
+        let run_result = runner
+            .run_iteration(&prompt, interrupt_rx.clone(), |activity| {
+                // Parse activity
+                let (entry, signal) = match parser_clone.lock() {
+                    Ok(mut parser) => parser.parse_activity(activity.kind),
+                    Err(poisoned) => poisoned.into_inner().parse_activity(activity.kind),
+                };
 
-        // Check for gutter
-        let gutter = gutter_signal.lock().await;
-        if let Some(reason) = gutter.clone() {
-            return Ok(IterationResult::Gutter(reason));
-        }
-        drop(gutter);
+                // Broadcast activity
+                let _ = activity_tx.send(Forward::Activity(entry));
 
-        // Check for rotation
-        if session.token_usage.total >= session.config.rotate_threshold {
-            return Ok(IterationResult::Rotate);
-        }
+                let mut control = AgentControl::Continue;
 
-        // Check for completion
-        let complete = *saw_complete.lock().await;
-        if complete {
+                // Check for signals
+                if let Some(sig) = signal {
+                    match sig {
+                        Signal::Complete => {
+                            saw_complete = true;
+                        }
+                        Signal::Warn if !warned && !rotating => {
+                            warned = true;
+                            tracing::warn!("Token usage warning for session {}, steering agent to wrap up", session_id);
+                            let _ = activity_tx.send(Forward::Steer(steering.wrapup.clone()));
+                            let _ = activity_tx.send(Forward::Activity(signal_entry(&parser_clone, Signal::Warn)));
+                        }
+                        Signal::Rotate if !rotating => {
+                            rotating = true;
+                            tracing::info!("Rotation signal for session {}, asking agent to commit and exit", session_id);
+                            let _ = activity_tx.send(Forward::Steer(steering.rotate.clone()));
+                            // The loop records the Rotate signal once the iteration has ended.
+                            control = AgentControl::Stop;
+                        }
+                        _ => {}
+                    }
+                }
+
+                control
+            })
+            .await;
+
+        drop(activity_tx);
+        let (monitor, gutter_signal) = forwarder.await.unwrap_or_default();
+        clear_steering(session.work_dir()).await;
+
+        // Update session token usage and keep the gutter detectors for the next iteration
+        {
+            let parser = match parser.lock() {
+                Ok(parser) => parser,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            session.token_usage = parser.token_usage().clone();
+        }
+        *gutter = monitor;
+        run_result?;
+
+        // An interrupted run proves nothing; the loop decides what happens next
+        if interrupt_rx.borrow().is_some() {
+            return Ok(IterationResult::Interrupted);
+        }
+
+        // Check for gutter; rules that judge the whole iteration run even if one already fired
+        let changed_files = self.changed_files(session, start_head).await;
+        let ended = match gutter.end_iteration(changed_files) {
+            Some(Signal::Gutter { rule, reason }) => Some((rule, reason)),
+            _ => None,
+        };
+        if let Some((rule, reason)) = gutter_signal.or(ended) {
+            return Ok(IterationResult::Gutter { rule, reason });
+        }
+
+        // Check for rotation
+        if rotating || session.token_usage.total >= session.config.rotate_threshold {
+            let mut notes = format!(
+                "- Rotated at {} tokens (threshold {}).\n",
+                session.token_usage.total, session.config.rotate_threshold
+            );
+            notes.push_str(&self.commit_wip(session, story_id, "context rotation").await);
+            session.handoff_notes = Some(notes);
+            session.handoff_reason = Some("rotated at the context rotation threshold".to_string());
+            return Ok(IterationResult::Rotate);
+        }
+
+        if saw_complete {
             tracing::info!(
                 "Story completion signal received for session {}",
                 session.id
             );
+        }
+
+        // A clean exit is not proof of completion; verify before marking the story.
+        self.verify_story(session, story_id, start_head).await
+    }
+
+    /// Run the completion checks for `story_id` and record each outcome in the
+    /// activity stream.
+    async fn verify_story(
+        &self,
+        session: &Session,
+        story_id: &str,
+        start_head: Option<&str>,
+    ) -> Result<IterationResult, RalphError> {
+        tracing::info!("🔍 Verifying story {} for session {}", story_id, session.id);
+        let verifier = StoryVerifier::new(session.work_dir().to_string());
+        let checks = verifier
+            .verify(story_id, start_head, session.config.verify_command.as_deref())
+            .await;
+
+        for check in &checks {
+            tracing::info!(
+                "{} {}: {}",
+                if check.passed { "✓" } else { "✗" },
+                check.name,
+                check.detail
+            );
+            let entry = ActivityEntry {
+                timestamp: SystemTime::now(),
+                iteration: session.current_iteration,
+                kind: ActivityKind::Verification(check.clone()),
+                health: session.token_usage.health(
+                    session.config.warn_threshold,
+                    session.config.rotate_threshold,
+                ),
+                tokens: None,
+            };
+            self.broadcast_activity(&session.id, entry).await;
+        }
+
+        if all_passed(&checks) {
             return Ok(IterationResult::StoryComplete);
         }
 
-        // Default to story complete (cursor-agent finished successfully)
-        Ok(IterationResult::StoryComplete)
+        let failed: Vec<&str> = checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.name.as_str())
+            .collect();
+        Ok(IterationResult::Unverified(format!(
+            "{} failed verification: {}",
+            story_id,
+            failed.join(", ")
+        )))
+    }
+
+    /// Commit whatever an unfinished agent left behind and describe it for the
+    /// next iteration. `reason` ends up in the commit message.
+    async fn commit_wip(&self, session: &Session, story_id: &str, reason: &str) -> String {
+        let git = GitOperations::new(session.work_dir().to_string());
+
+        let committed = match git.has_changes().await {
+            Ok(true) => {
+                let message = format!("wip: {} - partial implementation ({})", story_id, reason);
+                match git.commit(&message).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::error!("Failed to commit WIP for {}: {}", session.id, e);
+                        false
+                    }
+                }
+            }
+            Ok(false) => false,
+            Err(e) => {
+                tracing::warn!("Failed to check for uncommitted changes: {}", e);
+                false
+            }
+        };
+
+        let head = git
+            .recent_commits(1)
+            .await
+            .ok()
+            .and_then(|lines| lines.into_iter().next())
+            .unwrap_or_else(|| "(none)".to_string());
+        let stat = git.show_stat("HEAD").await.unwrap_or_default();
+
+        let mut notes = String::new();
+        if committed {
+            notes.push_str(&format!("- Uncommitted changes were saved as `{}`.\n", head));
+        } else {
+            notes.push_str(&format!("- The agent left no uncommitted changes; HEAD is `{}`.\n", head));
+        }
+        if !stat.trim().is_empty() {
+            notes.push_str(&format!("- Files in HEAD:\n\n```\n{}\n```\n", stat.trim_end()));
+        }
+        notes
+    }
+
+    /// What the agent committed during `iteration`. An iteration cut short by
+    /// a pause and resumed later spans both runs.
+    pub async fn iteration_diff(&self, session_id: &str, iteration: u32) -> Result<Diff, RalphError> {
+        let session = self.get_session(session_id).await?;
+        let records: Vec<&IterationRecord> = session
+            .iterations
+            .iter()
+            .filter(|r| r.iteration == iteration)
+            .collect();
+        diff_records(&session, &records, &format!("iteration {}", iteration)).await
+    }
+
+    /// Everything committed while working on `story_id`, across all of its
+    /// iterations that are still on the branch.
+    pub async fn story_diff(&self, session_id: &str, story_id: &str) -> Result<Diff, RalphError> {
+        let session = self.get_session(session_id).await?;
+        let records: Vec<&IterationRecord> = session
+            .iterations
+            .iter()
+            .filter(|r| r.story_id == story_id && !r.rolled_back)
+            .collect();
+        diff_records(&session, &records, story_id).await
+    }
+
+    /// Bring a session out of the gutter with `recovery`, record the choice in
+    /// its activity history, and start it again.
+    pub async fn recover_from_gutter(&self, id: &str, recovery: GutterRecovery) -> Result<Session, RalphError> {
+        let mut session = self.get_session(id).await?;
+        let SessionStatus::Gutter { reason } = session.status.clone() else {
+            return Err(RalphError::InvalidState(format!("Session {} is not in the gutter", id)));
+        };
+        let stuck = session
+            .iterations
+            .iter()
+            .rposition(|r| matches!(r.outcome, IterationOutcome::Gutter { .. }));
+        let stuck_story = stuck.map(|index| session.iterations[index].story_id.clone());
+        tracing::info!("🛟 Session {}: {}", id, recovery.describe());
+
+        match &recovery {
+            GutterRecovery::Resume => {}
+            GutterRecovery::SkipStory => {
+                let story_id = stuck_story.ok_or_else(|| {
+                    RalphError::InvalidState(format!("Session {} has no stuck story to skip", id))
+                })?;
+                set_story_status(&mut session, &story_id, StoryStatus::Skipped);
+            }
+            GutterRecovery::AddGuardrail(guardrail) => {
+                GuardrailManager::new(session.project_path.clone())
+                    .add_guardrail(guardrail)
+                    .await?;
+            }
+            GutterRecovery::RetryWithModel { model } => {
+                if let Some(index) = stuck.filter(|&i| !session.iterations[i].rolled_back) {
+                    let why = format!("the session hit the gutter ({}); retrying with {}", reason, model);
+                    self.roll_back(&mut session, index, &why).await?;
+                }
+                session.config.execution_model = model.clone();
+            }
+        }
+
+        session.status = SessionStatus::Paused;
+        session.updated_at = SystemTime::now();
+        self.update_session(session.clone()).await?;
+
+        let entry = ActivityEntry {
+            timestamp: SystemTime::now(),
+            iteration: session.current_iteration,
+            kind: ActivityKind::Recovery(recovery),
+            health: session.token_usage.health(
+                session.config.warn_threshold,
+                session.config.rotate_threshold,
+            ),
+            tokens: None,
+        };
+        self.broadcast_activity(&session.id, entry).await;
+
+        self.start_session(id).await
+    }
+
+    /// Set `story_id` aside so the loop passes over it. A session that
+    /// continues fails at the end while skipped stories are left.
+    pub async fn skip_story(&self, id: &str, story_id: &str) -> Result<Session, RalphError> {
+        self.change_story(id, story_id, |story| {
+            if story.passes() {
+                return Err(RalphError::InvalidState(format!("Story {} is already done", story.id)));
+            }
+            story.status = StoryStatus::Skipped;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Hand `story_id` back to the loop with a fresh set of attempts, whether
+    /// it was done, skipped or blocked.
+    pub async fn reopen_story(&self, id: &str, story_id: &str) -> Result<Session, RalphError> {
+        self.change_story(id, story_id, |story| {
+            story.status = StoryStatus::Pending;
+            story.attempts.clear();
+            Ok(())
+        })
+        .await
+    }
+
+    async fn change_story(
+        &self,
+        id: &str,
+        story_id: &str,
+        change: impl FnOnce(&mut Story) -> Result<(), RalphError>,
+    ) -> Result<Session, RalphError> {
+        let mut session = self.get_session(id).await?;
+        if matches!(
+            session.status,
+            SessionStatus::Queued { .. } | SessionStatus::Running { .. } | SessionStatus::WaitingForRotation
+        ) {
+            return Err(RalphError::InvalidState(format!(
+                "Pause session {} before changing its stories",
+                id
+            )));
+        }
+        let story = session
+            .prd
+            .as_mut()
+            .and_then(|prd| prd.stories.iter_mut().find(|s| s.id == story_id))
+            .ok_or_else(|| RalphError::InvalidState(format!("Session {} has no story {}", id, story_id)))?;
+        change(story)?;
+        tracing::info!("📝 Session {}: story {} is now {}", id, story_id, story.status.label());
+
+        self.save_prd(&mut session).await?;
+        // A finished session can run again for the stories that now need work.
+        let workable = session
+            .prd
+            .iter()
+            .flat_map(|prd| prd.stories.iter())
+            .any(|s| s.status.is_workable());
+        if matches!(session.status, SessionStatus::Complete | SessionStatus::Failed { .. }) && workable {
+            session.status = SessionStatus::Idle;
+        }
+        session.updated_at = SystemTime::now();
+        self.update_session(session.clone()).await?;
+        Ok(session)
+    }
+
+    /// Undo `iteration` and every iteration after it by resetting the
+    /// session's branch to where `iteration` started. Stories completed in
+    /// the undone iterations become pending again.
+    pub async fn rollback_iteration(&self, id: &str, iteration: u32) -> Result<Session, RalphError> {
+        let mut session = self.get_session(id).await?;
+        if matches!(
+            session.status,
+            SessionStatus::Queued { .. } | SessionStatus::Running { .. } | SessionStatus::WaitingForRotation
+        ) {
+            return Err(RalphError::InvalidState(format!(
+                "Pause session {} before rolling it back",
+                id
+            )));
+        }
+        let index = session
+            .iterations
+            .iter()
+            .position(|r| r.iteration == iteration && !r.rolled_back)
+            .ok_or_else(|| {
+                RalphError::InvalidState(format!("Session {} has no iteration {} to roll back", id, iteration))
+            })?;
+
+        let reason = format!("iteration {} was rolled back by hand", iteration + 1);
+        self.roll_back(&mut session, index, &reason).await?;
+        if session.status == SessionStatus::Complete {
+            session.status = SessionStatus::Idle;
+        }
+        session.updated_at = SystemTime::now();
+        self.update_session(session.clone()).await?;
+        Ok(session)
+    }
+
+    /// Automatic rollback of the iteration that just failed; errors are
+    /// reported and the loop carries on from wherever the branch is.
+    async fn roll_back_failed(&self, session: &mut Session, reason: &str) {
+        let index = session.iterations.len().saturating_sub(1);
+        if let Err(e) = self.roll_back(session, index, reason).await {
+            self.report_error(session, format!("Failed to roll back iteration: {}", e)).await;
+        }
+    }
+
+    /// Reset the session's branch to the start of `iterations[index]`, keeping
+    /// the undone work on an attempt branch if configured, and leave
+    /// `reason` in the notes of the story that iteration worked on.
+    async fn roll_back(&self, session: &mut Session, index: usize, reason: &str) -> Result<(), RalphError> {
+        let record = session.iterations[index].clone();
+        let start = record.start_commit.clone().ok_or_else(|| {
+            RalphError::InvalidState(format!("Iteration {} has no start commit", record.iteration + 1))
+        })?;
+        let branch = session_branch(session);
+        let work_dir = session.work_dir().to_string();
+        let git = GitOperations::new(work_dir.clone());
+
+        // Without the branch checked out (e.g. its worktree is gone) only the
+        // branch itself can be moved.
+        let checked_out = git.get_current_branch().await.map(|b| b == branch).unwrap_or(false);
+        let repo = if checked_out {
+            git
+        } else {
+            GitOperations::new(session.project_path.clone())
+        };
+
+        if session.config.keep_attempts {
+            if checked_out && repo.has_changes().await? {
+                let message = format!("wip: {} - rolled back attempt", record.story_id);
+                repo.commit(&message).await?;
+            }
+            let head = repo.rev_parse(&branch).await?;
+            if head != repo.rev_parse(&start).await? {
+                let attempt = self.attempt_branch(&repo, &record.story_id).await?;
+                repo.force_branch(&attempt, &head).await?;
+                session.iterations[index].attempt_branch = Some(attempt);
+            }
+        }
+
+        if checked_out {
+            repo.reset_hard(&start).await?;
+            repo.clean(RALPH_PATHS).await?;
+        } else {
+            repo.force_branch(&branch, &start).await?;
+        }
+        tracing::info!("⏪ Rolled back session {} to {} ({})", session.id, start, reason);
+
+        // Stories completed by the undone iterations need doing again.
+        let undone: Vec<IterationRecord> = session.iterations[index..].to_vec();
+        for later in &mut session.iterations[index..] {
+            later.rolled_back = true;
+        }
+        if let Some(prd) = &mut session.prd {
+            for story in prd.stories.iter_mut() {
+                let redo = undone
+                    .iter()
+                    .any(|r| r.story_id == story.id && r.outcome == IterationOutcome::StoryComplete);
+                if redo {
+                    story.status = StoryStatus::Pending;
+                }
+                if story.id == record.story_id {
+                    story.status = StoryStatus::Pending;
+                    if !story.notes.is_empty() {
+                        story.notes.push(' ');
+                    }
+                    story.notes.push_str(&format!("A previous attempt was rolled back: {}.", reason));
+                }
+            }
+        }
+        if checked_out {
+            self.save_prd(session).await?;
+        }
+        // Handoff notes describe work that no longer exists.
+        session.handoff_notes = None;
+        session.handoff_reason = None;
+        Ok(())
+    }
+
+    /// First free `ralph/attempts/<story>-<n>` branch name.
+    async fn attempt_branch(&self, git: &GitOperations, story_id: &str) -> Result<String, RalphError> {
+        let mut n = 1;
+        loop {
+            let name = format!("ralph/attempts/{}-{}", story_id, n);
+            if !git.branch_exists(&name).await? {
+                return Ok(name);
+            }
+            n += 1;
+        }
+    }
+
+    /// `HEAD` of the session's checkout, or `None` if it can't be read.
+    async fn head_commit(&self, session: &Session) -> Option<String> {
+        match GitOperations::new(session.work_dir().to_string()).head_commit().await {
+            Ok(head) => head,
+            Err(e) => {
+                tracing::warn!("Failed to read HEAD for session {}: {}", session.id, e);
+                None
+            }
+        }
+    }
+
+    /// Whether the agent changed any files since `start_head`, committed or
+    /// not. Assumes it did when git can't tell.
+    async fn changed_files(&self, session: &Session, start_head: Option<&str>) -> bool {
+        if self.head_commit(session).await.as_deref() != start_head {
+            return true;
+        }
+        match GitOperations::new(session.work_dir().to_string())
+            .uncommitted_changes(RALPH_PATHS)
+            .await
+        {
+            Ok(changes) => !changes.is_empty(),
+            Err(e) => {
+                tracing::warn!("Failed to check changes for session {}: {}", session.id, e);
+                true
+            }
+        }
+    }
+
+    /// Close the record of the iteration that began at `start`, in the
+    /// session and in the attempts of the story it worked on.
+    async fn record_iteration(&self, session: &mut Session, start: IterationStart, outcome: IterationOutcome) {
+        let end_commit = self.head_commit(session).await;
+        let commits = match &start.commit {
+            Some(base) if end_commit.as_ref() != Some(base) => GitOperations::new(session.work_dir().to_string())
+                .commits_since(base)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to list commits for session {}: {}", session.id, e);
+                    Vec::new()
+                }),
+            _ => Vec::new(),
+        };
+        let attempt = StoryAttempt {
+            iteration: start.iteration,
+            outcome: outcome.clone(),
+            tokens: session.token_usage.total.saturating_sub(start.tokens),
+            commits,
+        };
+        if let Some(story) = session
+            .prd
+            .as_mut()
+            .and_then(|prd| prd.stories.iter_mut().find(|s| s.id == start.story_id))
+        {
+            story.attempts.push(attempt);
+        }
+
+        session.iterations.push(IterationRecord {
+            iteration: start.iteration,
+            story_id: start.story_id,
+            start_commit: start.commit,
+            end_commit,
+            outcome,
+            started_at: start.at,
+            finished_at: SystemTime::now(),
+            rolled_back: false,
+            attempt_branch: None,
+        });
+    }
+
+    /// Block `story_id` once its failed attempts reach the session's limit, so
+    /// the loop moves on to other stories.
+    async fn block_if_out_of_attempts(&self, session: &mut Session, story_id: &str) {
+        let Some(max) = session.config.max_attempts_per_story else {
+            return;
+        };
+        let Some(story) = session
+            .prd
+            .iter()
+            .flat_map(|prd| prd.stories.iter())
+            .find(|s| s.id == story_id)
+        else {
+            return;
+        };
+        let attempts = story.failed_attempts();
+        if attempts < max || story.status == StoryStatus::Blocked {
+            return;
+        }
+
+        tracing::warn!("⛔ Story {} blocked after {} failed attempts (session {})", story_id, attempts, session.id);
+        set_story_status(session, story_id, StoryStatus::Blocked);
+        let entry = ActivityEntry {
+            timestamp: SystemTime::now(),
+            iteration: session.current_iteration,
+            kind: ActivityKind::Signal(Signal::StoryBlocked {
+                story_id: story_id.to_string(),
+                attempts,
+            }),
+            health: session.token_usage.health(
+                session.config.warn_threshold,
+                session.config.rotate_threshold,
+            ),
+            tokens: None,
+        };
+        self.broadcast_activity(&session.id, entry).await;
+    }
+
+    /// Put the session on its branch: in a worktree of its own, or checked out
+    /// in the project checkout. The branch is created from the configured base
+    /// if it doesn't exist yet, and the commit it started from is recorded.
+    async fn prepare_checkout(&self, session: &mut Session) -> Result<(), RalphError> {
+        let git = GitOperations::new(session.project_path.clone());
+        let branch = session_branch(session);
+
+        // Resolve the base before anything is checked out
+        let base = match session.config.base_branch.as_deref().map(str::trim) {
+            Some(base) if !base.is_empty() => base.to_string(),
+            _ => git.head_commit().await?.ok_or_else(|| {
+                RalphError::Git("Repository has no commits to branch from".into())
+            })?,
+        };
+
+        if session.config.use_worktree {
+            self.prepare_worktree(session, &branch, &base).await?;
+        } else {
+            self.clean_checkout(session, true).await?;
+            git.create_branch(&branch, Some(&base)).await?;
+        }
+
+        if session.base_commit.is_none() {
+            let base_commit = git.merge_base(&base, &branch).await?;
+            tracing::info!("Session {} branch {} starts at {}", session.id, branch, base_commit);
+            session.base_commit = Some(base_commit);
+        }
+
+        session.updated_at = SystemTime::now();
+        self.update_session(session.clone()).await
+    }
+
+    /// Give a worktree session its worktree, created under the managed
+    /// directory the first time the session runs.
+    async fn prepare_worktree(&self, session: &mut Session, branch: &str, base: &str) -> Result<(), RalphError> {
+        if let Some(path) = &session.worktree_path {
+            if Path::new(path).exists() {
+                return Ok(());
+            }
+            tracing::warn!("Worktree {} for session {} is gone, recreating it", path, session.id);
+        }
+
+        let repo_name = Path::new(&session.project_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "project".to_string());
+        let short_id: String = session.id.chars().take(8).collect();
+        let path = self.worktree_root.join(format!("{}-{}", repo_name, short_id));
+
+        tokio::fs::create_dir_all(&self.worktree_root).await?;
+        GitOperations::new(session.project_path.clone())
+            .add_worktree(&path, branch, Some(base))
+            .await?;
+        tokio::fs::create_dir_all(path.join(".ralph")).await?;
+
+        session.worktree_path = Some(path.to_string_lossy().to_string());
+        if let Some(prd) = &session.prd {
+            self.write_prd_to_disk(session.work_dir(), prd).await?;
+        }
+        Ok(())
+    }
+
+    /// Refuse to work in a project checkout holding the developer's
+    /// uncommitted changes, unless the session may stash them (which happens
+    /// only when `stash` is set).
+    async fn clean_checkout(&self, session: &Session, stash: bool) -> Result<(), RalphError> {
+        let git = GitOperations::new(session.project_path.clone());
+        let changes = git.uncommitted_changes(RALPH_PATHS).await?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        if session.config.stash_changes {
+            if stash {
+                git.stash(&format!("ralph: before session {}", session.id), &changes)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let mut files = changes.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+        if changes.len() > 5 {
+            files.push_str(", ...");
+        }
+        Err(RalphError::InvalidState(format!(
+            "{} has uncommitted changes ({}). Commit them, or enable stashing for this session.",
+            session.project_path, files
+        )))
+    }
+
+    /// Push a completed session's branch to `origin` and, with `open_pr`,
+    /// open a pull request for it. Failures are reported on the activity
+    /// stream; the session still counts as complete.
+    async fn publish(&self, session: &mut Session) {
+        let git = GitOperations::new(session.work_dir().to_string());
+        let branch = session_branch(session);
+
+        match git.has_remote("origin").await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::info!("No origin remote for session {}, not pushing {}", session.id, branch);
+                return;
+            }
+            Err(e) => {
+                self.report_error(session, format!("Failed to list remotes: {}", e)).await;
+                return;
+            }
+        }
+
+        if let Err(e) = git.push(Some(&branch)).await {
+            self.report_error(session, format!("Failed to push {}: {}", branch, e)).await;
+            return;
+        }
+
+        if !session.config.open_pr {
+            return;
+        }
+        let Some(prd) = &session.prd else {
+            return;
+        };
+
+        let commits = match &session.base_commit {
+            Some(base) => git.commits_since(base).await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to list commits for the PR body: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let progress_path = Path::new(session.work_dir()).join(".ralph/progress.md");
+        let progress = tokio::fs::read_to_string(&progress_path).await.unwrap_or_default();
+        let title = pr_title(prd);
+        let body = pr_body(prd, &commits, &progress);
+
+        // A session resumed after completing already has a pull request; refresh
+        // its description with the new work instead of opening another.
+        let forge = self.forge(session);
+        let result = match session.pr_number {
+            Some(number) => {
+                let update = PullRequestUpdate {
+                    title: Some(title),
+                    body: Some(body),
+                };
+                forge.update_pr(number, &update).await
+            }
+            None => {
+                let pr = NewPullRequest {
+                    head: branch.clone(),
+                    base: session.config.base_branch.clone(),
+                    title,
+                    body,
+                };
+                forge.create_pr(&pr).await
+            }
+        };
+
+        match result {
+            Ok(pr) => {
+                tracing::info!("🔗 {} #{} for session {}: {}", forge.name(), pr.number, session.id, pr.url);
+                session.pr_url = Some(pr.url);
+                session.pr_number = Some(pr.number);
+            }
+            Err(e) => {
+                self.report_error(session, format!("Failed to open a pull request for {}: {}", branch, e))
+                    .await
+            }
+        }
+    }
+
+    /// The forge hosting a session's pull request.
+    pub fn forge(&self, session: &Session) -> Box<dyn ForgeProvider> {
+        match &self.forge_factory {
+            Some(factory) => factory(session),
+            None => forge_for(&session.config.forge, session.work_dir().to_string()),
+        }
+    }
+
+    /// Open a pull request for `head` into the session's base branch, and
+    /// keep it as the session's pull request like one opened on completion.
+    pub async fn open_pull_request(
+        &self,
+        session_id: &str,
+        head: String,
+        title: String,
+        body: String,
+    ) -> Result<PullRequest, RalphError> {
+        let session = self.get_session(session_id).await?;
+        let forge = self.forge(&session);
+        let pr = NewPullRequest {
+            head,
+            base: session.config.base_branch.clone(),
+            title,
+            body,
+        };
+        let pr = forge.create_pr(&pr).await?;
+        tracing::info!("🔗 {} #{} for session {}: {}", forge.name(), pr.number, session_id, pr.url);
+
+        // The loop may have saved the session while the forge answered.
+        let mut session = self.get_session(session_id).await?;
+        session.pr_url = Some(pr.url.clone());
+        session.pr_number = Some(pr.number);
+        session.updated_at = SystemTime::now();
+        self.update_session(session).await?;
+        Ok(pr)
+    }
+
+    /// Current state of the pull request Ralph opened for a session.
+    pub async fn pull_request_status(&self, session_id: &str) -> Result<PullRequest, RalphError> {
+        let session = self.get_session(session_id).await?;
+        let number = session.pr_number.ok_or_else(|| {
+            RalphError::InvalidState(format!("Session {} has no pull request", session_id))
+        })?;
+        self.forge(&session).pr_status(number).await
+    }
+
+    /// Post a comment on the pull request Ralph opened for a session.
+    pub async fn comment_on_pull_request(&self, session_id: &str, body: &str) -> Result<(), RalphError> {
+        let session = self.get_session(session_id).await?;
+        let number = session.pr_number.ok_or_else(|| {
+            RalphError::InvalidState(format!("Session {} has no pull request", session_id))
+        })?;
+        self.forge(&session).comment(number, body).await
+    }
+
+    /// Log `message` and put it on the session's activity stream.
+    async fn report_error(&self, session: &Session, message: String) {
+        tracing::error!("❌ {} (session {})", message, session.id);
+        let entry = ActivityEntry {
+            timestamp: SystemTime::now(),
+            iteration: session.current_iteration,
+            kind: ActivityKind::Error(message),
+            health: session.token_usage.health(
+                session.config.warn_threshold,
+                session.config.rotate_threshold,
+            ),
+            tokens: None,
+        };
+        self.broadcast_activity(&session.id, entry).await;
+    }
+
+    /// Remove a completed session's worktree unless it is configured to be
+    /// kept. The branch and its commits stay in the repository.
+    async fn cleanup_worktree(&self, session: &mut Session) {
+        if session.config.keep_worktree {
+            return;
+        }
+        let Some(path) = session.worktree_path.clone() else {
+            return;
+        };
+
+        match GitOperations::new(session.project_path.clone())
+            .remove_worktree(Path::new(&path))
+            .await
+        {
+            Ok(()) => session.worktree_path = None,
+            Err(e) => tracing::error!("Failed to remove worktree {} for {}: {}", path, session.id, e),
+        }
+    }
+
+    /// Record the final state of a loop ended by `interrupt`. When an iteration
+    /// was in progress, its leftovers are committed and handed off to the next run.
+    async fn finish_interrupted(
+        &self,
+        mut session: Session,
+        interrupt: Interrupt,
+        iteration: Option<IterationStart>,
+    ) -> Result<(), RalphError> {
+        let (status, verb) = match interrupt {
+            Interrupt::Pause => (SessionStatus::Paused, "paused"),
+            Interrupt::Shutdown => (SessionStatus::Paused, "interrupted by shutdown"),
+            Interrupt::Stop => (SessionStatus::Idle, "stopped"),
+        };
+        tracing::info!("⏹️  Session {} {}", session.id, verb);
+
+        if let Some(start) = iteration {
+            let story_id = start.story_id.clone();
+            let mut notes = format!("- The previous run on {} was {} before it finished.\n", story_id, verb);
+            notes.push_str(&self.commit_wip(&session, &story_id, verb).await);
+            session.handoff_notes = Some(notes);
+            session.handoff_reason = Some(verb.to_string());
+            self.record_iteration(&mut session, start, IterationOutcome::Interrupted).await;
+        }
+
+        session.status = status;
+        session.updated_at = SystemTime::now();
+        self.update_session(session).await
+    }
+
+    /// Render the mid-iteration steering prompts for the current story.
+    fn steering_prompts(&self, session: &Session, story_id: &str) -> Result<SteeringPrompts, RalphError> {
+        let vars = self.story_template(session, story_id)?;
+        Ok(SteeringPrompts {
+            wrapup: vars.render(include_str!("../assets/prompts/wrapup.md")),
+            rotate: vars.render(include_str!("../assets/prompts/rotate.md")),
+        })
     }
 
-    async fn build_iteration_prompt(&self, session: &Session) -> Result<String, RalphError> {
+    fn story_template(&self, session: &Session, story_id: &str) -> Result<PromptTemplate, RalphError> {
+        let prd = session.prd.as_ref().ok_or_else(|| {
+            RalphError::InvalidState("Cannot build iteration prompt without a PRD".into())
+        })?;
+        let story = prd
+            .stories
+            .iter()
+            .find(|s| s.id == story_id)
+            .ok_or_else(|| {
+                RalphError::InvalidState(format!("Story {} not found in PRD", story_id))
+            })?;
+
+        let mut vars = PromptTemplate::new();
+        vars.with_story(prd, story)
+            .set("branch_name", session_branch(session))
+            .set("warn_threshold", session.config.warn_threshold.to_string())
+            .set("rotate_threshold", session.config.rotate_threshold.to_string());
+        Ok(vars)
+    }
+
+    async fn build_iteration_prompt(
+        &self,
+        session: &Session,
+        story_id: &str,
+    ) -> Result<String, RalphError> {
         tracing::debug!("Building iteration prompt for session {}", session.id);
 
         // Load iteration template
         let template = include_str!("../assets/prompts/iteration.md");
 
+        // Progress notes and git history are best-effort context.
+        let progress_path = std::path::Path::new(session.work_dir()).join(".ralph/progress.md");
+        let progress_tail = tokio::fs::read_to_string(&progress_path)
+            .await
+            .map(|content| tail_lines(&content, PROGRESS_TAIL_LINES))
+            .unwrap_or_default();
+
+        let git = GitOperations::new(session.work_dir().to_string());
+        let recent_commits = git
+            .recent_commits(RECENT_COMMITS)
+            .await
+            .map(|lines| lines.join("\n"))
+            .unwrap_or_default();
+
+        let mut vars = self.story_template(session, story_id)?;
+        vars.set("progress_tail", or_placeholder(progress_tail, "(no progress yet)"))
+            .set("recent_commits", or_placeholder(recent_commits, "(no commits yet)"));
+
         // Load guardrails
         let guardrail_manager = GuardrailManager::new(session.project_path.clone());
         let guardrails = guardrail_manager
@@ -557,8 +2061,14 @@ impl SessionManager {
             .await
             .unwrap_or_else(|_| String::new());
 
-        // Build the full prompt
-        let mut prompt = String::from(template);
+        // Build the full prompt, leading with the handoff after a rotation or interrupt
+        let mut prompt = String::new();
+        if let Some(notes) = &session.handoff_notes {
+            let reason = session.handoff_reason.as_deref().unwrap_or("interrupted");
+            vars.set("handoff_notes", notes.clone()).set("handoff_reason", reason);
+            prompt.push_str(&vars.render(include_str!("../assets/prompts/handoff.md")));
+        }
+        prompt.push_str(&vars.render(template));
 
         if !guardrails.is_empty() {
             prompt.push_str("\n\n---\n\n");
@@ -570,12 +2080,165 @@ impl SessionManager {
     }
 }
 
+/// The session's prd.json, once it has one; a worktree session's lives in its
+/// worktree.
+fn prd_path(session: &Session) -> Option<PathBuf> {
+    if session.config.use_worktree && session.worktree_path.is_none() {
+        return None;
+    }
+    Some(Path::new(session.work_dir()).join("prd.json"))
+}
+
+fn set_story_status(session: &mut Session, story_id: &str, status: StoryStatus) {
+    if let Some(story) = session
+        .prd
+        .as_mut()
+        .and_then(|prd| prd.stories.iter_mut().find(|s| s.id == story_id))
+    {
+        story.status = status;
+    }
+}
+
+/// Ids of the session's stories with `status`.
+fn with_status(session: &Session, status: StoryStatus) -> Vec<String> {
+    session
+        .prd
+        .iter()
+        .flat_map(|prd| prd.stories.iter())
+        .filter(|s| s.status == status)
+        .map(|s| s.id.clone())
+        .collect()
+}
+
+/// Diff from the start of the first of `records` to the end of the last.
+/// Worktrees share the repository's objects, so this works after a session's
+/// worktree is gone.
+/// The changes made by `records`. Back-to-back records are diffed as one
+/// range; a gap between them, where other stories ran, is left out.
+async fn diff_records(session: &Session, records: &[&IterationRecord], what: &str) -> Result<Diff, RalphError> {
+    if records.is_empty() {
+        return Err(RalphError::InvalidState(format!(
+            "Session {} has no record of {}",
+            session.id, what
+        )));
+    }
+    let mut ranges: Vec<(&String, &String)> = Vec::new();
+    for record in records {
+        let (Some(from), Some(to)) = (&record.start_commit, &record.end_commit) else {
+            return Err(RalphError::InvalidState(format!(
+                "The commits of {} in session {} are unknown",
+                what, session.id
+            )));
+        };
+        match ranges.last_mut() {
+            Some((_, end)) if *end == from => *end = to,
+            _ => ranges.push((from, to)),
+        }
+    }
+
+    let git = GitOperations::new(session.project_path.clone());
+    let mut diff = git.diff(ranges[0].0, ranges[0].1).await?;
+    for (from, to) in &ranges[1..] {
+        diff.append(git.diff(from, to).await?);
+    }
+    Ok(diff)
+}
+
+/// Where an iteration started, until it ends and becomes an `IterationRecord`.
+struct IterationStart {
+    iteration: u32,
+    story_id: String,
+    commit: Option<String>,
+    /// `TokenUsage::total` before the agent started.
+    tokens: u32,
+    at: SystemTime,
+}
+
+/// Rendered prompts delivered to a running agent through `.ralph/steer.md`.
+struct SteeringPrompts {
+    wrapup: String,
+    rotate: String,
+}
+
+/// Branch a session works on: the configured one, else the PRD's.
+fn session_branch(session: &Session) -> String {
+    session
+        .config
+        .branch_name
+        .clone()
+        .or_else(|| session.prd.as_ref().map(|prd| prd.branch_name.clone()))
+        .filter(|branch| !branch.trim().is_empty())
+        .unwrap_or_else(|| format!("ralph/{}", session.id))
+}
+
+/// What a session occupies while it runs, for the scheduler: its repository,
+/// or just its branch of it when it runs in a worktree. Paths are
+/// canonicalized so different spellings of one repository still collide.
+fn checkout_key(session: &Session) -> String {
+    let repo = std::fs::canonicalize(&session.project_path)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| session.project_path.clone());
+    if session.config.use_worktree {
+        format!("{}#{}", repo, session_branch(session))
+    } else {
+        repo
+    }
+}
+
+fn steering_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path).join(".ralph/steer.md")
+}
+
+/// What an iteration's activity callback hands to its forwarder task.
+enum Forward {
+    Activity(ActivityEntry),
+    /// Steer the agent with this prompt.
+    Steer(String),
+}
+
+async fn write_steering(project_path: &str, content: String) {
+    if let Err(e) = tokio::fs::write(steering_path(project_path), content).await {
+        tracing::error!("Failed to write steering prompt for {}: {}", project_path, e);
+    }
+}
+
+async fn clear_steering(project_path: &str) {
+    let path = steering_path(project_path);
+    if path.exists() {
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            tracing::warn!("Failed to remove {:?}: {}", path, e);
+        }
+    }
+}
+
+fn signal_entry(parser: &std::sync::Mutex<StreamParser>, signal: Signal) -> ActivityEntry {
+    match parser.lock() {
+        Ok(parser) => parser.entry(ActivityKind::Signal(signal)),
+        Err(poisoned) => poisoned.into_inner().entry(ActivityKind::Signal(signal)),
+    }
+}
+
+fn or_placeholder(value: String, placeholder: &str) -> String {
+    if value.trim().is_empty() {
+        placeholder.to_string()
+    } else {
+        value
+    }
+}
+
 impl Clone for SessionManager {
     fn clone(&self) -> Self {
         Self {
             sessions: self.sessions.clone(),
             activity_channels: self.activity_channels.clone(),
             shutdown_tx: self.shutdown_tx.clone(),
+            store: self.store.clone(),
+            backend_factory: self.backend_factory.clone(),
+            forge_factory: self.forge_factory.clone(),
+            tasks: self.tasks.clone(),
+            scheduler: self.scheduler.clone(),
+            worktree_root: self.worktree_root.clone(),
+            synced_prds: self.synced_prds.clone(),
         }
     }
 }
@@ -605,6 +2268,17 @@ mod tests {
             rotate_threshold: 80_000,
             branch_name: None,
             open_pr: false,
+            verify_command: None,
+            agent: AgentBackendKind::Cursor,
+            use_worktree: true,
+            keep_worktree: false,
+            base_branch: None,
+            stash_changes: false,
+            forge: ForgeKind::GitHub,
+            rollback_failed: false,
+            keep_attempts: true,
+            gutter_rules: GutterRule::defaults(),
+            max_attempts_per_story: Some(5),
         };
 
         // Verify execution_model is different from prd_model
@@ -618,4 +2292,295 @@ mod tests {
         );
         assert_eq!(runner.model, "opus-4.5-thinking");
     }
+
+    #[tokio::test]
+    async fn test_running_sessions_rehydrate_as_paused() {
+        let dir = std::env::temp_dir().join(format!("ralph-manager-{}", uuid::Uuid::new_v4()));
+        let store = SessionStore::new(&dir);
+
+        let session = Session {
+            id: "running".to_string(),
+            project_path: "/tmp/test-project".to_string(),
+            status: SessionStatus::Running {
+                story_id: "US-001".to_string(),
+            },
+            config: SessionConfig::default(),
+            prd: None,
+            current_iteration: 4,
+            token_usage: TokenUsage::default(),
+            handoff_notes: None,
+            handoff_reason: None,
+            worktree_path: None,
+            base_commit: None,
+            pr_url: None,
+            pr_number: None,
+            iterations: Vec::new(),
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        };
+        store.save(&session).await.unwrap();
+        store
+            .save(&Session {
+                id: "queued".to_string(),
+                status: SessionStatus::Queued { position: 1 },
+                ..session.clone()
+            })
+            .await
+            .unwrap();
+
+        let manager = SessionManager::with_store(SessionStore::new(&dir));
+        let restored = manager.get_session("running").await.unwrap();
+        assert_eq!(restored.status, SessionStatus::Paused);
+        assert_eq!(restored.current_iteration, 4);
+        let queued = manager.get_session("queued").await.unwrap();
+        assert_eq!(queued.status, SessionStatus::Paused);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_steering_prompts_are_rendered_for_story() {
+        let manager = SessionManager::new();
+        let session = Session {
+            id: "s1".to_string(),
+            project_path: "/tmp/test-project".to_string(),
+            status: SessionStatus::Idle,
+            config: SessionConfig::default(),
+            prd: Some(Prd {
+                project: "Demo".to_string(),
+                branch_name: "ralph/demo".to_string(),
+                description: String::new(),
+                stories: vec![Story {
+                    id: "US-007".to_string(),
+                    title: "Export CSV".to_string(),
+                    description: String::new(),
+                    acceptance_criteria: vec!["Typecheck passes".to_string()],
+                    priority: 1,
+                    status: StoryStatus::Pending,
+                    notes: String::new(),
+                    depends_on: Vec::new(),
+                    attempts: Vec::new(),
+                }],
+            }),
+            current_iteration: 0,
+            token_usage: TokenUsage::default(),
+            handoff_notes: None,
+            handoff_reason: None,
+            worktree_path: None,
+            base_commit: None,
+            pr_url: None,
+            pr_number: None,
+            iterations: Vec::new(),
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        };
+
+        let steering = manager.steering_prompts(&session, "US-007").unwrap();
+        assert!(steering.wrapup.contains("70000 tokens"));
+        assert!(steering.wrapup.contains("feat: US-007 - Export CSV"));
+        assert!(steering.rotate.contains("80000 tokens"));
+        assert!(steering.rotate.contains("wip: US-007 - partial implementation"));
+        assert!(!steering.wrapup.contains("{{"));
+        assert!(!steering.rotate.contains("{{"));
+    }
+
+    fn test_entry(iteration: u32) -> ActivityEntry {
+        ActivityEntry {
+            timestamp: SystemTime::now(),
+            iteration,
+            kind: ActivityKind::Error(format!("entry {}", iteration)),
+            health: ContextHealth::Healthy,
+            tokens: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_activity_resume_from_sequence() {
+        let manager = SessionManager::new();
+
+        manager.broadcast_activity("s1", test_entry(1)).await;
+        manager.broadcast_activity("s1", test_entry(2)).await;
+        manager.broadcast_activity("s1", test_entry(3)).await;
+
+        let mut rx = manager.subscribe_to_activity("s1", Some(1)).await;
+        manager.broadcast_activity("s1", test_entry(4)).await;
+
+        let seqs: Vec<u64> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_activity_replays_from_journal_after_restart() {
+        let dir = std::env::temp_dir().join(format!("ralph-journal-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        let state_dir = dir.join("state");
+
+        let manager = SessionManager::with_store(SessionStore::new(&state_dir));
+        let session = manager
+            .create_session(dir.to_string_lossy().to_string(), SessionConfig::default())
+            .await
+            .unwrap();
+        manager.broadcast_activity(&session.id, test_entry(1)).await;
+        manager.broadcast_activity(&session.id, test_entry(2)).await;
+
+        // A fresh manager has an empty in-memory backlog.
+        let restarted = SessionManager::with_store(SessionStore::new(&state_dir));
+        restarted.broadcast_activity(&session.id, test_entry(3)).await;
+
+        let mut rx = restarted.subscribe_to_activity(&session.id, Some(0)).await;
+        let seqs: Vec<u64> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+
+        let page = restarted
+            .get_activity(&session.id, 1, 10, &ActivityFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(page.events.len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_activity_since_far_future_replays_nothing() {
+        let manager = SessionManager::new();
+        manager.broadcast_activity("s1", test_entry(1)).await;
+
+        let mut rx = manager.subscribe_to_activity("s1", Some(u64::MAX)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_prd_keeps_what_the_session_won() {
+        let dir = std::env::temp_dir().join(format!("ralph-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        let manager = SessionManager::with_store(SessionStore::new(dir.join("state")));
+        let config = SessionConfig {
+            use_worktree: false,
+            ..SessionConfig::default()
+        };
+        let mut session = manager
+            .create_session(dir.to_string_lossy().to_string(), config)
+            .await
+            .unwrap();
+
+        let story = |id: &str, priority: u32| Story {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            acceptance_criteria: vec!["Typecheck passes".to_string()],
+            priority,
+            status: StoryStatus::Pending,
+            notes: String::new(),
+            depends_on: Vec::new(),
+            attempts: Vec::new(),
+        };
+        let base = Prd {
+            project: "Test".to_string(),
+            branch_name: "ralph/test".to_string(),
+            description: String::new(),
+            stories: vec![story("US-001", 1), story("US-002", 2)],
+        };
+        manager.write_prd_to_disk(session.work_dir(), &base).await.unwrap();
+        let mut ours = base.clone();
+        ours.stories[0].notes = "Ours".to_string();
+        session.prd = Some(ours);
+        let mut theirs = base.clone();
+        theirs.stories[0].notes = "Theirs".to_string();
+        theirs.stories[1].notes = "Edited on disk".to_string();
+        std::fs::write(dir.join("prd.json"), serde_json::to_string_pretty(&theirs).unwrap()).unwrap();
+
+        manager.sync_prd(&mut session).await;
+        let merged = session.prd.clone().unwrap();
+        assert_eq!(merged.stories[0].notes, "Ours");
+        assert_eq!(merged.stories[1].notes, "Edited on disk");
+        let on_disk: Prd = serde_json::from_str(&std::fs::read_to_string(dir.join("prd.json")).unwrap()).unwrap();
+        assert_eq!(on_disk, merged);
+
+        // Syncing again finds nothing new to take.
+        manager.sync_prd(&mut session).await;
+        assert_eq!(session.prd, Some(merged));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_story_diff_leaves_out_other_stories() {
+        let dir = std::env::temp_dir().join(format!("ralph-story-diff-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| -> String {
+            let output = std::process::Command::new("git")
+                .args(["-c", "user.name=Ralph", "-c", "user.email=ralph@example.com"])
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        let commit = |path: &str, content: &str| -> String {
+            std::fs::write(dir.join(path), content).unwrap();
+            git(&["add", "-A"]);
+            git(&["commit", "-q", "-m", path]);
+            git(&["rev-parse", "HEAD"])
+        };
+        git(&["init", "-q"]);
+        let start = commit("README.md", "Test\n");
+        let us1 = commit("a.txt", "one\n");
+        let us2 = commit("b.txt", "two\n");
+        let us1_again = commit("a.txt", "one\nmore\n");
+        let undone = commit("c.txt", "undone\n");
+
+        let manager = SessionManager::with_store(SessionStore::new(dir.join(".ralph-state")));
+        let mut session = manager
+            .create_session(dir.to_string_lossy().to_string(), SessionConfig::default())
+            .await
+            .unwrap();
+        let record = |iteration: u32, story_id: &str, from: &str, to: &str| IterationRecord {
+            iteration,
+            story_id: story_id.to_string(),
+            start_commit: Some(from.to_string()),
+            end_commit: Some(to.to_string()),
+            outcome: IterationOutcome::StoryComplete,
+            started_at: SystemTime::now(),
+            finished_at: SystemTime::now(),
+            rolled_back: false,
+            attempt_branch: None,
+        };
+        session.iterations = vec![
+            record(1, "US-001", &start, &us1),
+            record(2, "US-002", &us1, &us2),
+            record(3, "US-001", &us2, &us1_again),
+            IterationRecord {
+                rolled_back: true,
+                ..record(4, "US-001", &us1_again, &undone)
+            },
+        ];
+        manager.update_session(session.clone()).await.unwrap();
+
+        let diff = manager.story_diff(&session.id, "US-001").await.unwrap();
+        let paths: Vec<&str> = diff.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt"]);
+        assert_eq!((diff.from.as_str(), diff.to.as_str()), (start.as_str(), us1_again.as_str()));
+        assert_eq!(diff.additions(), 2);
+        assert_eq!(diff.files[0].patch.matches("diff --git").count(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_activity_live_only_without_since() {
+        let manager = SessionManager::new();
+        manager.broadcast_activity("s1", test_entry(1)).await;
+
+        let mut rx = manager.subscribe_to_activity("s1", None).await;
+        manager.broadcast_activity("s1", test_entry(2)).await;
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.seq, 2);
+        assert!(rx.try_recv().is_err());
+    }
 }
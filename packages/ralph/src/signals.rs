@@ -20,13 +20,16 @@ impl SignalHandler {
                 "🔄 ROTATE: Token limit reached. Committing and starting fresh iteration."
                     .to_string()
             }
-            Signal::Gutter(reason) => {
-                format!("🚨 GUTTER: Stuck state detected. {}", reason)
+            Signal::Gutter { rule, reason } => {
+                format!("🚨 GUTTER: Stuck state detected ({}). {}", rule, reason)
             }
             Signal::Complete => "✅ COMPLETE: All stories have passed!".to_string(),
             Signal::StoryComplete(id) => {
                 format!("✓ Story {} completed", id)
             }
+            Signal::StoryBlocked { story_id, attempts } => {
+                format!("⛔ Story {} blocked after {} failed attempts", story_id, attempts)
+            }
         }
     }
 }
@@ -87,8 +90,12 @@ mod tests {
         assert!(SignalHandler::format_signal(&Signal::Rotate).contains("ROTATE"));
         assert!(SignalHandler::format_signal(&Signal::Complete).contains("COMPLETE"));
 
-        let gutter = Signal::Gutter("test reason".to_string());
+        let gutter = Signal::Gutter {
+            rule: "repeated_error".to_string(),
+            reason: "test reason".to_string(),
+        };
         assert!(SignalHandler::format_signal(&gutter).contains("GUTTER"));
+        assert!(SignalHandler::format_signal(&gutter).contains("repeated_error"));
         assert!(SignalHandler::format_signal(&gutter).contains("test reason"));
     }
 }
@@ -0,0 +1,142 @@
+use crate::types::*;
+use std::path::{Path, PathBuf};
+
+/// Durable session storage.
+///
+/// Each session is written as `<state_dir>/sessions/<id>.json`. Writes go to a
+/// temporary file first and are renamed into place so a crash mid-write never
+/// leaves a truncated session behind.
+pub struct SessionStore {
+    state_dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(state_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            state_dir: state_dir.into(),
+        }
+    }
+
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+
+    fn sessions_dir(&self) -> PathBuf {
+        self.state_dir.join("sessions")
+    }
+
+    fn session_path(&self, id: &str) -> PathBuf {
+        self.sessions_dir().join(format!("{}.json", id))
+    }
+
+    /// Load every persisted session. Runs synchronously so it can be used while
+    /// constructing the `SessionManager` at server startup.
+    pub fn load_all(&self) -> Result<Vec<Session>, RalphError> {
+        let dir = self.sessions_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("Failed to read session file {:?}: {}", path, e);
+                    continue;
+                }
+            };
+
+            match serde_json::from_str::<Session>(&content) {
+                Ok(session) => sessions.push(session),
+                Err(e) => {
+                    // Skip corrupt entries rather than refusing to start the server.
+                    tracing::warn!("Skipping unreadable session file {:?}: {}", path, e);
+                }
+            }
+        }
+
+        tracing::info!("Loaded {} sessions from {:?}", sessions.len(), dir);
+        Ok(sessions)
+    }
+
+    pub async fn save(&self, session: &Session) -> Result<(), RalphError> {
+        let dir = self.sessions_dir();
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let json = serde_json::to_string_pretty(session)
+            .map_err(|e| RalphError::Io(format!("Failed to serialize session: {}", e)))?;
+
+        let path = self.session_path(&session.id);
+        let tmp_path = dir.join(format!("{}.json.tmp", session.id));
+        tokio::fs::write(&tmp_path, json).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+
+        tracing::trace!("Session {} persisted to {:?}", session.id, path);
+        Ok(())
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<(), RalphError> {
+        let path = self.session_path(id);
+        if path.exists() {
+            tokio::fs::remove_file(&path).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn test_session(id: &str, status: SessionStatus) -> Session {
+        Session {
+            id: id.to_string(),
+            project_path: "/tmp/test-project".to_string(),
+            status,
+            config: SessionConfig::default(),
+            prd: None,
+            current_iteration: 3,
+            token_usage: TokenUsage::default(),
+            handoff_notes: None,
+            handoff_reason: None,
+            worktree_path: None,
+            base_commit: None,
+            pr_url: None,
+            pr_number: None,
+            iterations: Vec::new(),
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ralph-store-{}", uuid::Uuid::new_v4()));
+        let store = SessionStore::new(&dir);
+
+        let session = test_session("abc", SessionStatus::Paused);
+        store.save(&session).await.unwrap();
+
+        let loaded = store.load_all().unwrap();
+        assert_eq!(loaded, vec![session]);
+
+        store.remove("abc").await.unwrap();
+        assert!(store.load_all().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_all_missing_dir() {
+        let dir = std::env::temp_dir().join(format!("ralph-store-{}", uuid::Uuid::new_v4()));
+        let store = SessionStore::new(&dir);
+        assert!(store.load_all().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,561 @@
+//! Typed model of cursor-agent's `--output-format stream-json` output.
+//!
+//! Every line is one JSON object tagged by `type`:
+//!
+//! - `system` (`subtype: "init"`) describes the agent session.
+//! - `user` / `assistant` carry message content blocks.
+//! - `thinking` carries reasoning deltas.
+//! - `tool_call` is emitted twice per call, `started` with the arguments and
+//!   `completed` with arguments and result. The call itself is an object keyed
+//!   by tool, e.g. `{"shellToolCall": {"args": {..}, "result": {..}}}`.
+//! - `result` ends the run with the final text and, when reported, usage.
+//!
+//! Unknown event types, content blocks and tools are kept rather than
+//! rejected so newer agent versions degrade gracefully.
+
+use crate::types::*;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    System(SystemEvent),
+    User(MessageEvent),
+    Assistant(MessageEvent),
+    Thinking(ThinkingEvent),
+    ToolCall(ToolCallEvent),
+    Result(ResultEvent),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct SystemEvent {
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct MessageEvent {
+    pub message: StreamMessage,
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct StreamMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+    /// Per-turn usage, for agents that report it on each message.
+    #[serde(default)]
+    pub usage: Option<ReportedUsage>,
+}
+
+impl StreamMessage {
+    /// All text blocks joined together.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                ContentBlock::Other => None,
+            })
+            .collect::<Vec<_>>()
+            .join("")
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text { text: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ThinkingEvent {
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ToolCallEvent {
+    pub subtype: ToolCallPhase,
+    #[serde(default)]
+    pub call_id: Option<String>,
+    pub tool_call: ToolCall,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallPhase {
+    Started,
+    Completed,
+    #[serde(other)]
+    Other,
+}
+
+/// A tool invocation, keyed in the stream by the tool's name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolCall {
+    Read(ToolInvocation<ReadArgs, ReadSuccess>),
+    Write(ToolInvocation<WriteArgs, WriteSuccess>),
+    Edit(ToolInvocation<EditArgs, EditSuccess>),
+    Shell(ToolInvocation<ShellArgs, ShellOutput, ShellOutput>),
+    /// Any other tool; `args` is kept verbatim.
+    Other { name: String, args: serde_json::Value },
+}
+
+impl<'de> Deserialize<'de> for ToolCall {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+        let Some((name, body)) = map.into_iter().next() else {
+            return Err(D::Error::custom("empty tool_call object"));
+        };
+
+        let call = match name.as_str() {
+            "readToolCall" => ToolCall::Read(serde_json::from_value(body).map_err(D::Error::custom)?),
+            "writeToolCall" => ToolCall::Write(serde_json::from_value(body).map_err(D::Error::custom)?),
+            "editToolCall" => ToolCall::Edit(serde_json::from_value(body).map_err(D::Error::custom)?),
+            "shellToolCall" => ToolCall::Shell(serde_json::from_value(body).map_err(D::Error::custom)?),
+            // Generic function calls carry their own name.
+            "function" => ToolCall::Other {
+                name: body
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("function")
+                    .to_string(),
+                args: body.get("arguments").cloned().unwrap_or_default(),
+            },
+            _ => ToolCall::Other {
+                args: body.get("args").cloned().unwrap_or_default(),
+                name,
+            },
+        };
+
+        Ok(call)
+    }
+}
+
+/// Arguments of a tool call plus its result once `completed`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(bound(deserialize = "A: Deserialize<'de>, S: Deserialize<'de>, F: Deserialize<'de>"))]
+pub struct ToolInvocation<A, S, F = serde_json::Value> {
+    pub args: A,
+    #[serde(default = "Option::default")]
+    pub result: Option<ToolResult<S, F>>,
+}
+
+/// `{"success": {..}}` or `{"failure": {..}}` / `{"error": {..}}`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(bound(deserialize = "S: Deserialize<'de>, F: Deserialize<'de>"))]
+pub struct ToolResult<S, F = serde_json::Value> {
+    #[serde(default = "Option::default")]
+    pub success: Option<S>,
+    #[serde(default = "Option::default", alias = "error")]
+    pub failure: Option<F>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ReadArgs {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadSuccess {
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub total_lines: u32,
+    #[serde(default)]
+    pub total_chars: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteArgs {
+    pub path: String,
+    #[serde(default)]
+    pub file_text: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteSuccess {
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub lines_created: u32,
+    #[serde(default)]
+    pub file_size: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct EditArgs {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EditSuccess {
+    #[serde(default)]
+    pub lines_added: u32,
+    #[serde(default)]
+    pub lines_removed: u32,
+    #[serde(default)]
+    pub diff_string: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellArgs {
+    pub command: String,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellOutput {
+    #[serde(default)]
+    pub exit_code: i32,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ResultEvent {
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub usage: Option<ReportedUsage>,
+}
+
+impl StreamEvent {
+    pub fn parse(line: &str) -> Result<Self, RalphError> {
+        serde_json::from_str(line).map_err(|e| RalphError::Parse(format!("Invalid stream-json line: {}", e)))
+    }
+
+    /// The activities this event represents.
+    ///
+    /// Tool calls are reported once they complete so the result is known;
+    /// `started` events, system/user messages and thinking deltas carry no
+    /// activity of their own. Reported usage becomes an `ActivityKind::Usage`.
+    pub fn activities(&self) -> Vec<ActivityKind> {
+        let mut activities = Vec::new();
+
+        match self {
+            StreamEvent::Assistant(event) => {
+                let text = event.message.text();
+                if !text.trim().is_empty() {
+                    activities.push(ActivityKind::Assistant(text));
+                }
+                if let Some(usage) = &event.message.usage {
+                    activities.push(ActivityKind::Usage(usage.clone()));
+                }
+            }
+            StreamEvent::ToolCall(event) if event.subtype == ToolCallPhase::Completed => {
+                activities.extend(event.tool_call.activity());
+            }
+            StreamEvent::Result(event) => {
+                if let Some(usage) = &event.usage {
+                    activities.push(ActivityKind::Usage(usage.clone()));
+                }
+                if event.is_error {
+                    activities.push(ActivityKind::Error(
+                        event
+                            .result
+                            .clone()
+                            .unwrap_or_else(|| "cursor-agent reported an error".to_string()),
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        activities
+    }
+}
+
+impl ToolCall {
+    fn activity(&self) -> Option<ActivityKind> {
+        match self {
+            ToolCall::Read(call) => match call.result.as_ref()? {
+                ToolResult { success: Some(read), .. } => Some(ActivityKind::Read {
+                    path: call.args.path.clone(),
+                    lines: read.total_lines,
+                    bytes: if read.total_chars > 0 {
+                        read.total_chars
+                    } else {
+                        read.content.len() as u32
+                    },
+                }),
+                ToolResult { failure, .. } => Some(ActivityKind::Error(format!(
+                    "Read {} failed: {}",
+                    call.args.path,
+                    describe_failure(failure.as_ref())
+                ))),
+            },
+            ToolCall::Write(call) => match call.result.as_ref()? {
+                ToolResult { success: Some(write), .. } => Some(ActivityKind::Write {
+                    path: call.args.path.clone(),
+                    lines: if write.lines_created > 0 {
+                        write.lines_created
+                    } else {
+                        call.args.file_text.lines().count() as u32
+                    },
+                    bytes: if write.file_size > 0 {
+                        write.file_size
+                    } else {
+                        call.args.file_text.len() as u32
+                    },
+                }),
+                ToolResult { failure, .. } => Some(ActivityKind::Error(format!(
+                    "Write {} failed: {}",
+                    call.args.path,
+                    describe_failure(failure.as_ref())
+                ))),
+            },
+            ToolCall::Edit(call) => match call.result.as_ref()? {
+                ToolResult { success: Some(edit), .. } => Some(ActivityKind::Write {
+                    path: call.args.path.clone(),
+                    lines: edit.lines_added + edit.lines_removed,
+                    bytes: edit.diff_string.len() as u32,
+                }),
+                ToolResult { failure, .. } => Some(ActivityKind::Error(format!(
+                    "Edit {} failed: {}",
+                    call.args.path,
+                    describe_failure(failure.as_ref())
+                ))),
+            },
+            ToolCall::Shell(call) => {
+                let result = call.result.as_ref()?;
+                let (exit_code, output) = match (&result.success, &result.failure) {
+                    (Some(output), _) => (output.exit_code, Some(output)),
+                    // A failure without an exit code still failed.
+                    (None, Some(output)) if output.exit_code != 0 => (output.exit_code, Some(output)),
+                    (None, output) => (1, output.as_ref()),
+                };
+                Some(ActivityKind::Shell {
+                    command: call.args.command.clone(),
+                    exit_code,
+                    output_bytes: output
+                        .map(|o| (o.stdout.len() + o.stderr.len()) as u32)
+                        .unwrap_or(0),
+                })
+            }
+            ToolCall::Other { .. } => None,
+        }
+    }
+}
+
+fn describe_failure(failure: Option<&serde_json::Value>) -> String {
+    let Some(failure) = failure else {
+        return "unknown error".to_string();
+    };
+
+    ["errorMessage", "message", "error"]
+        .iter()
+        .find_map(|key| failure.get(key).and_then(|v| v.as_str()))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| failure.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_fixture(fixture: &str) -> Vec<StreamEvent> {
+        fixture
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| StreamEvent::parse(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_story_complete_fixture() {
+        let events = parse_fixture(include_str!("../fixtures/stream-json/story_complete.jsonl"));
+        assert_eq!(events.len(), 15);
+
+        let StreamEvent::System(init) = &events[0] else {
+            panic!("expected system init, got {:?}", events[0]);
+        };
+        assert_eq!(init.subtype.as_deref(), Some("init"));
+        assert_eq!(init.model.as_deref(), Some("Claude 4.5 Opus (Thinking)"));
+
+        let StreamEvent::ToolCall(started) = &events[3] else {
+            panic!("expected tool call");
+        };
+        assert_eq!(started.subtype, ToolCallPhase::Started);
+        assert_eq!(started.call_id.as_deref(), Some("toolu_01"));
+        assert!(events[3].activities().is_empty());
+
+        let StreamEvent::ToolCall(shell) = &events[10] else {
+            panic!("expected tool call");
+        };
+        let ToolCall::Shell(call) = &shell.tool_call else {
+            panic!("expected shell call, got {:?}", shell.tool_call);
+        };
+        assert_eq!(call.args.command, "cargo test");
+        let output = call.result.as_ref().and_then(|r| r.success.as_ref()).unwrap();
+        assert!(output.stdout.contains("4 passed"));
+
+        let activities: Vec<ActivityKind> = events.iter().flat_map(|e| e.activities()).collect();
+        assert_eq!(
+            activities,
+            vec![
+                ActivityKind::Assistant("I'll start by reading the PRD and progress log.".into()),
+                ActivityKind::Read {
+                    path: "prd.json".into(),
+                    lines: 3,
+                    bytes: 24
+                },
+                ActivityKind::Write {
+                    path: "src/greet.rs".into(),
+                    lines: 3,
+                    bytes: 47
+                },
+                ActivityKind::Write {
+                    path: "src/lib.rs".into(),
+                    lines: 1,
+                    bytes: 45
+                },
+                ActivityKind::Shell {
+                    command: "cargo test".into(),
+                    exit_code: 0,
+                    output_bytes: 36
+                },
+                ActivityKind::Assistant(
+                    "US-001 is implemented and committed. All stories now pass.\n\n<ralph>COMPLETE</ralph>"
+                        .into()
+                ),
+                ActivityKind::Usage(ReportedUsage {
+                    input_tokens: 18234,
+                    output_tokens: 1422,
+                    cache_read_tokens: 12011,
+                    cache_write_tokens: 3096,
+                }),
+            ]
+        );
+
+        let StreamEvent::Result(result) = events.last().unwrap() else {
+            panic!("expected result");
+        };
+        assert!(!result.is_error);
+        assert_eq!(result.duration_ms, Some(48213));
+        assert_eq!(
+            result.usage,
+            Some(ReportedUsage {
+                input_tokens: 18234,
+                output_tokens: 1422,
+                cache_read_tokens: 12011,
+                cache_write_tokens: 3096,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_failing_shell_fixture() {
+        let events = parse_fixture(include_str!("../fixtures/stream-json/failing_shell.jsonl"));
+
+        assert!(matches!(&events[1], StreamEvent::Thinking(t) if t.text.contains("tests are failing")));
+
+        let activities: Vec<ActivityKind> = events.iter().flat_map(|e| e.activities()).collect();
+        assert_eq!(
+            activities[0],
+            ActivityKind::Shell {
+                command: "npm test".into(),
+                exit_code: 1,
+                output_bytes: 56
+            }
+        );
+        assert_eq!(
+            activities[1],
+            ActivityKind::Error("Read src/missing.ts failed: File not found: src/missing.ts".into())
+        );
+        assert_eq!(
+            activities.last(),
+            Some(&ActivityKind::Error("Agent stopped: repeated test failures".into()))
+        );
+    }
+
+    #[test]
+    fn test_unknown_events_and_tools_are_tolerated() {
+        let event = StreamEvent::parse(r#"{"type":"connection","subtype":"reconnecting"}"#).unwrap();
+        assert_eq!(event, StreamEvent::Unknown);
+
+        let event = StreamEvent::parse(
+            r#"{"type":"tool_call","subtype":"completed","tool_call":{"grepToolCall":{"args":{"pattern":"fn main"},"result":{"success":{}}}}}"#,
+        )
+        .unwrap();
+        let StreamEvent::ToolCall(call) = &event else {
+            panic!("expected tool call");
+        };
+        assert_eq!(
+            call.tool_call,
+            ToolCall::Other {
+                name: "grepToolCall".into(),
+                args: serde_json::json!({"pattern": "fn main"}),
+            }
+        );
+        assert!(event.activities().is_empty());
+
+        let event = StreamEvent::parse(
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"image","url":"x"}]}}"#,
+        )
+        .unwrap();
+        assert!(event.activities().is_empty());
+
+        assert!(StreamEvent::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_per_message_usage() {
+        let event = StreamEvent::parse(
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"ok"}],"usage":{"input_tokens":3,"cache_read_input_tokens":900,"cache_creation_input_tokens":100,"output_tokens":2}}}"#,
+        )
+        .unwrap();
+
+        let activities = event.activities();
+        assert_eq!(activities[0], ActivityKind::Assistant("ok".into()));
+        let ActivityKind::Usage(usage) = &activities[1] else {
+            panic!("expected usage, got {:?}", activities[1]);
+        };
+        assert_eq!(usage.context_tokens(), 1005);
+    }
+
+    #[test]
+    fn test_snake_case_usage() {
+        let event = StreamEvent::parse(
+            r#"{"type":"result","subtype":"success","is_error":false,"result":"done","usage":{"input_tokens":10,"output_tokens":5}}"#,
+        )
+        .unwrap();
+        let StreamEvent::Result(result) = event else {
+            panic!("expected result");
+        };
+        let usage = result.usage.unwrap();
+        assert_eq!(usage.input_tokens, 10);
+        assert_eq!(usage.output_tokens, 5);
+    }
+}
@@ -10,13 +10,46 @@ pub struct Session {
     pub prd: Option<Prd>,
     pub current_iteration: u32,
     pub token_usage: TokenUsage,
+    /// Notes carried into the next iteration after a context rotation or an
+    /// interrupt.
+    #[serde(default)]
+    pub handoff_notes: Option<String>,
+    /// Why the iteration that left `handoff_notes` stopped short, e.g. "paused".
+    #[serde(default)]
+    pub handoff_reason: Option<String>,
+    /// Dedicated worktree the session runs in, once it has been created.
+    #[serde(default)]
+    pub worktree_path: Option<String>,
+    /// Commit the session's branch started from, for diffs and pull requests.
+    #[serde(default)]
+    pub base_commit: Option<String>,
+    /// Pull request opened for the session's branch once it completed.
+    #[serde(default)]
+    pub pr_url: Option<String>,
+    /// The forge's number for that pull request.
+    #[serde(default)]
+    pub pr_number: Option<u64>,
+    /// Every finished iteration, oldest first.
+    #[serde(default)]
+    pub iterations: Vec<IterationRecord>,
     pub created_at: SystemTime,
     pub updated_at: SystemTime,
 }
 
+impl Session {
+    /// Directory the agent works in: the session's worktree if it has one,
+    /// otherwise the project checkout.
+    pub fn work_dir(&self) -> &str {
+        self.worktree_path.as_deref().unwrap_or(&self.project_path)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SessionStatus {
     Idle,
+    /// Waiting for a free slot or for another session in the same repository;
+    /// `position` is 1-based.
+    Queued { position: u32 },
     Running { story_id: String },
     Paused,
     WaitingForRotation,
@@ -34,6 +67,226 @@ pub struct SessionConfig {
     pub rotate_threshold: u32,
     pub branch_name: Option<String>,
     pub open_pr: bool,
+    /// Shell command (e.g. `cargo test`) that must exit 0 before a story counts
+    /// as complete.
+    #[serde(default)]
+    pub verify_command: Option<String>,
+    /// Coding agent CLI that runs the PRD conversation and the iterations.
+    #[serde(default)]
+    pub agent: AgentBackendKind,
+    /// Run in a dedicated `git worktree` on the PRD branch rather than in the
+    /// project checkout. Sessions saved before worktrees existed run in place.
+    #[serde(default)]
+    pub use_worktree: bool,
+    /// Keep the worktree once the session completes instead of removing it.
+    #[serde(default)]
+    pub keep_worktree: bool,
+    /// Branch or commit a new session branch starts from; defaults to what
+    /// the project checkout has checked out.
+    #[serde(default)]
+    pub base_branch: Option<String>,
+    /// Stash uncommitted changes in the project checkout instead of refusing
+    /// to start. Only sessions that run in place touch the checkout.
+    #[serde(default)]
+    pub stash_changes: bool,
+    /// Where `origin` is hosted, for opening pull requests.
+    #[serde(default)]
+    pub forge: ForgeKind,
+    /// Reset the branch to where an iteration started when it fails
+    /// verification or ends in the gutter, instead of building on its edits.
+    #[serde(default)]
+    pub rollback_failed: bool,
+    /// Keep rolled-back work on a `ralph/attempts/<story>-<n>` branch.
+    #[serde(default = "default_true")]
+    pub keep_attempts: bool,
+    /// When the agent counts as stuck. The session stops in
+    /// `SessionStatus::Gutter` as soon as any rule fires.
+    #[serde(default = "GutterRule::defaults", deserialize_with = "deserialize_gutter_rules")]
+    pub gutter_rules: Vec<GutterRule>,
+    /// Failed attempts a story gets before it is blocked and the loop moves
+    /// on to other stories. `None` lets a story use up `max_iterations`;
+    /// saved configs without it get `None`, new sessions default to 5.
+    #[serde(default)]
+    pub max_attempts_per_story: Option<u32>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn deserialize_gutter_rules<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<GutterRule>, D::Error> {
+    let rules = Vec::<GutterRule>::deserialize(deserializer)?;
+    for rule in &rules {
+        rule.validate().map_err(serde::de::Error::custom)?;
+    }
+    Ok(rules)
+}
+
+/// Which coding agent CLI a session drives.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub enum AgentBackendKind {
+    #[default]
+    Cursor,
+    ClaudeCode,
+    Codex,
+    Aider,
+    /// Any program that prints JSON-lines activity. `{prompt}` and `{model}`
+    /// in `args` are substituted before spawning.
+    Command { program: String, args: Vec<String> },
+}
+
+impl AgentBackendKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AgentBackendKind::Cursor => "cursor-agent",
+            AgentBackendKind::ClaudeCode => "Claude Code",
+            AgentBackendKind::Codex => "Codex CLI",
+            AgentBackendKind::Aider => "aider",
+            AgentBackendKind::Command { .. } => "Custom command",
+        }
+    }
+}
+
+/// Git forge hosting a project's `origin`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub enum ForgeKind {
+    /// GitHub, through the `gh` CLI.
+    #[default]
+    GitHub,
+    /// GitLab, through the `glab` CLI.
+    GitLab,
+    /// A Gitea instance at `url`, through its REST API. The token is read
+    /// from `GITEA_TOKEN`.
+    Gitea { url: String },
+}
+
+impl ForgeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "GitHub",
+            ForgeKind::GitLab => "GitLab",
+            ForgeKind::Gitea { .. } => "Gitea",
+        }
+    }
+}
+
+/// A way for the agent to be stuck, with the thresholds that decide it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum GutterRule {
+    /// The same command failed `count` times in one iteration. Flags and
+    /// paths are ignored, so `cargo test --lib` and `cargo test src/a.rs`
+    /// count as the same command.
+    RepeatedCommandFailure { count: u32 },
+    /// One file was written `count` times within `window_secs`.
+    FileThrashing { count: u32, window_secs: u64 },
+    /// The agent ran no `git commit` and `HEAD` didn't move for `minutes`,
+    /// across iterations. Only checked as the agent's activity arrives.
+    NoCommit { minutes: u64 },
+    /// The same error was reported `count` times in one iteration. Numbers
+    /// are ignored, so line numbers and counts may differ.
+    RepeatedError { count: u32 },
+    /// Files were written back to content they had earlier in the iteration
+    /// `count` times.
+    RevertedEdits { count: u32 },
+    /// `iterations` iterations in a row ended without changing any files.
+    NoFileChanges { iterations: u32 },
+}
+
+impl GutterRule {
+    /// Stable name reported in `Signal::Gutter` when this rule fires.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GutterRule::RepeatedCommandFailure { .. } => "repeated_command_failure",
+            GutterRule::FileThrashing { .. } => "file_thrashing",
+            GutterRule::NoCommit { .. } => "no_commit",
+            GutterRule::RepeatedError { .. } => "repeated_error",
+            GutterRule::RevertedEdits { .. } => "reverted_edits",
+            GutterRule::NoFileChanges { .. } => "no_file_changes",
+        }
+    }
+
+    /// Refuse thresholds of 0, which would fire on the first event or never.
+    pub fn validate(&self) -> Result<(), String> {
+        let thresholds: &[(&str, u64)] = match self {
+            GutterRule::RepeatedCommandFailure { count }
+            | GutterRule::RepeatedError { count }
+            | GutterRule::RevertedEdits { count } => &[("count", *count as u64)],
+            GutterRule::FileThrashing { count, window_secs } => &[("count", *count as u64), ("window_secs", *window_secs)],
+            GutterRule::NoCommit { minutes } => &[("minutes", *minutes)],
+            GutterRule::NoFileChanges { iterations } => &[("iterations", *iterations as u64)],
+        };
+        match thresholds.iter().find(|(_, value)| *value == 0) {
+            Some((field, _)) => Err(format!("Gutter rule {}: {} must be at least 1", self.name(), field)),
+            None => Ok(()),
+        }
+    }
+
+    /// The rules a session uses unless configured otherwise.
+    pub fn defaults() -> Vec<GutterRule> {
+        vec![
+            GutterRule::RepeatedCommandFailure { count: 3 },
+            GutterRule::FileThrashing {
+                count: 5,
+                window_secs: 600,
+            },
+        ]
+    }
+
+    /// Every rule, with suggested thresholds.
+    pub fn all() -> Vec<GutterRule> {
+        vec![
+            GutterRule::RepeatedCommandFailure { count: 3 },
+            GutterRule::FileThrashing {
+                count: 5,
+                window_secs: 600,
+            },
+            GutterRule::NoCommit { minutes: 30 },
+            GutterRule::RepeatedError { count: 3 },
+            GutterRule::RevertedEdits { count: 2 },
+            GutterRule::NoFileChanges { iterations: 2 },
+        ]
+    }
+}
+
+/// A pull (or merge) request on a forge.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: String,
+    pub title: String,
+    pub state: PrState,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PrState {
+    Open,
+    Closed,
+    Merged,
+}
+
+/// A model an agent backend accepts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentModel {
+    /// Value passed to the agent's `--model` flag.
+    pub id: String,
+    pub label: String,
+}
+
+impl AgentModel {
+    pub fn new(id: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+        }
+    }
+}
+
+/// One commit, as `git log --oneline` shows it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommitSummary {
+    /// Abbreviated hash.
+    pub hash: String,
+    pub subject: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -53,6 +306,17 @@ impl Default for SessionConfig {
             rotate_threshold: 80_000,
             branch_name: None,
             open_pr: false,
+            verify_command: None,
+            agent: AgentBackendKind::Cursor,
+            use_worktree: true,
+            keep_worktree: false,
+            base_branch: None,
+            stash_changes: false,
+            forge: ForgeKind::GitHub,
+            rollback_failed: false,
+            keep_attempts: true,
+            gutter_rules: GutterRule::defaults(),
+            max_attempts_per_story: Some(5),
         }
     }
 }
@@ -65,15 +329,159 @@ pub struct Prd {
     pub stories: Vec<Story>,
 }
 
+/// Something `check_prd` found in a PRD.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrdIssue {
+    pub severity: IssueSeverity,
+    /// The story the issue is about, when it is about one.
+    pub story_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    /// Ralph won't run the PRD until it is fixed.
+    Error,
+    /// Worth a look, but doesn't stop the PRD from running.
+    Warning,
+}
+
+/// A story in `prd.json`. Stored with both `status` and the `passes` flag
+/// the agent flips, see `StoryRecord`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "StoryRecord", into = "StoryRecord")]
 pub struct Story {
     pub id: String,
     pub title: String,
     pub description: String,
     pub acceptance_criteria: Vec<String>,
     pub priority: u32,
-    pub passes: bool,
+    pub status: StoryStatus,
     pub notes: String,
+    /// Ids of stories that must pass before this one is picked.
+    pub depends_on: Vec<String>,
+    /// Every iteration spent on this story since it was last reopened,
+    /// oldest first.
+    pub attempts: Vec<StoryAttempt>,
+}
+
+impl Story {
+    pub fn passes(&self) -> bool {
+        self.status == StoryStatus::Done
+    }
+
+    /// Attempts that count against `SessionConfig::max_attempts_per_story`.
+    pub fn failed_attempts(&self) -> u32 {
+        self.attempts.iter().filter(|a| a.outcome.is_failure()).count() as u32
+    }
+}
+
+/// Where a story stands.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StoryStatus {
+    #[default]
+    Pending,
+    /// An iteration is working on it, or handed it on to the next one.
+    InProgress,
+    /// Used up `SessionConfig::max_attempts_per_story`.
+    Blocked,
+    /// Set aside by hand or to get out of the gutter.
+    Skipped,
+    /// The last attempt failed verification; the next one starts by
+    /// reviewing what is there.
+    NeedsReview,
+    Done,
+}
+
+impl StoryStatus {
+    /// Whether the loop may pick a story with this status.
+    pub fn is_workable(&self) -> bool {
+        matches!(self, StoryStatus::Pending | StoryStatus::InProgress | StoryStatus::NeedsReview)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StoryStatus::Pending => "Pending",
+            StoryStatus::InProgress => "In progress",
+            StoryStatus::Blocked => "Blocked",
+            StoryStatus::Skipped => "Skipped",
+            StoryStatus::NeedsReview => "Needs review",
+            StoryStatus::Done => "Done",
+        }
+    }
+}
+
+/// `Story` as written to `prd.json`. `passes` is kept alongside `status`
+/// because the agent marks a story complete by flipping it, and files from
+/// before `status` existed only have `passes`; when the two disagree,
+/// `passes` wins.
+#[derive(Serialize, Deserialize)]
+struct StoryRecord {
+    id: String,
+    title: String,
+    description: String,
+    acceptance_criteria: Vec<String>,
+    priority: u32,
+    passes: bool,
+    #[serde(default)]
+    status: Option<StoryStatus>,
+    notes: String,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    #[serde(default)]
+    attempts: Vec<StoryAttempt>,
+}
+
+impl From<StoryRecord> for Story {
+    fn from(record: StoryRecord) -> Self {
+        let status = match (record.status, record.passes) {
+            (_, true) => StoryStatus::Done,
+            (Some(StoryStatus::Done) | None, false) => StoryStatus::Pending,
+            (Some(status), false) => status,
+        };
+        Story {
+            id: record.id,
+            title: record.title,
+            description: record.description,
+            acceptance_criteria: record.acceptance_criteria,
+            priority: record.priority,
+            status,
+            notes: record.notes,
+            depends_on: record.depends_on,
+            attempts: record.attempts,
+        }
+    }
+}
+
+impl From<Story> for StoryRecord {
+    fn from(story: Story) -> Self {
+        StoryRecord {
+            passes: story.passes(),
+            id: story.id,
+            title: story.title,
+            description: story.description,
+            acceptance_criteria: story.acceptance_criteria,
+            priority: story.priority,
+            status: Some(story.status),
+            notes: story.notes,
+            depends_on: story.depends_on,
+            attempts: story.attempts,
+        }
+    }
+}
+
+/// One iteration's work on a story.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StoryAttempt {
+    /// Matches `IterationRecord::iteration`.
+    pub iteration: u32,
+    pub outcome: IterationOutcome,
+    /// Tokens the iteration added to the context.
+    pub tokens: u32,
+    /// Commits the iteration left on the branch, oldest first.
+    pub commits: Vec<CommitSummary>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -83,6 +491,64 @@ pub struct TokenUsage {
     pub write: u32,
     pub assistant: u32,
     pub shell: u32,
+    /// Where `total` came from. The per-kind breakdown is always estimated.
+    #[serde(default)]
+    pub source: TokenSource,
+}
+
+/// How a token count was obtained.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub enum TokenSource {
+    /// Nothing has been counted yet.
+    #[default]
+    Unknown,
+    /// Reported by the agent's own usage events.
+    Reported,
+    /// Estimated with the named estimator.
+    Estimated(String),
+}
+
+impl TokenSource {
+    pub fn is_measured(&self) -> bool {
+        matches!(self, TokenSource::Reported)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            TokenSource::Unknown => "no usage yet".to_string(),
+            TokenSource::Reported => "measured".to_string(),
+            TokenSource::Estimated(estimator) => format!("estimated ({})", estimator),
+        }
+    }
+}
+
+/// Tokens one activity added to the context, and how they were counted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenCount {
+    pub tokens: u32,
+    pub source: TokenSource,
+}
+
+/// Usage as reported by the agent. Key spellings differ between agents, hence
+/// the aliases.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ReportedUsage {
+    #[serde(default, alias = "inputTokens")]
+    pub input_tokens: u32,
+    #[serde(default, alias = "outputTokens")]
+    pub output_tokens: u32,
+    #[serde(default, alias = "cacheReadTokens", alias = "cache_read_input_tokens")]
+    pub cache_read_tokens: u32,
+    #[serde(default, alias = "cacheWriteTokens", alias = "cache_creation_input_tokens")]
+    pub cache_write_tokens: u32,
+}
+
+impl ReportedUsage {
+    /// Tokens occupying the context window: the full prompt (cached or not)
+    /// plus the generated output.
+    pub fn context_tokens(&self) -> u32 {
+        self.input_tokens + self.cache_read_tokens + self.cache_write_tokens + self.output_tokens
+    }
 }
 
 impl TokenUsage {
@@ -106,9 +572,12 @@ impl TokenUsage {
 pub enum Signal {
     Warn,
     Rotate,
-    Gutter(String),
+    /// The agent is stuck; `rule` is the `GutterRule::name` that fired.
+    Gutter { rule: String, reason: String },
     Complete,
     StoryComplete(String),
+    /// A story used up its attempts and was set aside.
+    StoryBlocked { story_id: String, attempts: u32 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -117,6 +586,19 @@ pub struct ActivityEntry {
     pub iteration: u32,
     pub kind: ActivityKind,
     pub health: ContextHealth,
+    /// Tokens this activity added to the context, when it added any.
+    #[serde(default)]
+    pub tokens: Option<TokenCount>,
+}
+
+/// An `ActivityEntry` tagged with its per-session sequence number.
+///
+/// Sequence numbers start at 1 and increase monotonically, so a client that
+/// reconnects can ask for everything after the last `seq` it saw.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityEvent {
+    pub seq: u64,
+    pub entry: ActivityEntry,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -134,10 +616,170 @@ pub enum ActivityKind {
     Shell {
         command: String,
         exit_code: i32,
+        #[serde(default)]
+        output_bytes: u32,
     },
+    /// Text the agent said to the user.
+    Assistant(String),
+    /// Token usage reported by the agent.
+    Usage(ReportedUsage),
     TokenUpdate(TokenUsage),
     Signal(Signal),
+    Verification(VerificationCheck),
     Error(String),
+    /// How a session was brought out of the gutter.
+    Recovery(GutterRecovery),
+    /// prd.json was edited outside Ralph, by the agent or a person.
+    PrdEdited(PrdEdit),
+}
+
+impl ActivityKind {
+    /// Stable lowercase name used for filtering activity history.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActivityKind::Read { .. } => "read",
+            ActivityKind::Write { .. } => "write",
+            ActivityKind::Shell { .. } => "shell",
+            ActivityKind::Assistant(_) => "assistant",
+            ActivityKind::Usage(_) => "usage",
+            ActivityKind::TokenUpdate(_) => "token_update",
+            ActivityKind::Signal(_) => "signal",
+            ActivityKind::Verification(_) => "verification",
+            ActivityKind::Error(_) => "error",
+            ActivityKind::Recovery(_) => "recovery",
+            ActivityKind::PrdEdited(_) => "prd_edited",
+        }
+    }
+
+    /// Whether this reports a finished tool call (including a failed one).
+    pub fn is_tool_call(&self) -> bool {
+        matches!(
+            self,
+            ActivityKind::Read { .. }
+                | ActivityKind::Write { .. }
+                | ActivityKind::Shell { .. }
+                | ActivityKind::Error(_)
+        )
+    }
+}
+
+/// A way out of `SessionStatus::Gutter`. Each one resumes the session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum GutterRecovery {
+    /// Carry on with the stuck story, with failure detection starting over.
+    Resume,
+    /// Leave the stuck story for a human and move on to the next one.
+    SkipStory,
+    /// Record a guardrail capturing the lesson, then resume.
+    AddGuardrail(Guardrail),
+    /// Roll back the stuck iteration and retry it with another execution model.
+    RetryWithModel { model: String },
+}
+
+impl GutterRecovery {
+    /// One-line description for the activity history.
+    pub fn describe(&self) -> String {
+        match self {
+            GutterRecovery::Resume => "Resumed after the gutter".to_string(),
+            GutterRecovery::SkipStory => "Skipped the stuck story".to_string(),
+            GutterRecovery::AddGuardrail(guardrail) => {
+                format!("Added guardrail \"{}\" and resumed", guardrail.title)
+            }
+            GutterRecovery::RetryWithModel { model } => {
+                format!("Rolled back and retrying with {}", model)
+            }
+        }
+    }
+}
+
+/// An edit of prd.json made outside Ralph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrdEdit {
+    /// What the edit changed, e.g. "US-001 notes".
+    pub changes: Vec<String>,
+    /// Changes that clashed with the session's own and were not taken.
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    /// Whether the edit has been merged into the session yet.
+    pub merged: bool,
+}
+
+impl PrdEdit {
+    /// One-line description for the activity history.
+    pub fn describe(&self) -> String {
+        let mut text = if self.merged {
+            "Merged prd.json edits".to_string()
+        } else {
+            "prd.json changed on disk".to_string()
+        };
+        if !self.changes.is_empty() {
+            text.push_str(&format!(": {}", self.changes.join(", ")));
+        }
+        if !self.conflicts.is_empty() {
+            text.push_str(&format!("; kept the session's {}", self.conflicts.join(", ")));
+        }
+        text
+    }
+}
+
+/// Outcome of one story-completion check run after an iteration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerificationCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl VerificationCheck {
+    pub fn passed(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn failed(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// A story is only complete when every check agrees.
+pub fn all_passed(checks: &[VerificationCheck]) -> bool {
+    !checks.is_empty() && checks.iter().all(|c| c.passed)
+}
+
+/// Criteria for paging through persisted activity history.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ActivityFilter {
+    /// Only entries from this iteration.
+    pub iteration: Option<u32>,
+    /// Only entries whose `ActivityKind::name` is listed; empty means all kinds.
+    pub kinds: Vec<String>,
+}
+
+impl ActivityFilter {
+    pub fn matches(&self, entry: &ActivityEntry) -> bool {
+        if let Some(iteration) = self.iteration {
+            if entry.iteration != iteration {
+                return false;
+            }
+        }
+
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k == entry.kind.name())
+    }
+}
+
+/// One page of activity history.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityPage {
+    pub events: Vec<ActivityEvent>,
+    /// More matching events exist after the last one in `events`.
+    pub has_more: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -174,11 +816,118 @@ pub struct Guardrail {
     pub added_after: String,
 }
 
+/// What one iteration of the loop did to the session's branch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IterationRecord {
+    /// Matches `ActivityEntry::iteration` of the activities it produced.
+    pub iteration: u32,
+    pub story_id: String,
+    /// `HEAD` before the agent started.
+    pub start_commit: Option<String>,
+    /// `HEAD` once the iteration ended, including any `wip:` commit Ralph made.
+    pub end_commit: Option<String>,
+    pub outcome: IterationOutcome,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    /// The branch was reset to `start_commit`, undoing this iteration.
+    #[serde(default)]
+    pub rolled_back: bool,
+    /// Branch keeping the undone work, when it was kept.
+    #[serde(default)]
+    pub attempt_branch: Option<String>,
+}
+
+impl IterationRecord {
+    /// Whether the iteration left any commits on the branch.
+    pub fn has_commits(&self) -> bool {
+        self.end_commit.is_some() && self.start_commit != self.end_commit
+    }
+}
+
+/// How an iteration ended.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum IterationOutcome {
+    StoryComplete,
+    Unverified { reason: String },
+    Rotated,
+    Interrupted,
+    Gutter { reason: String },
+    Failed { error: String },
+}
+
+impl IterationOutcome {
+    /// Why the iteration failed, for outcomes that mean the story wasn't done.
+    /// Rotations and interruptions hand the work on rather than fail it.
+    pub fn failure(&self) -> Option<&str> {
+        match self {
+            IterationOutcome::Unverified { reason } | IterationOutcome::Gutter { reason } => Some(reason),
+            IterationOutcome::Failed { error } => Some(error),
+            IterationOutcome::StoryComplete | IterationOutcome::Rotated | IterationOutcome::Interrupted => None,
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        self.failure().is_some()
+    }
+}
+
+/// Changes between two commits.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Diff {
+    pub from: String,
+    pub to: String,
+    pub files: Vec<FileDiff>,
+}
+
+impl Diff {
+    pub fn additions(&self) -> u32 {
+        self.files.iter().map(|f| f.additions).sum()
+    }
+
+    pub fn deletions(&self) -> u32 {
+        self.files.iter().map(|f| f.deletions).sum()
+    }
+
+    /// Add the changes of `later`, a diff that starts where this one ends or
+    /// after it. A file changed in both keeps both patches, in order.
+    pub fn append(&mut self, later: Diff) {
+        self.to = later.to;
+        for file in later.files {
+            match self.files.iter_mut().find(|f| f.path == file.path) {
+                Some(existing) => {
+                    existing.additions += file.additions;
+                    existing.deletions += file.deletions;
+                    existing.binary |= file.binary;
+                    existing.patch.push_str(&file.patch);
+                }
+                None => self.files.push(file),
+            }
+        }
+    }
+}
+
+/// One file's changes in a `Diff`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    pub additions: u32,
+    pub deletions: u32,
+    /// Binary files have no line counts and an empty patch.
+    pub binary: bool,
+    /// Unified diff of this file, starting at its `diff --git` line.
+    pub patch: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum IterationResult {
     StoryComplete,
+    /// The agent exited but the story failed verification; the reason lists
+    /// the failed checks.
+    Unverified(String),
     Rotate,
-    Gutter(String),
+    Gutter { rule: String, reason: String },
+    /// The run was cut short by a pause, stop or shutdown.
+    Interrupted,
 }
 
 // Conversation types for PRD generation
@@ -264,6 +1013,12 @@ pub enum RalphError {
     #[error("Cursor agent error: {0}")]
     CursorAgent(String),
 
+    #[error("Agent error: {0}")]
+    Agent(String),
+
+    #[error("Forge error: {0}")]
+    Forge(String),
+
     #[error("Parse error: {0}")]
     Parse(String),
 
@@ -272,6 +1027,9 @@ pub enum RalphError {
 
     #[error("Invalid session state: {0}")]
     InvalidState(String),
+
+    #[error("Invalid PRD: {0}")]
+    InvalidPrd(String),
 }
 
 impl From<std::io::Error> for RalphError {
@@ -294,6 +1052,17 @@ mod tests {
             rotate_threshold: 80_000,
             branch_name: None,
             open_pr: false,
+            verify_command: None,
+            agent: AgentBackendKind::Cursor,
+            use_worktree: true,
+            keep_worktree: false,
+            base_branch: None,
+            stash_changes: false,
+            forge: ForgeKind::GitHub,
+            rollback_failed: false,
+            keep_attempts: true,
+            gutter_rules: GutterRule::defaults(),
+            max_attempts_per_story: Some(5),
         };
 
         assert_eq!(config.prd_model, "sonnet-4.5-thinking");
@@ -307,4 +1076,78 @@ mod tests {
         assert_eq!(config.prd_model, "opus-4.5-thinking");
         assert_eq!(config.execution_model, "opus-4.5-thinking");
     }
+
+    #[test]
+    fn test_saved_config_gets_default_gutter_rules() {
+        let json = r#"{"prd_model":"a","execution_model":"b","max_iterations":5,"warn_threshold":1,"rotate_threshold":2,"branch_name":null,"open_pr":false}"#;
+        let config: SessionConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.gutter_rules, GutterRule::defaults());
+        // Sessions from before the limit keep running without one.
+        assert_eq!(config.max_attempts_per_story, None);
+    }
+
+    #[test]
+    fn test_config_refuses_zero_gutter_thresholds() {
+        let config = |rules: &str| {
+            let json = format!(
+                r#"{{"prd_model":"a","execution_model":"b","max_iterations":5,"warn_threshold":1,"rotate_threshold":2,"branch_name":null,"open_pr":false,"gutter_rules":{}}}"#,
+                rules
+            );
+            serde_json::from_str::<SessionConfig>(&json)
+        };
+        assert!(config(r#"[{"RepeatedError":{"count":2}},{"NoCommit":{"minutes":30}}]"#).is_ok());
+        let error = config(r#"[{"NoFileChanges":{"iterations":0}}]"#).unwrap_err().to_string();
+        assert!(error.contains("Gutter rule no_file_changes: iterations must be at least 1"), "{}", error);
+        assert!(config(r#"[{"FileThrashing":{"count":5,"window_secs":0}}]"#).is_err());
+    }
+
+    #[test]
+    fn test_story_status_migrates_from_passes() {
+        let story = |extra: &str| -> Story {
+            let json = format!(
+                r#"{{"id":"US-001","title":"T","description":"","acceptance_criteria":[],"priority":1,"notes":""{}}}"#,
+                extra
+            );
+            serde_json::from_str(&json).unwrap()
+        };
+        assert_eq!(story(r#","passes":false"#).status, StoryStatus::Pending);
+        assert_eq!(story(r#","passes":true"#).status, StoryStatus::Done);
+        assert_eq!(story(r#","passes":false,"status":"blocked""#).status, StoryStatus::Blocked);
+        // The agent flipped `passes` without touching `status`, or a person
+        // flipped it back.
+        assert_eq!(story(r#","passes":true,"status":"in_progress""#).status, StoryStatus::Done);
+        assert_eq!(story(r#","passes":false,"status":"done""#).status, StoryStatus::Pending);
+
+        let json = serde_json::to_value(story(r#","passes":true"#)).unwrap();
+        assert_eq!(json["passes"], true);
+        assert_eq!(json["status"], "done");
+    }
+
+    #[test]
+    fn test_failed_attempts_leave_out_handoffs() {
+        let attempt = |outcome| StoryAttempt {
+            iteration: 0,
+            outcome,
+            tokens: 0,
+            commits: Vec::new(),
+        };
+        let story = Story {
+            id: "US-001".to_string(),
+            title: "Story".to_string(),
+            description: String::new(),
+            acceptance_criteria: Vec::new(),
+            priority: 1,
+            status: StoryStatus::Pending,
+            notes: String::new(),
+            depends_on: Vec::new(),
+            attempts: vec![
+                attempt(IterationOutcome::Unverified { reason: "no commit".to_string() }),
+                attempt(IterationOutcome::Rotated),
+                attempt(IterationOutcome::Interrupted),
+                attempt(IterationOutcome::Gutter { reason: "stuck".to_string() }),
+            ],
+        };
+        assert_eq!(story.failed_attempts(), 2);
+        assert_eq!(story.attempts[0].outcome.failure(), Some("no commit"));
+    }
 }
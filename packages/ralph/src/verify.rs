@@ -0,0 +1,211 @@
+use crate::git::GitOperations;
+use crate::prd::parse_prd;
+use crate::types::*;
+use std::path::Path;
+use tokio::process::Command;
+
+/// How long the configured verification command may run.
+const VERIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(900);
+
+/// Independent checks that an iteration really finished its story, rather than
+/// trusting a clean agent exit.
+pub struct StoryVerifier {
+    project_path: String,
+}
+
+impl StoryVerifier {
+    pub fn new(project_path: String) -> Self {
+        Self { project_path }
+    }
+
+    /// Run every check for `story_id`.
+    ///
+    /// `start_head` is the commit `HEAD` pointed at when the iteration began
+    /// (`None` for a repository without commits).
+    pub async fn verify(
+        &self,
+        story_id: &str,
+        start_head: Option<&str>,
+        verify_command: Option<&str>,
+    ) -> Vec<VerificationCheck> {
+        let mut checks = vec![
+            self.check_prd_marked(story_id).await,
+            self.check_new_commit(start_head).await,
+        ];
+
+        if let Some(command) = verify_command.filter(|c| !c.trim().is_empty()) {
+            checks.push(self.check_command(command).await);
+        }
+
+        checks
+    }
+
+    /// Did the agent set `passes: true` for the story in `prd.json`?
+    async fn check_prd_marked(&self, story_id: &str) -> VerificationCheck {
+        let name = "prd_marked";
+        let prd_path = Path::new(&self.project_path).join("prd.json");
+
+        let prd = match tokio::fs::read_to_string(&prd_path).await {
+            Ok(content) => match parse_prd(&content) {
+                Ok(prd) => prd,
+                Err(e) => return VerificationCheck::failed(name, format!("prd.json is invalid: {}", e)),
+            },
+            Err(e) => return VerificationCheck::failed(name, format!("Failed to read prd.json: {}", e)),
+        };
+
+        match prd.stories.iter().find(|s| s.id == story_id) {
+            Some(story) if story.passes() => {
+                VerificationCheck::passed(name, format!("{} has passes: true in prd.json", story_id))
+            }
+            Some(_) => {
+                VerificationCheck::failed(name, format!("{} still has passes: false in prd.json", story_id))
+            }
+            None => VerificationCheck::failed(name, format!("{} is missing from prd.json", story_id)),
+        }
+    }
+
+    /// Did the iteration commit anything?
+    async fn check_new_commit(&self, start_head: Option<&str>) -> VerificationCheck {
+        let name = "new_commit";
+        let git = GitOperations::new(self.project_path.clone());
+
+        match (git.head_commit().await, start_head) {
+            (Ok(Some(head)), Some(start)) if head != start => {
+                VerificationCheck::passed(name, format!("HEAD moved {} → {}", short(start), short(&head)))
+            }
+            (Ok(Some(head)), None) => {
+                VerificationCheck::passed(name, format!("First commit {}", short(&head)))
+            }
+            (Ok(_), _) => VerificationCheck::failed(name, "No commit since the iteration started"),
+            (Err(e), _) => VerificationCheck::failed(name, format!("Failed to read HEAD: {}", e)),
+        }
+    }
+
+    /// Does the project's own verification command (typecheck/test) pass?
+    async fn check_command(&self, command: &str) -> VerificationCheck {
+        let name = "verify_command";
+        tracing::info!("🧪 Running verification command: {}", command);
+
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&self.project_path)
+            .kill_on_drop(true)
+            .output();
+
+        match tokio::time::timeout(VERIFY_TIMEOUT, output).await {
+            Ok(Ok(output)) if output.status.success() => {
+                VerificationCheck::passed(name, format!("`{}` passed", command))
+            }
+            Ok(Ok(output)) => {
+                // Test runners often report failures on stdout, so keep both.
+                let tails: Vec<String> = [&output.stdout, &output.stderr]
+                    .iter()
+                    .map(|stream| String::from_utf8_lossy(stream).trim_end().to_string())
+                    .filter(|text| !text.is_empty())
+                    .map(|text| crate::prompt::tail_lines(&text, 20))
+                    .collect();
+                VerificationCheck::failed(
+                    name,
+                    format!("`{}` exited with {}\n{}", command, output.status, tails.join("\n")),
+                )
+            }
+            Ok(Err(e)) => VerificationCheck::failed(name, format!("Failed to run `{}`: {}", command, e)),
+            Err(_) => VerificationCheck::failed(
+                name,
+                format!("`{}` timed out after {:?}", command, VERIFY_TIMEOUT),
+            ),
+        }
+    }
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(8)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .await
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    async fn temp_repo() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ralph-verify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "-q"]).await;
+        git(&dir, &["config", "user.email", "ralph@example.com"]).await;
+        git(&dir, &["config", "user.name", "Ralph"]).await;
+        dir
+    }
+
+    fn write_prd(dir: &Path, passes: bool) {
+        let prd = Prd {
+            project: "Demo".to_string(),
+            branch_name: "ralph/demo".to_string(),
+            description: String::new(),
+            stories: vec![Story {
+                id: "US-001".to_string(),
+                title: "First".to_string(),
+                description: String::new(),
+                acceptance_criteria: vec!["Typecheck passes".to_string()],
+                priority: 1,
+                status: if passes { StoryStatus::Done } else { StoryStatus::Pending },
+                notes: String::new(),
+                depends_on: Vec::new(),
+                attempts: Vec::new(),
+            }],
+        };
+        std::fs::write(dir.join("prd.json"), serde_json::to_string(&prd).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_all_checks_agree() {
+        let dir = temp_repo().await;
+        write_prd(&dir, false);
+        git(&dir, &["add", "-A"]).await;
+        git(&dir, &["commit", "-qm", "init"]).await;
+
+        let verifier = StoryVerifier::new(dir.to_string_lossy().to_string());
+        let start = GitOperations::new(dir.to_string_lossy().to_string())
+            .head_commit()
+            .await
+            .unwrap();
+
+        // Nothing happened yet.
+        let checks = verifier.verify("US-001", start.as_deref(), Some("true")).await;
+        assert!(!all_passed(&checks));
+        assert!(!checks[0].passed);
+        assert!(!checks[1].passed);
+        assert!(checks[2].passed);
+
+        // Agent marks the story and commits.
+        write_prd(&dir, true);
+        git(&dir, &["commit", "-qam", "feat: US-001 - First"]).await;
+
+        let checks = verifier.verify("US-001", start.as_deref(), Some("true")).await;
+        assert!(all_passed(&checks));
+
+        // A failing verification command vetoes the story, with its output.
+        let command = "echo 'test login ... FAILED'; echo 'error: 1 test failed' >&2; exit 3";
+        let checks = verifier.verify("US-001", start.as_deref(), Some(command)).await;
+        assert!(!all_passed(&checks));
+        assert!(checks[2].detail.contains("test login ... FAILED"));
+        assert!(checks[2].detail.contains("error: 1 test failed"));
+
+        // So does a prd.json Ralph wouldn't accept.
+        std::fs::write(dir.join("prd.json"), "{ \"stories\": [] }").unwrap();
+        let checks = verifier.verify("US-001", start.as_deref(), None).await;
+        assert!(!checks[0].passed);
+        assert!(checks[0].detail.starts_with("prd.json is invalid"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
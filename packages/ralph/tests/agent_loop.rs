@@ -0,0 +1,1288 @@
+//! End-to-end runs of the Ralph loop against a temporary git repository, with
+//! the agent replaced by a scripted `FakeAgentBackend`.
+
+use ralph::{
+    ActivityFilter, ActivityKind, FakeAgentBackend, FakeForge, FakeStep, GutterRecovery, GutterRule,
+    Guardrail, IterationOutcome, PrState, Prd, PrdEdit, ReportedUsage, Session, SessionConfig, SessionManager,
+    SessionStatus, Signal, Story, StoryStatus,
+};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// A throwaway git repository with one initial commit.
+struct TempRepo {
+    path: PathBuf,
+}
+
+impl TempRepo {
+    fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("ralph-loop-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        let repo = Self { path };
+
+        repo.git(&["init", "-q"]);
+        repo.git(&["config", "user.email", "ralph@example.com"]);
+        repo.git(&["config", "user.name", "Ralph"]);
+        std::fs::write(repo.path.join(".gitignore"), ".ralph/\n").unwrap();
+        repo.git(&["add", "-A"]);
+        repo.git(&["commit", "-q", "-m", "initial"]);
+        repo
+    }
+
+    fn path_string(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed: {:?}", args, output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    /// Add a bare repository next to this one as `origin`.
+    fn add_origin(&self) -> PathBuf {
+        let origin = self.origin_path();
+        let output = Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&origin)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git init --bare failed: {:?}", output);
+        self.git(&["remote", "add", "origin", &origin.to_string_lossy()]);
+        origin
+    }
+
+    fn origin_path(&self) -> PathBuf {
+        self.path.with_extension("origin.git")
+    }
+
+    fn commit_subjects(&self) -> Vec<String> {
+        self.git(&["log", "--format=%s"])
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+        let _ = std::fs::remove_dir_all(self.origin_path());
+    }
+}
+
+fn story(id: &str, priority: u32) -> Story {
+    Story {
+        id: id.to_string(),
+        title: format!("Story {}", id),
+        description: "Do the thing".to_string(),
+        acceptance_criteria: vec!["It works".to_string()],
+        priority,
+        status: StoryStatus::Pending,
+        notes: String::new(),
+        depends_on: Vec::new(),
+        attempts: Vec::new(),
+    }
+}
+
+fn prd(stories: Vec<Story>) -> Prd {
+    Prd {
+        project: "loop-test".to_string(),
+        branch_name: "ralph/loop-test".to_string(),
+        description: "Integration test PRD".to_string(),
+        stories,
+    }
+}
+
+fn complete_story(story_id: &str) -> Vec<FakeStep> {
+    vec![
+        FakeStep::Write {
+            path: format!("src/{}.txt", story_id),
+            content: format!("{} done\n", story_id),
+        },
+        FakeStep::MarkPassing {
+            story_id: story_id.to_string(),
+        },
+        FakeStep::Commit {
+            message: format!("feat: {}", story_id),
+        },
+        FakeStep::Say {
+            text: "<ralph>COMPLETE</ralph>".to_string(),
+        },
+    ]
+}
+
+/// Config for a session that works directly in the test repository's
+/// checkout, so its files and commits can be inspected there.
+fn in_place() -> SessionConfig {
+    SessionConfig {
+        use_worktree: false,
+        ..SessionConfig::default()
+    }
+}
+
+/// Create and start a session for `repo` whose agent is `fake`.
+async fn start(
+    repo: &TempRepo,
+    fake: &FakeAgentBackend,
+    config: SessionConfig,
+    stories: Vec<Story>,
+) -> (SessionManager, Session) {
+    let manager = SessionManager::new().with_backend_factory(fake.factory());
+    let session = manager
+        .create_session(repo.path_string(), config)
+        .await
+        .unwrap();
+    manager.set_prd(&session.id, prd(stories)).await.unwrap();
+    let session = manager.start_session(&session.id).await.unwrap();
+    (manager, session)
+}
+
+/// Wait until the session leaves the queued and running states.
+async fn wait_until_settled(manager: &SessionManager, id: &str) -> Session {
+    wait_for(manager, id, |s| {
+        !matches!(
+            s.status,
+            SessionStatus::Queued { .. }
+                | SessionStatus::Running { .. }
+                | SessionStatus::WaitingForRotation
+        )
+    })
+    .await
+}
+
+async fn wait_for(
+    manager: &SessionManager,
+    id: &str,
+    done: impl Fn(&Session) -> bool,
+) -> Session {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(30);
+    loop {
+        let session = manager.get_session(id).await.unwrap();
+        if done(&session) {
+            return session;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "session never settled, last status: {:?}",
+            session.status
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+fn read_prd(path: &Path) -> Prd {
+    serde_json::from_str(&std::fs::read_to_string(path.join("prd.json")).unwrap()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stories_complete_from_transcript() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::from_transcript(
+        repo.path_string(),
+        include_str!("../fixtures/fake/two_stories.jsonl"),
+    )
+    .unwrap();
+
+    let (manager, session) = start(
+        &repo,
+        &fake,
+        in_place(),
+        vec![story("US-002", 2), story("US-001", 1)],
+    )
+    .await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.current_iteration, 2);
+    assert!(session.prd.unwrap().stories.iter().all(|s| s.passes()));
+    assert!(read_prd(&repo.path).stories.iter().all(|s| s.passes()));
+    assert_eq!(std::fs::read_to_string(repo.path.join("src/greeting.txt")).unwrap(), "hello\n");
+
+    let subjects = repo.commit_subjects();
+    assert_eq!(subjects[..2], ["feat: US-002 - farewell", "feat: US-001 - greeting"]);
+
+    // Stories are worked on in priority order, one per prompt.
+    let prompts = fake.prompts();
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[0].contains("US-001"));
+    assert!(prompts[1].contains("US-002"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stories_wait_for_their_dependencies() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(complete_story("US-002"))
+        .with_iteration(complete_story("US-001"));
+
+    // US-001 has the better priority but can't start until US-002 passes.
+    let mut first = story("US-001", 1);
+    first.depends_on = vec!["US-002".to_string()];
+    let (manager, session) = start(&repo, &fake, in_place(), vec![first, story("US-002", 2)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    let prompts = fake.prompts();
+    assert!(prompts[0].contains("US-002"));
+    assert!(prompts[1].contains("US-001"));
+
+    let mut cyclic = story("US-002", 2);
+    cyclic.depends_on = vec!["US-001".to_string()];
+    let mut first = story("US-001", 1);
+    first.depends_on = vec!["US-002".to_string()];
+    let session = manager.create_session(repo.path_string(), in_place()).await.unwrap();
+    let err = manager.set_prd(&session.id, prd(vec![first, cyclic])).await.unwrap_err();
+    assert!(err.to_string().contains("cycle"), "{}", err);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unverified_story_is_retried() {
+    let repo = TempRepo::new();
+    // The first attempt claims completion without committing anything.
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![FakeStep::Say {
+            text: "<ralph>COMPLETE</ralph>".to_string(),
+        }])
+        .with_iteration(complete_story("US-001"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.current_iteration, 2);
+    assert_eq!(fake.prompts().len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unverified_claim_is_cleared_from_prd() {
+    let repo = TempRepo::new();
+    // The first attempt marks the story passing without committing; the
+    // second commits but leaves prd.json alone, so neither is verified.
+    let claim = vec![
+        FakeStep::MarkPassing {
+            story_id: "US-001".to_string(),
+        },
+        FakeStep::Say {
+            text: "<ralph>COMPLETE</ralph>".to_string(),
+        },
+    ];
+    let mut commit_only = complete_story("US-001");
+    commit_only.retain(|step| !matches!(step, FakeStep::MarkPassing { .. }));
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(claim)
+        .with_iteration(commit_only);
+
+    let config = SessionConfig {
+        max_iterations: 2,
+        ..in_place()
+    };
+    let (manager, session) = start(&repo, &fake, config, vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(
+        session.status,
+        SessionStatus::Failed {
+            error: "Max iterations reached".into()
+        }
+    );
+    let outcomes: Vec<bool> = session.iterations.iter().map(|r| r.outcome.is_failure()).collect();
+    assert_eq!(outcomes, vec![true, true]);
+    let on_disk = read_prd(&repo.path);
+    assert_eq!(on_disk.stories[0].status, StoryStatus::NeedsReview);
+    let raw = std::fs::read_to_string(repo.path.join("prd.json")).unwrap();
+    assert!(raw.contains("\"passes\": false"), "{}", raw);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotation_commits_wip_and_hands_off() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![
+            FakeStep::Write {
+                path: "src/partial.txt".to_string(),
+                content: "half done\n".to_string(),
+            },
+            FakeStep::Usage(ReportedUsage {
+                input_tokens: 90_000,
+                output_tokens: 500,
+                ..Default::default()
+            }),
+        ])
+        .with_iteration(complete_story("US-001"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.current_iteration, 2);
+
+    let subjects = repo.commit_subjects();
+    assert!(
+        subjects.iter().any(|s| s.starts_with("wip: US-001")),
+        "no WIP commit in {:?}",
+        subjects
+    );
+
+    // The iteration after the rotation starts from the handoff notes.
+    let prompts = fake.prompts();
+    assert!(!prompts[0].contains("wip: US-001"));
+    assert!(prompts[1].contains("wip: US-001"));
+    assert!(prompts[1].contains("was rotated at the context rotation threshold"));
+    assert!(!repo.path.join(".ralph/steer.md").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_repeated_failures_end_in_gutter() {
+    let repo = TempRepo::new();
+    let failing = FakeStep::Shell {
+        command: "cargo build --offline-nope".to_string(),
+    };
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(vec![
+        failing.clone(),
+        FakeStep::Error {
+            message: "build is broken".to_string(),
+        },
+        failing.clone(),
+        failing,
+    ]);
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    match session.status {
+        SessionStatus::Gutter { reason } => assert!(reason.contains("cargo build --offline-nope")),
+        other => panic!("expected gutter, got {:?}", other),
+    }
+    assert_eq!(fake.prompts().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_configured_gutter_rules_replace_defaults() {
+    let repo = TempRepo::new();
+    let failing = FakeStep::Shell {
+        command: "cargo build --offline-nope".to_string(),
+    };
+    // Three failed builds would trip the default rules, but only idle
+    // iterations count here.
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![failing.clone(), failing.clone(), failing]);
+    let config = SessionConfig {
+        max_iterations: 5,
+        gutter_rules: vec![GutterRule::NoFileChanges { iterations: 2 }],
+        ..in_place()
+    };
+
+    let (manager, session) = start(&repo, &fake, config, vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(
+        session.status,
+        SessionStatus::Gutter {
+            reason: "2 iterations in a row changed no files".to_string()
+        }
+    );
+    assert_eq!(fake.prompts().len(), 2);
+
+    let filter = ActivityFilter {
+        iteration: None,
+        kinds: vec!["signal".to_string()],
+    };
+    let page = manager.get_activity(&session.id, 0, 100, &filter).await.unwrap();
+    let rules: Vec<String> = page
+        .events
+        .into_iter()
+        .filter_map(|event| match event.entry.kind {
+            ActivityKind::Signal(Signal::Gutter { rule, .. }) => Some(rule),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rules, ["no_file_changes"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_story_out_of_attempts_is_blocked() {
+    let repo = TempRepo::new();
+    // Two attempts at US-001 claim completion without committing anything.
+    let unverified = vec![FakeStep::Say {
+        text: "<ralph>COMPLETE</ralph>".to_string(),
+    }];
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(unverified.clone())
+        .with_iteration(unverified)
+        .with_iteration(complete_story("US-002"));
+    let config = SessionConfig {
+        max_attempts_per_story: Some(2),
+        ..in_place()
+    };
+
+    let (manager, session) = start(&repo, &fake, config, vec![story("US-001", 1), story("US-002", 2)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(
+        session.status,
+        SessionStatus::Failed {
+            error: "Stories ran out of attempts: US-001".to_string()
+        }
+    );
+    assert_eq!(fake.prompts().len(), 3);
+
+    let stories = session.prd.unwrap().stories;
+    assert_eq!(stories[0].status, StoryStatus::Blocked);
+    let iterations: Vec<u32> = stories[0].attempts.iter().map(|a| a.iteration).collect();
+    assert_eq!(iterations, [0, 1]);
+    assert_eq!(stories[0].failed_attempts(), 2);
+    assert!(stories[0].attempts[0].outcome.failure().unwrap().contains("failed verification"));
+    assert!(stories[0].attempts[0].commits.is_empty());
+
+    let done = &stories[1].attempts[0];
+    assert_eq!(done.outcome, IterationOutcome::StoryComplete);
+    assert_eq!(done.commits.len(), 1);
+    assert_eq!(done.commits[0].subject, "feat: US-002");
+    assert!(done.tokens > 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_skip_and_reopen_stories() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(complete_story("US-002"))
+        .with_iteration(complete_story("US-001"));
+
+    let manager = SessionManager::new().with_backend_factory(fake.factory());
+    let session = manager.create_session(repo.path_string(), in_place()).await.unwrap();
+    manager
+        .set_prd(&session.id, prd(vec![story("US-001", 1), story("US-002", 2)]))
+        .await
+        .unwrap();
+    assert!(manager.skip_story(&session.id, "US-009").await.is_err());
+    let skipped = manager.skip_story(&session.id, "US-001").await.unwrap();
+    assert_eq!(skipped.prd.unwrap().stories[0].status, StoryStatus::Skipped);
+    assert_eq!(read_prd(&repo.path).stories[0].status, StoryStatus::Skipped);
+
+    // The session completes with the skipped story left for later.
+    manager.start_session(&session.id).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.prd.as_ref().unwrap().stories[0].status, StoryStatus::Skipped);
+    assert_eq!(fake.prompts().len(), 1);
+    assert!(fake.prompts()[0].contains("US-002"));
+
+    let reopened = manager.reopen_story(&session.id, "US-001").await.unwrap();
+    assert_eq!(reopened.prd.unwrap().stories[0].status, StoryStatus::Pending);
+    manager.start_session(&session.id).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert!(manager.skip_story(&session.id, "US-001").await.is_err(), "already done");
+}
+
+async fn prd_edits(manager: &SessionManager, id: &str) -> Vec<PrdEdit> {
+    let filter = ActivityFilter {
+        iteration: None,
+        kinds: vec!["prd_edited".to_string()],
+    };
+    let page = manager.get_activity(id, 0, 100, &filter).await.unwrap();
+    page.events
+        .into_iter()
+        .filter_map(|event| match event.entry.kind {
+            ActivityKind::PrdEdited(edit) => Some(edit),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_agent_prd_edits_are_merged() {
+    let repo = TempRepo::new();
+    let mut edited = prd(vec![story("US-001", 1), story("US-002", 2), story("US-003", 3)]);
+    edited.stories[0].status = StoryStatus::Done;
+    edited.stories[1].notes = "Reuse src/US-001.txt".to_string();
+    let first = vec![
+        FakeStep::Write {
+            path: "src/US-001.txt".to_string(),
+            content: "US-001 done\n".to_string(),
+        },
+        FakeStep::Write {
+            path: "prd.json".to_string(),
+            content: serde_json::to_string_pretty(&edited).unwrap(),
+        },
+        // Long enough for the watcher to notice
+        FakeStep::Hang { secs: 2 },
+        FakeStep::Commit {
+            message: "feat: US-001".to_string(),
+        },
+        FakeStep::Say {
+            text: "<ralph>COMPLETE</ralph>".to_string(),
+        },
+    ];
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(first)
+        .with_iteration(complete_story("US-002"))
+        .with_iteration(complete_story("US-003"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1), story("US-002", 2)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert_eq!(session.status, SessionStatus::Complete);
+
+    // Writing US-001 as done kept the agent's edits instead of overwriting them
+    let stories = session.prd.unwrap().stories;
+    assert_eq!(stories.len(), 3);
+    assert_eq!(stories[1].notes, "Reuse src/US-001.txt");
+    assert!(stories.iter().all(|s| s.status == StoryStatus::Done));
+    let on_disk = read_prd(&repo.path);
+    assert_eq!(on_disk.stories[1].notes, "Reuse src/US-001.txt");
+
+    let edits = prd_edits(&manager, &session.id).await;
+    let seen = edits.iter().find(|e| !e.merged).expect("watcher reports the edit");
+    assert_eq!(seen.changes, vec!["US-002 notes", "US-003 added", "US-001 marked done"]);
+    let merged = edits.iter().find(|e| e.merged).expect("loop merges the edit");
+    assert_eq!(merged.changes, vec!["US-002 notes", "US-003 added"]);
+    assert!(merged.conflicts.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_invalid_prd_is_refused() {
+    let repo = TempRepo::new();
+    let manager = SessionManager::new();
+    let session = manager.create_session(repo.path_string(), in_place()).await.unwrap();
+
+    let mut duplicate = prd(vec![story("US-001", 1), story("US-001", 2)]);
+    duplicate.branch_name = "ralph/two words".to_string();
+    let error = manager.set_prd(&session.id, duplicate).await.unwrap_err().to_string();
+    assert!(error.contains("US-001 is used by 2 stories"), "{}", error);
+    assert!(error.contains("not a valid git branch name"), "{}", error);
+    assert!(manager.get_session(&session.id).await.unwrap().prd.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_iterations_fails_session() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string());
+    let config = SessionConfig {
+        max_iterations: 3,
+        ..in_place()
+    };
+
+    // With no scripts left the agent exits without doing anything.
+    let (manager, session) = start(&repo, &fake, config, vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(
+        session.status,
+        SessionStatus::Failed {
+            error: "Max iterations reached".to_string()
+        }
+    );
+    assert_eq!(session.current_iteration, 3);
+    assert_eq!(fake.prompts().len(), 3);
+    assert!(!read_prd(&repo.path).stories[0].passes());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_agent_fails_session() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(vec![
+        FakeStep::Say {
+            text: "giving up".to_string(),
+        },
+        FakeStep::Exit { code: 2 },
+    ]);
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert!(
+        matches!(&session.status, SessionStatus::Failed { error } if error.contains("exit")),
+        "unexpected status {:?}",
+        session.status
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shutdown_interrupts_hung_agent() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![FakeStep::Hang { secs: 120 }]);
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+
+    // Wait until the agent has actually been started.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while fake.prompts().is_empty() {
+        assert!(tokio::time::Instant::now() < deadline, "agent never started");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let started = std::time::Instant::now();
+    manager.shutdown();
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Paused);
+    assert!(started.elapsed() < Duration::from_secs(30));
+    assert_eq!(session.current_iteration, 0);
+}
+
+/// Wait until the fake agent has written `path` in `repo`.
+async fn wait_for_file(repo: &TempRepo, path: &str) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while !repo.path.join(path).exists() {
+        assert!(tokio::time::Instant::now() < deadline, "{} never appeared", path);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stop_kills_agent_and_commits_leftovers() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(vec![
+        FakeStep::Write {
+            path: "src/started.txt".to_string(),
+            content: "work in progress\n".to_string(),
+        },
+        FakeStep::Hang { secs: 120 },
+    ]);
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    wait_for_file(&repo, "src/started.txt").await;
+
+    let started = std::time::Instant::now();
+    let session = manager.stop_session(&session.id).await.unwrap();
+
+    // The final state is recorded by the time stop returns.
+    assert!(started.elapsed() < Duration::from_secs(30));
+    assert_eq!(session.status, SessionStatus::Idle);
+    assert_eq!(session.current_iteration, 0);
+    assert!(session.handoff_notes.unwrap().contains("stopped"));
+    assert_eq!(
+        repo.commit_subjects()[0],
+        "wip: US-001 - partial implementation (stopped)"
+    );
+    assert_eq!(manager.get_session(&session.id).await.unwrap().status, SessionStatus::Idle);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pause_finishes_current_tool_call() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![
+            FakeStep::Write {
+                path: "src/before.txt".to_string(),
+                content: "before\n".to_string(),
+            },
+            FakeStep::Shell {
+                command: "sleep 1 && echo built > src/built.txt".to_string(),
+            },
+            // The model thinks for a while before its next tool call.
+            FakeStep::Hang { secs: 2 },
+            FakeStep::Write {
+                path: "src/after.txt".to_string(),
+                content: "after\n".to_string(),
+            },
+            FakeStep::Hang { secs: 120 },
+        ])
+        .with_iteration(complete_story("US-001"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    wait_for_file(&repo, "src/before.txt").await;
+
+    let session = manager.pause_session(&session.id).await.unwrap();
+
+    assert_eq!(session.status, SessionStatus::Paused);
+    assert!(repo.path.join("src/built.txt").exists(), "running tool call was cut short");
+    assert!(!repo.path.join("src/after.txt").exists(), "agent kept going after pause");
+    assert_eq!(
+        repo.commit_subjects()[0],
+        "wip: US-001 - partial implementation (paused)"
+    );
+
+    // Resuming hands the paused work to the next iteration.
+    manager.start_session(&session.id).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert_eq!(session.status, SessionStatus::Complete);
+    let prompts = fake.prompts();
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[1].contains("US-001 (Story US-001) was paused before it finished"));
+    assert!(!prompts[1].contains("rotation"), "a pause is not a rotation");
+}
+
+/// Create a session for `repo` with a one-story PRD, without starting it.
+async fn create(manager: &SessionManager, repo: &TempRepo) -> Session {
+    let session = manager
+        .create_session(repo.path_string(), in_place())
+        .await
+        .unwrap();
+    manager
+        .set_prd(&session.id, prd(vec![story("US-001", 1)]))
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_concurrency_limit_queues_sessions() {
+    let repos = [TempRepo::new(), TempRepo::new(), TempRepo::new()];
+    let fakes: Vec<FakeAgentBackend> = repos
+        .iter()
+        .map(|repo| {
+            let mut steps = vec![FakeStep::Hang { secs: 1 }];
+            steps.extend(complete_story("US-001"));
+            FakeAgentBackend::new(repo.path_string()).with_iteration(steps)
+        })
+        .collect();
+
+    // Each session gets the fake scripted for its repository.
+    let by_path: std::collections::HashMap<String, FakeAgentBackend> = repos
+        .iter()
+        .map(|repo| repo.path_string())
+        .zip(fakes.iter().cloned())
+        .collect();
+    let factory: ralph::BackendFactory = std::sync::Arc::new(move |session: &Session| {
+        Box::new(by_path[&session.project_path].clone()) as Box<dyn ralph::AgentBackend>
+    });
+    let manager = SessionManager::new()
+        .with_backend_factory(factory)
+        .with_max_concurrent_sessions(1);
+
+    let mut ids = Vec::new();
+    for repo in &repos {
+        ids.push(create(&manager, repo).await.id);
+    }
+
+    let first = manager.start_session(&ids[0]).await.unwrap();
+    assert!(matches!(first.status, SessionStatus::Running { .. }));
+    let second = manager.start_session(&ids[1]).await.unwrap();
+    assert_eq!(second.status, SessionStatus::Queued { position: 1 });
+    let third = manager.start_session(&ids[2]).await.unwrap();
+    assert_eq!(third.status, SessionStatus::Queued { position: 2 });
+
+    // Pausing a queued session takes it out of line.
+    let second = manager.pause_session(&ids[1]).await.unwrap();
+    assert_eq!(second.status, SessionStatus::Paused);
+    assert_eq!(
+        manager.get_session(&ids[2]).await.unwrap().status,
+        SessionStatus::Queued { position: 1 }
+    );
+
+    let third = wait_for(&manager, &ids[2], |s| !matches!(s.status, SessionStatus::Queued { .. })).await;
+    assert!(!matches!(
+        manager.get_session(&ids[0]).await.unwrap().status,
+        SessionStatus::Running { .. }
+    ));
+    assert_ne!(third.status, SessionStatus::Paused);
+
+    assert_eq!(wait_until_settled(&manager, &ids[0]).await.status, SessionStatus::Complete);
+    assert_eq!(wait_until_settled(&manager, &ids[2]).await.status, SessionStatus::Complete);
+    assert_eq!(manager.get_session(&ids[1]).await.unwrap().status, SessionStatus::Paused);
+    assert!(fakes[1].prompts().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sessions_in_one_repository_take_turns() {
+    let repo = TempRepo::new();
+    let mut first_run = vec![FakeStep::Hang { secs: 1 }];
+    first_run.extend(complete_story("US-001"));
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(first_run)
+        .with_iteration(vec![
+            FakeStep::Write {
+                path: "src/second.txt".to_string(),
+                content: "second\n".to_string(),
+            },
+            FakeStep::Commit {
+                message: "feat: second session".to_string(),
+            },
+            FakeStep::Say {
+                text: "<ralph>COMPLETE</ralph>".to_string(),
+            },
+        ]);
+    let manager = SessionManager::new().with_backend_factory(fake.factory());
+
+    let first = create(&manager, &repo).await;
+    let second = create(&manager, &repo).await;
+
+    manager.start_session(&first.id).await.unwrap();
+    let second = manager.start_session(&second.id).await.unwrap();
+    // A free slot is not enough while the repository is busy.
+    assert_eq!(second.status, SessionStatus::Queued { position: 1 });
+
+    assert_eq!(wait_until_settled(&manager, &first.id).await.status, SessionStatus::Complete);
+    assert_eq!(wait_until_settled(&manager, &second.id).await.status, SessionStatus::Complete);
+    assert_eq!(
+        repo.commit_subjects()[..2],
+        ["feat: second session".to_string(), "feat: US-001".to_string()]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_worktree_sessions_share_a_repository() {
+    let repo = TempRepo::new();
+    let worktrees = std::env::temp_dir().join(format!("ralph-worktrees-{}", uuid::Uuid::new_v4()));
+    let mut script = vec![FakeStep::Hang { secs: 1 }];
+    script.extend(complete_story("US-001"));
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(script.clone())
+        .with_iteration(script);
+    let manager = SessionManager::new()
+        .with_backend_factory(fake.factory())
+        .with_worktree_root(&worktrees);
+
+    let mut ids = Vec::new();
+    for (branch, keep_worktree) in [("ralph/kept", true), ("ralph/removed", false)] {
+        let config = SessionConfig {
+            branch_name: Some(branch.to_string()),
+            keep_worktree,
+            ..SessionConfig::default()
+        };
+        let session = manager.create_session(repo.path_string(), config).await.unwrap();
+        manager
+            .set_prd(&session.id, prd(vec![story("US-001", 1)]))
+            .await
+            .unwrap();
+        ids.push(session.id);
+    }
+
+    // Different branches of one repository run side by side.
+    for id in &ids {
+        let session = manager.start_session(id).await.unwrap();
+        assert!(matches!(session.status, SessionStatus::Running { .. }));
+    }
+
+    let kept = wait_until_settled(&manager, &ids[0]).await;
+    let removed = wait_until_settled(&manager, &ids[1]).await;
+    assert_eq!(kept.status, SessionStatus::Complete);
+    assert_eq!(removed.status, SessionStatus::Complete);
+
+    // The developer's checkout is untouched; the work is on the branches.
+    assert_eq!(repo.commit_subjects(), vec!["initial".to_string()]);
+    let initial = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+    assert_eq!(kept.base_commit.as_deref(), Some(initial.as_str()));
+    assert!(!repo.path.join("prd.json").exists());
+    assert!(!repo.path.join("src").exists());
+    for branch in ["ralph/kept", "ralph/removed"] {
+        assert_eq!(
+            repo.git(&["log", "-1", "--format=%s", branch]).trim(),
+            "feat: US-001"
+        );
+    }
+
+    let kept_path = PathBuf::from(kept.worktree_path.expect("kept worktree"));
+    assert!(kept_path.starts_with(&worktrees));
+    assert!(kept_path.join("src/US-001.txt").exists());
+    assert_eq!(removed.worktree_path, None);
+    assert_eq!(
+        repo.git(&["worktree", "list", "--porcelain"])
+            .lines()
+            .filter(|line| line.starts_with("worktree "))
+            .count(),
+        2
+    );
+
+    repo.git(&["worktree", "remove", "--force", &kept_path.to_string_lossy()]);
+    let _ = std::fs::remove_dir_all(&worktrees);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_in_place_session_branches_from_base() {
+    let repo = TempRepo::new();
+    let main = repo.git(&["branch", "--show-current"]).trim().to_string();
+    repo.git(&["checkout", "-q", "-b", "develop"]);
+    std::fs::write(repo.path.join("develop.txt"), "develop\n").unwrap();
+    repo.git(&["add", "-A"]);
+    repo.git(&["commit", "-q", "-m", "develop work"]);
+    let develop = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+    repo.git(&["checkout", "-q", &main]);
+
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(complete_story("US-001"));
+    let config = SessionConfig {
+        base_branch: Some("develop".to_string()),
+        ..in_place()
+    };
+    let (manager, session) = start(&repo, &fake, config, vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.base_commit.as_deref(), Some(develop.as_str()));
+    assert_eq!(repo.git(&["branch", "--show-current"]).trim(), "ralph/loop-test");
+    assert_eq!(
+        repo.commit_subjects(),
+        vec!["feat: US-001".to_string(), "develop work".to_string(), "initial".to_string()]
+    );
+    assert!(fake.prompts()[0].contains("Ralph has already checked it out"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dirty_checkout_blocks_start_unless_stashing() {
+    let repo = TempRepo::new();
+    std::fs::write(repo.path.join(".gitignore"), ".ralph/\ntarget/\n").unwrap();
+    std::fs::write(repo.path.join("notes.txt"), "scratch\n").unwrap();
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(complete_story("US-001"));
+    let manager = SessionManager::new().with_backend_factory(fake.factory());
+
+    let session = manager.create_session(repo.path_string(), in_place()).await.unwrap();
+    manager
+        .set_prd(&session.id, prd(vec![story("US-001", 1)]))
+        .await
+        .unwrap();
+    let err = manager.start_session(&session.id).await.unwrap_err();
+    assert!(
+        err.to_string().contains(".gitignore") && err.to_string().contains("notes.txt"),
+        "unexpected error: {}",
+        err
+    );
+    assert_eq!(manager.get_session(&session.id).await.unwrap().status, SessionStatus::Idle);
+
+    let config = SessionConfig {
+        stash_changes: true,
+        ..in_place()
+    };
+    let session = manager.create_session(repo.path_string(), config).await.unwrap();
+    manager
+        .set_prd(&session.id, prd(vec![story("US-001", 1)]))
+        .await
+        .unwrap();
+    manager.start_session(&session.id).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert!(repo
+        .git(&["stash", "list"])
+        .contains(&format!("ralph: before session {}", session.id)));
+    assert!(!repo.path.join("notes.txt").exists());
+    assert_eq!(repo.commit_subjects()[0], "feat: US-001");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_complete_session_pushes_branch() {
+    let repo = TempRepo::new();
+    let origin = repo.add_origin();
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(complete_story("US-001"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.pr_url, None);
+    let pushed = Command::new("git")
+        .args(["log", "-1", "--format=%s", "ralph/loop-test"])
+        .current_dir(&origin)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&pushed.stdout).trim(), "feat: US-001");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_complete_session_opens_pull_request() {
+    let repo = TempRepo::new();
+    repo.add_origin();
+    let forge_dir = repo.path.with_extension("forge");
+    let forge = FakeForge::new(&forge_dir);
+    let fake = FakeAgentBackend::new(repo.path_string()).with_iteration(complete_story("US-001"));
+
+    let manager = SessionManager::new()
+        .with_backend_factory(fake.factory())
+        .with_forge_factory(forge.factory())
+        .with_worktree_root(repo.path.with_extension("worktrees"));
+    let config = SessionConfig {
+        open_pr: true,
+        ..SessionConfig::default()
+    };
+    let session = manager.create_session(repo.path_string(), config).await.unwrap();
+    manager.set_prd(&session.id, prd(vec![story("US-001", 1)])).await.unwrap();
+    manager.start_session(&session.id).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    let pulls = forge.pulls();
+    assert_eq!(pulls.len(), 1, "exactly one pull request is opened");
+    assert_eq!(pulls[0].head, "ralph/loop-test");
+    assert_eq!(pulls[0].title, "loop-test: Story US-001");
+    assert!(pulls[0].body.contains("### US-001 - Story US-001"));
+    assert!(pulls[0].body.contains("feat: US-001"));
+    assert_eq!(session.pr_number, Some(pulls[0].number));
+    assert!(session.pr_url.is_some());
+
+    manager.comment_on_pull_request(&session.id, "Ready for review").await.unwrap();
+    forge.merge(pulls[0].number).unwrap();
+    let status = manager.pull_request_status(&session.id).await.unwrap();
+    assert_eq!(status.state, PrState::Merged);
+    assert_eq!(forge.pulls()[0].comments, vec!["Ready for review"]);
+
+    let _ = std::fs::remove_dir_all(&forge_dir);
+    let _ = std::fs::remove_dir_all(repo.path.with_extension("worktrees"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pull_request_opened_by_hand_is_kept() {
+    let repo = TempRepo::new();
+    repo.add_origin();
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.git(&["push", "-q", "origin", "feature"]);
+    let forge_dir = repo.path.with_extension("forge");
+    let forge = FakeForge::new(&forge_dir);
+
+    let manager = SessionManager::new().with_forge_factory(forge.factory());
+    let config = SessionConfig {
+        base_branch: Some("release".to_string()),
+        ..in_place()
+    };
+    let session = manager.create_session(repo.path_string(), config).await.unwrap();
+    let pr = manager
+        .open_pull_request(&session.id, "feature".to_string(), "Feature".to_string(), "Body".to_string())
+        .await
+        .unwrap();
+
+    let pulls = forge.pulls();
+    assert_eq!(pulls.len(), 1);
+    assert_eq!(pulls[0].base.as_deref(), Some("release"));
+    let session = manager.get_session(&session.id).await.unwrap();
+    assert_eq!(session.pr_number, Some(pr.number));
+    assert_eq!(session.pr_url, Some(pr.url));
+
+    let _ = std::fs::remove_dir_all(&forge_dir);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_iterations_record_their_commits() {
+    let repo = TempRepo::new();
+    // US-001 needs a second attempt; the first leaves nothing committed.
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![FakeStep::Say {
+            text: "<ralph>COMPLETE</ralph>".to_string(),
+        }])
+        .with_iteration(complete_story("US-001"))
+        .with_iteration(complete_story("US-002"));
+
+    let (manager, session) = start(
+        &repo,
+        &fake,
+        in_place(),
+        vec![story("US-001", 1), story("US-002", 2)],
+    )
+    .await;
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert_eq!(session.status, SessionStatus::Complete);
+
+    let outcomes: Vec<_> = session.iterations.iter().map(|r| (r.iteration, r.outcome.clone())).collect();
+    assert!(matches!(outcomes[0], (0, IterationOutcome::Unverified { .. })));
+    assert_eq!(outcomes[1..], [(1, IterationOutcome::StoryComplete), (2, IterationOutcome::StoryComplete)]);
+    assert!(!session.iterations[0].has_commits());
+    assert_eq!(session.iterations[1].end_commit, session.iterations[2].start_commit);
+
+    let diff = manager.iteration_diff(&session.id, 2).await.unwrap();
+    let paths: Vec<&str> = diff.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["prd.json", "src/US-002.txt"]);
+    assert_eq!(diff.files[1].additions, 1);
+    assert!(diff.files[1].patch.contains("+US-002 done"));
+
+    let story_diff = manager.story_diff(&session.id, "US-001").await.unwrap();
+    assert!(story_diff.files.iter().any(|f| f.path == "src/US-001.txt"));
+    assert!(!story_diff.files.iter().any(|f| f.path == "src/US-002.txt"));
+    assert!(manager.iteration_diff(&session.id, 7).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_iteration_is_rolled_back() {
+    let repo = TempRepo::new();
+    // The first attempt commits a broken change, never marks the story and
+    // leaves an edit behind.
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(vec![
+            FakeStep::Write {
+                path: "src/broken.txt".to_string(),
+                content: "broken\n".to_string(),
+            },
+            FakeStep::Commit {
+                message: "feat: US-001 - broken".to_string(),
+            },
+            FakeStep::Write {
+                path: "src/leftover.txt".to_string(),
+                content: "half done\n".to_string(),
+            },
+        ])
+        .with_iteration(complete_story("US-001"));
+    let config = SessionConfig {
+        rollback_failed: true,
+        ..in_place()
+    };
+
+    let (manager, session) = start(&repo, &fake, config, vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert!(!repo.path.join("src/broken.txt").exists());
+    assert!(!repo.path.join("src/leftover.txt").exists());
+    assert_eq!(repo.commit_subjects()[..2], ["feat: US-001", "initial"]);
+
+    // The bad attempt, leftovers included, is kept for inspection.
+    let first = &session.iterations[0];
+    assert!(first.rolled_back);
+    assert_eq!(first.attempt_branch.as_deref(), Some("ralph/attempts/US-001-1"));
+    let attempt = repo.git(&["log", "--format=%s", "ralph/attempts/US-001-1"]);
+    assert!(attempt.starts_with("wip: US-001 - rolled back attempt\nfeat: US-001 - broken\n"));
+    assert!(!session.iterations[1].rolled_back);
+
+    // The retry is told why.
+    let notes = &session.prd.unwrap().stories[0].notes;
+    assert!(notes.contains("A previous attempt was rolled back: US-001 failed verification"));
+    assert!(fake.prompts()[1].contains("A previous attempt was rolled back"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rollback_iteration_reopens_stories() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(complete_story("US-001"))
+        .with_iteration(complete_story("US-002"));
+    let config = SessionConfig {
+        keep_attempts: false,
+        ..in_place()
+    };
+
+    let (manager, session) = start(
+        &repo,
+        &fake,
+        config,
+        vec![story("US-001", 1), story("US-002", 2)],
+    )
+    .await;
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert_eq!(session.status, SessionStatus::Complete);
+
+    let session = manager.rollback_iteration(&session.id, 1).await.unwrap();
+
+    assert_eq!(session.status, SessionStatus::Idle);
+    assert_eq!(
+        repo.git(&["rev-parse", "HEAD"]).trim(),
+        session.iterations[1].start_commit.as_deref().unwrap()
+    );
+    assert!(!repo.path.join("src/US-002.txt").exists());
+    let passes: Vec<bool> = session.prd.unwrap().stories.iter().map(|s| s.passes()).collect();
+    assert_eq!(passes, [true, false]);
+    assert_eq!(
+        read_prd(&repo.path).stories.iter().map(|s| s.passes()).collect::<Vec<_>>(),
+        [true, false]
+    );
+    assert!(repo.git(&["branch", "--list", "ralph/attempts/*"]).trim().is_empty());
+    assert!(manager.rollback_iteration(&session.id, 1).await.is_err(), "already rolled back");
+}
+
+/// An iteration that fails the same command until the session hits the gutter.
+fn gutter_iteration() -> Vec<FakeStep> {
+    let failing = FakeStep::Shell {
+        command: "cargo build --offline-nope".to_string(),
+    };
+    vec![failing.clone(), failing.clone(), failing]
+}
+
+async fn recoveries(manager: &SessionManager, id: &str) -> Vec<GutterRecovery> {
+    let filter = ActivityFilter {
+        iteration: None,
+        kinds: vec!["recovery".to_string()],
+    };
+    let page = manager.get_activity(id, 0, 100, &filter).await.unwrap();
+    page.events
+        .into_iter()
+        .filter_map(|event| match event.entry.kind {
+            ActivityKind::Recovery(recovery) => Some(recovery),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gutter_recovery_skips_stuck_story() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(gutter_iteration())
+        .with_iteration(complete_story("US-002"));
+
+    let (manager, session) = start(
+        &repo,
+        &fake,
+        in_place(),
+        vec![story("US-001", 1), story("US-002", 2)],
+    )
+    .await;
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert!(matches!(session.status, SessionStatus::Gutter { .. }));
+    assert!(manager
+        .recover_from_gutter("missing", GutterRecovery::Resume)
+        .await
+        .is_err());
+
+    manager.recover_from_gutter(&session.id, GutterRecovery::SkipStory).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    // US-002 gets done and the session completes without the stuck story.
+    let statuses: Vec<StoryStatus> = session.prd.as_ref().unwrap().stories.iter().map(|s| s.status).collect();
+    assert_eq!(statuses, [StoryStatus::Skipped, StoryStatus::Done]);
+    assert!(fake.prompts()[1].contains("US-002"));
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(recoveries(&manager, &session.id).await, [GutterRecovery::SkipStory]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gutter_recovery_retries_with_another_model() {
+    let repo = TempRepo::new();
+    let mut stuck = vec![
+        FakeStep::Write {
+            path: "src/attempt.txt".to_string(),
+            content: "wrong approach\n".to_string(),
+        },
+        FakeStep::Commit {
+            message: "feat: US-001 - wrong approach".to_string(),
+        },
+    ];
+    stuck.extend(gutter_iteration());
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(stuck)
+        .with_iteration(complete_story("US-001"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    let session = wait_until_settled(&manager, &session.id).await;
+    assert!(matches!(session.status, SessionStatus::Gutter { .. }));
+
+    let recovery = GutterRecovery::RetryWithModel {
+        model: "other-model".to_string(),
+    };
+    manager.recover_from_gutter(&session.id, recovery.clone()).await.unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert_eq!(session.config.execution_model, "other-model");
+    assert!(!repo.path.join("src/attempt.txt").exists());
+    assert!(session.iterations[0].rolled_back);
+    assert!(!repo.commit_subjects().contains(&"feat: US-001 - wrong approach".to_string()));
+    assert_eq!(recoveries(&manager, &session.id).await, [recovery]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gutter_recovery_adds_guardrail() {
+    let repo = TempRepo::new();
+    let fake = FakeAgentBackend::new(repo.path_string())
+        .with_iteration(gutter_iteration())
+        .with_iteration(complete_story("US-001"));
+
+    let (manager, session) = start(&repo, &fake, in_place(), vec![story("US-001", 1)]).await;
+    wait_until_settled(&manager, &session.id).await;
+
+    let guardrail = Guardrail {
+        id: "offline-build".to_string(),
+        title: "Build without --offline-nope".to_string(),
+        trigger: "cargo build fails with an unknown flag".to_string(),
+        instruction: "Run plain `cargo build`".to_string(),
+        added_after: "gutter in US-001".to_string(),
+    };
+    manager
+        .recover_from_gutter(&session.id, GutterRecovery::AddGuardrail(guardrail))
+        .await
+        .unwrap();
+    let session = wait_until_settled(&manager, &session.id).await;
+
+    assert_eq!(session.status, SessionStatus::Complete);
+    assert!(fake.prompts()[1].contains("Build without --offline-nope"));
+}
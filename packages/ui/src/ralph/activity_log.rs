@@ -1,30 +1,68 @@
 use dioxus::prelude::*;
-use ralph::{ActivityEntry, ActivityKind, ContextHealth, Signal};
+use ralph::{ActivityEntry, ActivityEvent, ActivityKind, ContextHealth, Signal};
+
+/// Maximum number of entries kept in the on-screen log.
+const MAX_VISIBLE_ENTRIES: usize = 500;
 
 #[component]
 pub fn ActivityLog(session_id: ReadSignal<String>) -> Element {
-    let entries = use_signal(|| Vec::<ActivityEntry>::new());
+    let mut entries = use_signal(Vec::<ActivityEvent>::new);
+    let mut last_seq = use_signal(|| 0u64);
+    let mut stream_session = use_signal(String::new);
+    let mut connected = use_signal(|| false);
+    let mut reconnect_nonce = use_signal(|| 0u32);
+
+    // Stream activity over SSE. Reruns (cancelling the previous stream) when the
+    // session changes or the user asks to reconnect, resuming after the last
+    // sequence number we received.
+    let _stream = use_resource(move || async move {
+        let id = session_id();
+        let _ = reconnect_nonce();
 
-    // TODO: Implement SSE streaming when Dioxus supports it better
-    // For now, we'll poll periodically
-    use_effect(move || {
-        spawn(async move {
-            // Placeholder for SSE connection
-            // In a real implementation, we'd connect to an SSE endpoint
-            // and stream ActivityEntry events
-        });
+        if *stream_session.peek() != id {
+            stream_session.set(id.clone());
+            entries.write().clear();
+            last_seq.set(0);
+        }
+
+        let since = *last_seq.peek();
+        if let Ok(mut stream) = api::ralph::stream_activity(id, since).await {
+            connected.set(true);
+            while let Some(Ok(event)) = stream.recv().await {
+                last_seq.set(event.seq);
+                let mut entries = entries.write();
+                entries.push(event);
+                if entries.len() > MAX_VISIBLE_ENTRIES {
+                    let overflow = entries.len() - MAX_VISIBLE_ENTRIES;
+                    entries.drain(..overflow);
+                }
+            }
+        }
+        connected.set(false);
     });
 
     rsx! {
         div { class: "activity-log",
-            h3 { "Activity Log" }
+            div { class: "activity-log-header",
+                h3 { "Activity Log" }
+                if connected() {
+                    span { class: "activity-stream-status live", "● Live" }
+                } else {
+                    span { class: "activity-stream-status disconnected", "Disconnected" }
+                    button {
+                        class: "btn btn-secondary",
+                        onclick: move |_| reconnect_nonce.with_mut(|n| *n += 1),
+                        "Reconnect"
+                    }
+                }
+            }
 
             div { class: "log-entries",
                 if entries().is_empty() {
                     div { class: "log-empty", "No activity yet. Start a session to see activity." }
                 } else {
-                    for entry in entries() {
-                        ActivityRow { entry }
+                    for event in entries() {
+                        ActivityRow { key: "{event.seq}", entry: event.entry }
                     }
                 }
             }
@@ -43,19 +81,48 @@ fn ActivityRow(entry: ActivityEntry) -> Element {
             "✏️",
             format!("WRITE {} ({} lines, {} bytes)", path, lines, bytes),
         ),
-        ActivityKind::Shell { command, exit_code } => {
+        ActivityKind::Shell {
+            command, exit_code, ..
+        } => {
             let icon = if *exit_code == 0 { "✅" } else { "❌" };
             (icon, format!("SHELL {} → exit {}", command, exit_code))
         }
+        ActivityKind::Assistant(text) => {
+            let preview: String = text.chars().take(160).collect();
+            let ellipsis = if text.chars().count() > 160 { "…" } else { "" };
+            ("💬", format!("AGENT: {}{}", preview, ellipsis))
+        }
+        ActivityKind::Usage(usage) => (
+            "📏",
+            format!(
+                "USAGE: {} in / {} out / {} cached",
+                usage.input_tokens,
+                usage.output_tokens,
+                usage.cache_read_tokens + usage.cache_write_tokens
+            ),
+        ),
         ActivityKind::TokenUpdate(usage) => ("📊", format!("TOKENS: {} total", usage.total)),
         ActivityKind::Signal(signal) => match signal {
             Signal::Warn => ("⚠️", "WARN: Approaching token limit".to_string()),
             Signal::Rotate => ("🔄", "ROTATE: Starting fresh iteration".to_string()),
-            Signal::Gutter(reason) => ("🚨", format!("GUTTER: {}", reason)),
+            Signal::Gutter { rule, reason } => ("🚨", format!("GUTTER ({}): {}", rule, reason)),
             Signal::Complete => ("🎉", "COMPLETE: All stories pass!".to_string()),
             Signal::StoryComplete(id) => ("✓", format!("Story {} completed", id)),
+            Signal::StoryBlocked { story_id, attempts } => (
+                "⛔",
+                format!("BLOCKED: Story {} after {} failed attempts", story_id, attempts),
+            ),
         },
+        ActivityKind::Verification(check) => {
+            let icon = if check.passed { "✅" } else { "❌" };
+            (icon, format!("VERIFY {}: {}", check.name, check.detail))
+        }
         ActivityKind::Error(msg) => ("❌", format!("ERROR: {}", msg)),
+        ActivityKind::Recovery(recovery) => ("🛟", format!("RECOVERY: {}", recovery.describe())),
+        ActivityKind::PrdEdited(edit) => {
+            let icon = if edit.conflicts.is_empty() { "📝" } else { "⚠️" };
+            (icon, format!("PRD: {}", edit.describe()))
+        }
     };
 
     let health_icon = match entry.health {
@@ -73,12 +140,20 @@ fn ActivityRow(entry: ActivityEntry) -> Element {
     // Format timestamp
     let timestamp = format!("{:?}", entry.timestamp); // Simple format for now
 
+    let tokens = entry
+        .tokens
+        .as_ref()
+        .map(|count| format!("{} tok · {}", count.tokens, count.source.label()));
+
     rsx! {
         div { class: "activity-row {health_class}",
             span { class: "activity-health", "{health_icon}" }
             span { class: "activity-icon", "{icon}" }
             span { class: "activity-time", "{timestamp}" }
             span { class: "activity-description", "{description}" }
+            if let Some(tokens) = tokens {
+                span { class: "activity-tokens", "{tokens}" }
+            }
         }
     }
 }
@@ -0,0 +1,80 @@
+use super::story_progress::status_style;
+use dioxus::prelude::*;
+use ralph::{dependency_layers, Story, StoryStatus};
+use std::collections::HashMap;
+
+const NODE_WIDTH: f64 = 110.0;
+const NODE_HEIGHT: f64 = 34.0;
+const COLUMN_GAP: f64 = 60.0;
+const ROW_GAP: f64 = 16.0;
+
+/// The stories as a left-to-right DAG: each column holds stories whose
+/// dependencies all sit in earlier columns, so Ralph works through them
+/// roughly in column order.
+#[component]
+pub fn DependencyGraph(stories: Vec<Story>) -> Element {
+    let layers = dependency_layers(&stories);
+
+    let mut positions: HashMap<&str, (f64, f64)> = HashMap::new();
+    for (column, layer) in layers.iter().enumerate() {
+        for (row, story) in layer.iter().enumerate() {
+            let x = column as f64 * (NODE_WIDTH + COLUMN_GAP);
+            let y = row as f64 * (NODE_HEIGHT + ROW_GAP);
+            positions.insert(story.id.as_str(), (x, y));
+        }
+    }
+    let tallest = layers.iter().map(Vec::len).max().unwrap_or(0) as f64;
+    let width = layers.len() as f64 * (NODE_WIDTH + COLUMN_GAP) - COLUMN_GAP;
+    let height = tallest * (NODE_HEIGHT + ROW_GAP) - ROW_GAP;
+
+    let edges: Vec<String> = stories
+        .iter()
+        .flat_map(|story| story.depends_on.iter().map(move |dep| (dep.as_str(), story.id.as_str())))
+        .filter_map(|(from, to)| {
+            let (x1, y1) = positions.get(from)?;
+            let (x2, y2) = positions.get(to)?;
+            let (x1, y1) = (x1 + NODE_WIDTH, y1 + NODE_HEIGHT / 2.0);
+            let y2 = y2 + NODE_HEIGHT / 2.0;
+            let bend = COLUMN_GAP / 2.0;
+            Some(format!(
+                "M {x1} {y1} C {} {y1}, {} {y2}, {x2} {y2}",
+                x1 + bend,
+                x2 - bend
+            ))
+        })
+        .collect();
+
+    rsx! {
+        div { class: "dependency-graph",
+            h4 { "Dependencies" }
+            svg {
+                class: "dag",
+                width: "{width.max(0.0)}",
+                height: "{height.max(0.0)}",
+                for d in edges {
+                    path { class: "dag-edge", d: "{d}" }
+                }
+                for layer in layers.iter() {
+                    for story in layer.iter() {
+                        g { class: "dag-node {status_style(story.status).1}",
+                            rect {
+                                x: "{positions[story.id.as_str()].0}",
+                                y: "{positions[story.id.as_str()].1}",
+                                width: "{NODE_WIDTH}",
+                                height: "{NODE_HEIGHT}",
+                                rx: "6",
+                            }
+                            text {
+                                x: "{positions[story.id.as_str()].0 + NODE_WIDTH / 2.0}",
+                                y: "{positions[story.id.as_str()].1 + NODE_HEIGHT / 2.0 + 4.0}",
+                                text_anchor: "middle",
+                                if story.status == StoryStatus::Pending { "{story.id}" } else { "{status_style(story.status).0} {story.id}" }
+                            }
+                            title { "{story.title} ({story.status.label()})" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
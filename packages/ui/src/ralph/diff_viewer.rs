@@ -0,0 +1,208 @@
+use dioxus::prelude::*;
+use ralph::{Diff, FileDiff, IterationOutcome, IterationRecord};
+
+#[derive(Clone, PartialEq)]
+enum DiffTarget {
+    Story(String),
+    Iteration(u32),
+}
+
+/// Browse what the agent committed, per story or per iteration. While the
+/// session isn't running, an iteration can be rolled back from here.
+#[component]
+pub fn DiffViewer(
+    session_id: String,
+    iterations: Vec<IterationRecord>,
+    can_roll_back: bool,
+    on_rolled_back: EventHandler<()>,
+) -> Element {
+    let session_id = use_signal(|| session_id);
+    let mut target = use_signal(|| None::<DiffTarget>);
+    let mut rolling_back = use_signal(|| false);
+    let mut rollback_error = use_signal(|| None::<String>);
+
+    let roll_back = move |iteration: u32| {
+        spawn(async move {
+            rolling_back.set(true);
+            rollback_error.set(None);
+            match api::ralph::rollback_iteration(session_id(), iteration).await {
+                Ok(_) => {
+                    target.set(None);
+                    on_rolled_back.call(());
+                }
+                Err(e) => rollback_error.set(Some(e.to_string())),
+            }
+            rolling_back.set(false);
+        });
+    };
+    let selected_iteration = match target() {
+        Some(DiffTarget::Iteration(iteration)) => iterations
+            .iter()
+            .find(|r| r.iteration == iteration && !r.rolled_back)
+            .map(|r| r.iteration),
+        _ => None,
+    };
+
+    let diff = use_resource(move || async move {
+        let result: Result<Diff, _> = match target()? {
+            DiffTarget::Story(story_id) => api::ralph::get_story_diff(session_id(), story_id).await,
+            DiffTarget::Iteration(iteration) => {
+                api::ralph::get_iteration_diff(session_id(), iteration).await
+            }
+        };
+        Some(result.map_err(|e| e.to_string()))
+    });
+
+    let mut stories: Vec<String> = Vec::new();
+    for record in &iterations {
+        if !stories.contains(&record.story_id) {
+            stories.push(record.story_id.clone());
+        }
+    }
+
+    rsx! {
+        div { class: "diff-viewer",
+            h3 { "Changes" }
+
+            if iterations.is_empty() {
+                div { class: "diff-empty", "No iterations have finished yet." }
+            } else {
+                div { class: "diff-targets",
+                    div { class: "diff-target-group",
+                        span { class: "diff-target-label", "Stories" }
+                        for story_id in stories {
+                            button {
+                                class: if target() == Some(DiffTarget::Story(story_id.clone())) { "diff-target active" } else { "diff-target" },
+                                onclick: {
+                                    let story_id = story_id.clone();
+                                    move |_| target.set(Some(DiffTarget::Story(story_id.clone())))
+                                },
+                                "{story_id}"
+                            }
+                        }
+                    }
+                    div { class: "diff-target-group",
+                        span { class: "diff-target-label", "Iterations" }
+                        for record in iterations.iter().filter(|r| r.has_commits()) {
+                            button {
+                                class: format!(
+                                    "diff-target{}{}",
+                                    if target() == Some(DiffTarget::Iteration(record.iteration)) { " active" } else { "" },
+                                    if record.rolled_back { " rolled-back" } else { "" },
+                                ),
+                                title: match &record.attempt_branch {
+                                    Some(branch) => format!("{}: {}, rolled back and kept on {}", record.story_id, outcome_label(&record.outcome), branch),
+                                    None if record.rolled_back => format!("{}: {}, rolled back", record.story_id, outcome_label(&record.outcome)),
+                                    None => format!("{}: {}", record.story_id, outcome_label(&record.outcome)),
+                                },
+                                onclick: {
+                                    let iteration = record.iteration;
+                                    move |_| target.set(Some(DiffTarget::Iteration(iteration)))
+                                },
+                                "#{record.iteration + 1}"
+                            }
+                        }
+                    }
+                }
+
+                if let (Some(iteration), true) = (selected_iteration, can_roll_back) {
+                    div { class: "diff-actions",
+                        button {
+                            class: "btn btn-stop",
+                            disabled: rolling_back(),
+                            onclick: move |_| roll_back(iteration),
+                            if rolling_back() { "Rolling back..." } else { "Roll back to before this iteration" }
+                        }
+                    }
+                }
+                if let Some(e) = rollback_error() {
+                    div { class: "error-message", "{e}" }
+                }
+
+                match diff() {
+                    Some(Some(Ok(diff))) => rsx! { DiffFiles { diff } },
+                    Some(Some(Err(e))) => rsx! { div { class: "error-message", "{e}" } },
+                    Some(None) => rsx! {
+                        div { class: "diff-empty", "Pick a story or an iteration to see its changes." }
+                    },
+                    None => rsx! { div { class: "loading", "Loading diff..." } },
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn DiffFiles(diff: Diff) -> Element {
+    let from = short(&diff.from);
+    let to = short(&diff.to);
+
+    rsx! {
+        div { class: "diff-summary",
+            "{from}..{to} · {diff.files.len()} files "
+            span { class: "diff-additions", "+{diff.additions()}" }
+            " "
+            span { class: "diff-deletions", "-{diff.deletions()}" }
+        }
+        if diff.files.is_empty() {
+            div { class: "diff-empty", "No changes." }
+        }
+        for file in diff.files {
+            FileDiffView { file }
+        }
+    }
+}
+
+#[component]
+fn FileDiffView(file: FileDiff) -> Element {
+    rsx! {
+        details { class: "diff-file",
+            summary {
+                span { class: "diff-file-path", "{file.path}" }
+                if file.binary {
+                    span { class: "diff-file-stat", "binary" }
+                } else {
+                    span { class: "diff-file-stat",
+                        span { class: "diff-additions", "+{file.additions}" }
+                        " "
+                        span { class: "diff-deletions", "-{file.deletions}" }
+                    }
+                }
+            }
+            pre { class: "diff-patch",
+                for line in file.patch.lines() {
+                    div { class: line_class(line), "{line}" }
+                }
+            }
+        }
+    }
+}
+
+fn line_class(line: &str) -> &'static str {
+    if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff --git") {
+        "diff-line diff-line-meta"
+    } else if line.starts_with("@@") {
+        "diff-line diff-line-hunk"
+    } else if line.starts_with('+') {
+        "diff-line diff-line-add"
+    } else if line.starts_with('-') {
+        "diff-line diff-line-del"
+    } else {
+        "diff-line"
+    }
+}
+
+fn outcome_label(outcome: &IterationOutcome) -> &'static str {
+    match outcome {
+        IterationOutcome::StoryComplete => "story complete",
+        IterationOutcome::Unverified { .. } => "failed verification",
+        IterationOutcome::Rotated => "rotated",
+        IterationOutcome::Interrupted => "interrupted",
+        IterationOutcome::Gutter { .. } => "gutter",
+        IterationOutcome::Failed { .. } => "failed",
+    }
+}
+
+fn short(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
@@ -5,10 +5,7 @@ const GIT_PANEL_CSS: Asset = asset!("/assets/styling/git_panel.css");
 
 #[component]
 pub fn GitPanel(session_id: String, project_path: String) -> Element {
-    // session_id is currently unused but kept to match the plan and to allow future
-    // session-scoped git behaviors.
-    let _ = session_id;
-
+    let session_id = use_signal(|| session_id);
     let project_path = use_signal(|| project_path);
     let mut refresh_nonce = use_signal(|| 0_u32);
 
@@ -72,7 +69,7 @@ pub fn GitPanel(session_id: String, project_path: String) -> Element {
     };
 
     let on_create_pr = move |_| {
-        let project_path = project_path();
+        let session_id = session_id();
         let branch = selected_branch();
         let title = pr_title();
         let body = pr_body();
@@ -89,7 +86,7 @@ pub fn GitPanel(session_id: String, project_path: String) -> Element {
             creating_pr.set(true);
 
             let result: Result<String, _> =
-                api::ralph::create_pull_request(project_path, branch.clone(), title, body).await;
+                api::ralph::create_pull_request(session_id, branch.clone(), title, body).await;
 
             creating_pr.set(false);
             match result {
@@ -0,0 +1,174 @@
+use dioxus::prelude::*;
+use ralph::{Guardrail, GutterRecovery, IterationOutcome, Session};
+
+#[derive(Clone, Copy, PartialEq)]
+enum RecoveryForm {
+    Guardrail,
+    Model,
+}
+
+/// Ways out of the gutter: resume, skip the stuck story, write down a
+/// guardrail first, or roll back and retry with another model. Each one
+/// restarts the session.
+#[component]
+pub fn GutterRecoveryPanel(session: Session, reason: String, on_recovered: EventHandler<()>) -> Element {
+    let session_id = use_signal(|| session.id.clone());
+    let mut form = use_signal(|| None::<RecoveryForm>);
+    let mut working = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    let mut title = use_signal(String::new);
+    let mut trigger = use_signal(String::new);
+    let mut instruction = use_signal(String::new);
+    let mut model = use_signal(String::new);
+
+    let agent = session.config.agent.clone();
+    let current_model = session.config.execution_model.clone();
+    let agent_models = use_resource(move || {
+        let agent = agent.clone();
+        async move { api::ralph::list_agent_models(agent).await.unwrap_or_default() }
+    });
+    let stuck_story = session
+        .iterations
+        .iter()
+        .rev()
+        .find(|r| matches!(r.outcome, IterationOutcome::Gutter { .. }))
+        .map(|r| r.story_id.clone());
+
+    let run = move |action: GutterRecovery| {
+        spawn(async move {
+            working.set(true);
+            error.set(None);
+            let id = session_id();
+            let result = match action {
+                GutterRecovery::Resume => api::ralph::resume_from_gutter(id).await,
+                GutterRecovery::SkipStory => api::ralph::skip_stuck_story(id).await,
+                GutterRecovery::AddGuardrail(guardrail) => api::ralph::add_guardrail_and_resume(id, guardrail).await,
+                GutterRecovery::RetryWithModel { model } => api::ralph::retry_with_model(id, model).await,
+            };
+            match result {
+                Ok(_) => {
+                    form.set(None);
+                    on_recovered.call(());
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            working.set(false);
+        });
+    };
+
+    let added_after = reason.clone();
+    let submit_guardrail = move |evt: FormEvent| {
+        evt.prevent_default();
+        run(GutterRecovery::AddGuardrail(Guardrail {
+            id: String::new(),
+            title: title(),
+            trigger: trigger(),
+            instruction: instruction(),
+            added_after: added_after.clone(),
+        }));
+    };
+    let submit_model = move |evt: FormEvent| {
+        evt.prevent_default();
+        run(GutterRecovery::RetryWithModel { model: model() });
+    };
+
+    rsx! {
+        div { class: "gutter-recovery",
+            h3 { "🛑 Stuck in the gutter" }
+            p { class: "gutter-reason", "{reason}" }
+            if let Some(story_id) = &stuck_story {
+                p { class: "gutter-story", "Stuck on {story_id}" }
+            }
+
+            div { class: "gutter-actions",
+                button {
+                    class: "btn btn-start",
+                    disabled: working(),
+                    title: "Reset the failure counters and try the same story again",
+                    onclick: move |_| run(GutterRecovery::Resume),
+                    "Resume"
+                }
+                button {
+                    class: "btn btn-pause",
+                    disabled: working() || stuck_story.is_none(),
+                    title: "Leave this story for later and work on the rest",
+                    onclick: move |_| run(GutterRecovery::SkipStory),
+                    "Skip story"
+                }
+                button {
+                    class: if form() == Some(RecoveryForm::Guardrail) { "btn active" } else { "btn" },
+                    disabled: working(),
+                    onclick: move |_| form.set(Some(RecoveryForm::Guardrail)),
+                    "Add guardrail..."
+                }
+                button {
+                    class: if form() == Some(RecoveryForm::Model) { "btn active" } else { "btn" },
+                    disabled: working(),
+                    onclick: move |_| form.set(Some(RecoveryForm::Model)),
+                    "Retry with model..."
+                }
+            }
+
+            match form() {
+                Some(RecoveryForm::Guardrail) => rsx! {
+                    form { class: "gutter-form", onsubmit: submit_guardrail,
+                        input {
+                            r#type: "text",
+                            placeholder: "Title",
+                            value: "{title}",
+                            oninput: move |e| title.set(e.value()),
+                        }
+                        input {
+                            r#type: "text",
+                            placeholder: "Trigger: when does this apply?",
+                            value: "{trigger}",
+                            oninput: move |e| trigger.set(e.value()),
+                        }
+                        textarea {
+                            placeholder: "Instruction: what should the agent do instead?",
+                            value: "{instruction}",
+                            oninput: move |e| instruction.set(e.value()),
+                        }
+                        button {
+                            r#type: "submit",
+                            class: "btn btn-start",
+                            disabled: working() || title().trim().is_empty() || instruction().trim().is_empty(),
+                            "Add guardrail and resume"
+                        }
+                    }
+                },
+                Some(RecoveryForm::Model) => rsx! {
+                    form { class: "gutter-form", onsubmit: submit_model,
+                        select {
+                            value: "{model}",
+                            onchange: move |e| model.set(e.value()),
+                            option { value: "", disabled: true, "Pick a model" }
+                            for m in agent_models().unwrap_or_default() {
+                                option {
+                                    value: "{m.id}",
+                                    disabled: m.id == current_model,
+                                    if m.id == current_model { "{m.label} (current)" } else { "{m.label}" }
+                                }
+                            }
+                        }
+                        button {
+                            r#type: "submit",
+                            class: "btn btn-start",
+                            disabled: working() || model().is_empty(),
+                            "Roll back and retry"
+                        }
+                    }
+                },
+                None => rsx! {},
+            }
+
+            if working() {
+                div { class: "loading", "Recovering..." }
+            }
+            if let Some(e) = error() {
+                div { class: "error-message", "{e}" }
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
 mod activity_log;
 mod bottom_tab_bar;
 mod branch_selector;
+mod dependency_graph;
+mod diff_viewer;
 mod file_picker;
 mod git_panel;
 mod guardrails_panel;
+mod gutter_recovery;
 mod prd_conversation;
 mod prd_editor;
 mod session_dashboard;
@@ -14,9 +17,12 @@ mod token_meter;
 pub use activity_log::ActivityLog;
 pub use bottom_tab_bar::BottomTabBar;
 pub use branch_selector::BranchSelector;
+pub use dependency_graph::DependencyGraph;
+pub use diff_viewer::DiffViewer;
 pub use file_picker::FilePicker;
 pub use git_panel::GitPanel;
 pub use guardrails_panel::GuardrailsPanel;
+pub use gutter_recovery::GutterRecoveryPanel;
 pub use prd_conversation::PrdConversation;
 pub use prd_editor::PrdEditor;
 pub use session_dashboard::SessionDashboard;
@@ -1,5 +1,7 @@
+use super::story_progress::status_style;
+use super::DependencyGraph;
 use dioxus::prelude::*;
-use ralph::{Prd, Story};
+use ralph::{check_prd, IssueSeverity, Prd, PrdIssue, Story, StoryStatus};
 
 #[component]
 pub fn PrdEditor(
@@ -14,6 +16,8 @@ pub fn PrdEditor(
     let mut converting = use_signal(|| false);
     let mut error = use_signal(|| None::<String>);
     let mut mode = use_signal(|| EditorMode::Markdown);
+    let issues = use_memo(move || prd_preview().map(|prd| check_prd(&prd)).unwrap_or_default());
+    let has_errors = use_memo(move || issues().iter().any(|i| i.severity == IssueSeverity::Error));
 
     // If the parent provides new markdown (e.g. "Use Generated PRD"), sync it into the editor.
     use_effect(move || {
@@ -102,6 +106,7 @@ Brief description of what problem this feature solves.
 - [ ] Typecheck passes
 
 **Priority:** 1
+**Dependencies:** None
 
 ### US-002: Another Story
 ..."#,
@@ -127,6 +132,12 @@ Brief description of what problem this feature solves.
                                 p { class: "prd-branch", "Branch: {prd.branch_name}" }
                             }
 
+                            PrdChecks { issues: issues() }
+
+                            if prd.stories.iter().any(|s| !s.depends_on.is_empty()) {
+                                DependencyGraph { stories: prd.stories.clone() }
+                            }
+
                             div { class: "stories-list",
                                 h4 { "Stories ({prd.stories.len()})" }
                                 for story in prd.stories.iter() {
@@ -143,6 +154,8 @@ Brief description of what problem this feature solves.
                                 button {
                                     onclick: set_prd,
                                     class: "btn btn-primary",
+                                    disabled: has_errors(),
+                                    title: if has_errors() { "Fix the errors above first" } else { "" },
                                     "Use This PRD"
                                 }
                             }
@@ -166,16 +179,47 @@ enum EditorMode {
     Preview,
 }
 
+/// What `check_prd` found in the previewed PRD, errors first.
+#[component]
+fn PrdChecks(issues: Vec<PrdIssue>) -> Element {
+    rsx! {
+        div { class: "prd-checks",
+            if issues.is_empty() {
+                p { class: "prd-checks-clean", "✅ No problems found" }
+            } else {
+                ul {
+                    for issue in issues.iter() {
+                        if issue.severity == IssueSeverity::Error {
+                            li { class: "prd-issue issue-error", "❌ {issue.message}" }
+                        } else {
+                            li { class: "prd-issue issue-warning", "⚠️ {issue.message}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn StoryCard(story: Story) -> Element {
+    let depends_on = story.depends_on.join(", ");
+    let (status_icon, _) = status_style(story.status);
+
     rsx! {
         div { class: "story-card",
             div { class: "story-header",
                 span { class: "story-id", "{story.id}" }
                 h5 { "{story.title}" }
                 span { class: "story-priority", "Priority: {story.priority}" }
+                if story.status != StoryStatus::Pending {
+                    span { class: "story-status", "{status_icon} {story.status.label()}" }
+                }
             }
             p { class: "story-description", "{story.description}" }
+            if !depends_on.is_empty() {
+                p { class: "story-depends-on", "Depends on: {depends_on}" }
+            }
             div { class: "acceptance-criteria",
                 strong { "Acceptance Criteria:" }
                 ul {
@@ -1,4 +1,7 @@
-use super::{ActivityLog, GitPanel, GuardrailsPanel, PrdConversation, PrdEditor, StoryProgress, TokenMeter};
+use super::{
+    ActivityLog, DiffViewer, GitPanel, GuardrailsPanel, GutterRecoveryPanel, PrdConversation, PrdEditor,
+    StoryProgress, TokenMeter,
+};
 use dioxus::prelude::*;
 use ralph::{Prd, Session};
 
@@ -17,18 +20,46 @@ pub fn SessionDashboard(session_id: ReadSignal<String>) -> Element {
             match session() {
                 Some(Some(sess)) => {
                     let stories = sess.prd.as_ref().map(|p| p.stories.clone()).unwrap_or_default();
+                    let settled = !matches!(
+                        sess.status,
+                        ralph::SessionStatus::Running { .. }
+                            | ralph::SessionStatus::Queued { .. }
+                            | ralph::SessionStatus::WaitingForRotation
+                    );
                     rsx! {
                         SessionHeader { session: sess.clone() }
 
                         div { class: "ralph-main",
                             div { class: "ralph-content",
+                                if let ralph::SessionStatus::Gutter { reason } = &sess.status {
+                                    GutterRecoveryPanel {
+                                        session: sess.clone(),
+                                        reason: reason.clone(),
+                                        on_recovered: move |_| refresh_nonce.with_mut(|n| *n += 1),
+                                    }
+                                }
+
                                 if sess.prd.is_none() {
                                     PrdSetupPanel {
                                         session_id: sess.id.clone(),
                                         on_prd_set: move |_prd: Prd| refresh_nonce.with_mut(|n| *n += 1),
                                     }
                                 } else if !stories.is_empty() {
-                                    StoryProgress { stories }
+                                    StoryProgress {
+                                        session_id: sess.id.clone(),
+                                        stories,
+                                        can_change: settled,
+                                        on_changed: move |_| refresh_nonce.with_mut(|n| *n += 1),
+                                    }
+                                }
+
+                                if !sess.iterations.is_empty() {
+                                    DiffViewer {
+                                        session_id: sess.id.clone(),
+                                        iterations: sess.iterations.clone(),
+                                        can_roll_back: settled,
+                                        on_rolled_back: move |_| refresh_nonce.with_mut(|n| *n += 1),
+                                    }
                                 }
 
                                 ActivityLog { session_id }
@@ -122,6 +153,17 @@ fn SessionHeader(session: Session) -> Element {
     let session_id = session.id.clone();
     let session_id2 = session.id.clone();
     let session_id3 = session.id.clone();
+    let session_id4 = session.id.clone();
+    let has_pr = session.pr_number.is_some();
+    let pr_state = use_resource(move || {
+        let id = session_id4.clone();
+        async move {
+            if !has_pr {
+                return None;
+            }
+            api::ralph::get_pull_request_status(id).await.ok().map(|pr| pr.state)
+        }
+    });
 
     let start_session = move |_| {
         let id = session_id.clone();
@@ -162,14 +204,21 @@ fn SessionHeader(session: Session) -> Element {
                     onclick: start_session,
                     disabled: starting()
                         || session.prd.is_none()
-                        || matches!(session.status, ralph::SessionStatus::Running { .. }),
+                        || matches!(
+                            session.status,
+                            ralph::SessionStatus::Running { .. } | ralph::SessionStatus::Queued { .. }
+                        ),
                     class: "btn btn-start",
                     if starting() { "Starting..." } else { "Start" }
                 }
 
                 button {
                     onclick: pause_session,
-                    disabled: pausing() || !matches!(session.status, ralph::SessionStatus::Running { .. }),
+                    disabled: pausing()
+                        || !matches!(
+                            session.status,
+                            ralph::SessionStatus::Running { .. } | ralph::SessionStatus::Queued { .. }
+                        ),
                     class: "btn btn-pause",
                     if pausing() { "Pausing..." } else { "Pause" }
                 }
@@ -199,6 +248,27 @@ fn SessionHeader(session: Session) -> Element {
                     span { class: "stat-label", "Max Iterations" }
                     span { class: "stat-value", "{session.config.max_iterations}" }
                 }
+                if let Some(pr_url) = &session.pr_url {
+                    div { class: "stat",
+                        span { class: "stat-label", "Pull Request" }
+                        a {
+                            class: "stat-value",
+                            href: "{pr_url}",
+                            target: "_blank",
+                            rel: "noopener",
+                            "{pr_url}"
+                        }
+                        if let Some(Some(state)) = pr_state() {
+                            span { class: "stat-value", "{state:?}" }
+                        }
+                    }
+                }
+                if let Some(worktree) = &session.worktree_path {
+                    div { class: "stat",
+                        span { class: "stat-label", "Worktree" }
+                        span { class: "stat-value", "{worktree}" }
+                    }
+                }
             }
         }
     }
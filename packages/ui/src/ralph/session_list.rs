@@ -37,6 +37,7 @@ pub fn SessionList() -> Element {
 fn SessionCard(session: Session) -> Element {
     let status_class = match &session.status {
         SessionStatus::Running { .. } => "status-running",
+        SessionStatus::Queued { .. } => "status-queued",
         SessionStatus::Complete => "status-complete",
         SessionStatus::Gutter { .. } => "status-gutter",
         SessionStatus::Failed { .. } => "status-failed",
@@ -46,6 +47,7 @@ fn SessionCard(session: Session) -> Element {
 
     let status_text = match &session.status {
         SessionStatus::Idle => "Idle".to_string(),
+        SessionStatus::Queued { position } => format!("Queued (#{})", position),
         SessionStatus::Running { story_id } => format!("Running: {}", story_id),
         SessionStatus::Paused => "Paused".to_string(),
         SessionStatus::WaitingForRotation => "Waiting for Rotation".to_string(),
@@ -55,7 +57,7 @@ fn SessionCard(session: Session) -> Element {
     };
 
     let stories_info = session.prd.as_ref().map(|prd| {
-        let completed = prd.stories.iter().filter(|s| s.passes).count();
+        let completed = prd.stories.iter().filter(|s| s.passes()).count();
         let total = prd.stories.len();
         format!("Stories: {}/{}", completed, total)
     });
@@ -1,9 +1,18 @@
 use dioxus::prelude::*;
-use ralph::Story;
+use ralph::{IterationOutcome, Story, StoryAttempt, StoryStatus};
 
+/// The session's stories with their status. While the session isn't
+/// running, stories can be skipped or reopened from here.
 #[component]
-pub fn StoryProgress(stories: Vec<Story>) -> Element {
-    let completed = stories.iter().filter(|s| s.passes).count();
+pub fn StoryProgress(
+    session_id: String,
+    stories: Vec<Story>,
+    can_change: bool,
+    on_changed: EventHandler<()>,
+) -> Element {
+    let session_id = use_signal(|| session_id);
+    let mut error = use_signal(|| None::<String>);
+    let completed = stories.iter().filter(|s| s.passes()).count();
     let total = stories.len();
     let percentage = if total > 0 {
         (completed as f32 / total as f32 * 100.0) as u32
@@ -11,6 +20,21 @@ pub fn StoryProgress(stories: Vec<Story>) -> Element {
         0
     };
 
+    let change = move |(story_id, reopen): (String, bool)| {
+        spawn(async move {
+            error.set(None);
+            let result = if reopen {
+                api::ralph::reopen_story(session_id(), story_id).await
+            } else {
+                api::ralph::skip_story(session_id(), story_id).await
+            };
+            match result {
+                Ok(_) => on_changed.call(()),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
     rsx! {
         div { class: "story-progress",
             h3 { "User Stories ({completed}/{total})" }
@@ -22,34 +46,62 @@ pub fn StoryProgress(stories: Vec<Story>) -> Element {
                 }
             }
 
+            if let Some(e) = error() {
+                div { class: "error-message", "{e}" }
+            }
+
             div { class: "story-list",
                 for story in stories {
-                    StoryCard { story }
+                    StoryCard { story, can_change, on_change: change }
                 }
             }
         }
     }
 }
 
+/// Icon and card class for a story status.
+pub(super) fn status_style(status: StoryStatus) -> (&'static str, &'static str) {
+    match status {
+        StoryStatus::Pending => ("⏳", "story-pending"),
+        StoryStatus::InProgress => ("🔨", "story-in-progress"),
+        StoryStatus::Blocked => ("⛔", "story-blocked"),
+        StoryStatus::Skipped => ("⏭️", "story-skipped"),
+        StoryStatus::NeedsReview => ("🔍", "story-needs-review"),
+        StoryStatus::Done => ("✅", "story-complete"),
+    }
+}
+
 #[component]
-fn StoryCard(story: Story) -> Element {
-    let status_icon = if story.passes { "✅" } else { "⏳" };
-    let status_class = if story.passes {
-        "story-complete"
-    } else {
-        "story-pending"
-    };
+fn StoryCard(story: Story, can_change: bool, on_change: EventHandler<(String, bool)>) -> Element {
+    let (status_icon, status_class) = status_style(story.status);
+    let failed = story.failed_attempts();
+    let depends_on = story.depends_on.join(", ");
+    let workable = story.status.is_workable();
 
     rsx! {
         div { class: "story-card {status_class}",
             div { class: "story-header",
                 span { class: "story-id", "{story.id}" }
-                span { class: "story-status", "{status_icon}" }
+                span { class: "story-status", title: "{story.status.label()}", "{status_icon} {story.status.label()}" }
                 span { class: "story-priority", "Priority: {story.priority}" }
+                if can_change {
+                    button {
+                        class: "btn btn-secondary story-action",
+                        title: if workable { "Leave this story for later" } else { "Hand this story back to the loop with fresh attempts" },
+                        onclick: {
+                            let story_id = story.id.clone();
+                            move |_| on_change.call((story_id.clone(), !workable))
+                        },
+                        if workable { "Skip" } else { "Reopen" }
+                    }
+                }
             }
 
             h4 { class: "story-title", "{story.title}" }
             p { class: "story-description", "{story.description}" }
+            if !depends_on.is_empty() {
+                p { class: "story-depends-on", "Depends on: {depends_on}" }
+            }
 
             div { class: "story-criteria",
                 h5 { "Acceptance Criteria:" }
@@ -66,6 +118,40 @@ fn StoryCard(story: Story) -> Element {
                     p { "{story.notes}" }
                 }
             }
+
+            if !story.attempts.is_empty() {
+                details { class: "story-attempts",
+                    summary { "Attempts: {story.attempts.len()} ({failed} failed)" }
+                    ul {
+                        for attempt in story.attempts {
+                            AttemptRow { attempt }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn AttemptRow(attempt: StoryAttempt) -> Element {
+    let label = match &attempt.outcome {
+        IterationOutcome::StoryComplete => "complete",
+        IterationOutcome::Unverified { .. } => "failed verification",
+        IterationOutcome::Rotated => "rotated",
+        IterationOutcome::Interrupted => "interrupted",
+        IterationOutcome::Gutter { .. } => "gutter",
+        IterationOutcome::Failed { .. } => "failed",
+    };
+    let commits = attempt.commits.len();
+
+    rsx! {
+        li { class: if attempt.outcome.is_failure() { "story-attempt failed" } else { "story-attempt" },
+            span { class: "attempt-iteration", "#{attempt.iteration + 1}" }
+            " {label} · {attempt.tokens} tokens · {commits} commits"
+            if let Some(reason) = attempt.outcome.failure() {
+                div { class: "attempt-reason", "{reason}" }
+            }
         }
     }
 }
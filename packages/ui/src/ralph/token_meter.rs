@@ -22,9 +22,19 @@ pub fn TokenMeter(usage: TokenUsage, warn_threshold: u32, rotate_threshold: u32)
         "🔴"
     };
 
+    let source_class = if usage.source.is_measured() {
+        "measured"
+    } else {
+        "estimated"
+    };
+    let source_label = usage.source.label();
+
     rsx! {
         div { class: "ralph-token-meter {health_class}",
-            h3 { "{icon} Context Usage" }
+            h3 {
+                "{icon} Context Usage "
+                span { class: "meter-source {source_class}", "{source_label}" }
+            }
 
             div { class: "meter-bar",
                 div {
@@ -1,5 +1,5 @@
 use dioxus::prelude::*;
-use ralph::SessionConfig;
+use ralph::{AgentBackendKind, ForgeKind, GutterRule, SessionConfig};
 use serde::{Deserialize, Serialize};
 use ui::ralph::FilePicker;
 
@@ -28,9 +28,44 @@ pub fn RalphNewSession() -> Element {
             rotate_threshold: 80_000,
             branch_name: String::new(),
             open_pr: false,
+            verify_command: String::new(),
+            agent: AgentBackendKind::Cursor,
+            use_worktree: true,
+            keep_worktree: false,
+            base_branch: String::new(),
+            stash_changes: false,
+            forge: ForgeKind::GitHub,
+            rollback_failed: false,
+            keep_attempts: true,
+            gutter_rules: GutterRule::defaults(),
+            max_attempts_per_story: 5,
         },
     );
 
+    // Model options depend on the selected agent backend
+    let agent_models = use_resource(move || {
+        let agent = draft().agent.clone();
+        async move { api::ralph::list_agent_models(agent).await.unwrap_or_default() }
+    });
+
+    // Keep both model selections valid when the backend changes
+    use_effect(move || {
+        let Some(models) = agent_models() else {
+            return;
+        };
+        let Some(first) = models.first() else {
+            return;
+        };
+        let current = draft();
+        let known = |id: &str| models.iter().any(|m| m.id == id);
+        if !known(&current.prd_model) {
+            draft.write().prd_model = first.id.clone();
+        }
+        if !known(&current.execution_model) {
+            draft.write().execution_model = first.id.clone();
+        }
+    });
+
     let mut creating = use_signal(|| false);
     let mut error = use_signal(|| None::<String>);
     let nav = navigator();
@@ -176,6 +211,28 @@ pub fn RalphNewSession() -> Element {
                     Some(draft.branch_name.clone())
                 },
                 open_pr: draft.open_pr,
+                verify_command: if draft.verify_command.trim().is_empty() {
+                    None
+                } else {
+                    Some(draft.verify_command.trim().to_string())
+                },
+                agent: draft.agent.clone(),
+                use_worktree: draft.use_worktree,
+                keep_worktree: draft.use_worktree && draft.keep_worktree,
+                base_branch: if draft.base_branch.trim().is_empty() {
+                    None
+                } else {
+                    Some(draft.base_branch.trim().to_string())
+                },
+                stash_changes: !draft.use_worktree && draft.stash_changes,
+                forge: draft.forge.clone(),
+                rollback_failed: draft.rollback_failed,
+                keep_attempts: draft.keep_attempts,
+                gutter_rules: draft.gutter_rules.clone(),
+                max_attempts_per_story: match draft.max_attempts_per_story {
+                    0 => None,
+                    max => Some(max),
+                },
             };
 
             match api::ralph::create_session(project_path, config).await {
@@ -220,6 +277,68 @@ pub fn RalphNewSession() -> Element {
                     p { class: "form-help", "Browse and select your project's git repository directory" }
                 }
 
+                div { class: "form-group",
+                    label { "for": "agent-backend", "Agent" }
+                    select {
+                        id: "agent-backend",
+                        value: "{agent_key(&draft().agent)}",
+                        onchange: move |e| {
+                            let agent = match e.value().as_str() {
+                                "claude-code" => AgentBackendKind::ClaudeCode,
+                                "codex" => AgentBackendKind::Codex,
+                                "aider" => AgentBackendKind::Aider,
+                                "command" => AgentBackendKind::Command {
+                                    program: String::new(),
+                                    args: Vec::new(),
+                                },
+                                _ => AgentBackendKind::Cursor,
+                            };
+                            draft.write().agent = agent;
+                        },
+                        option { value: "cursor", "cursor-agent" }
+                        option { value: "claude-code", "Claude Code" }
+                        option { value: "codex", "Codex CLI" }
+                        option { value: "aider", "aider" }
+                        option { value: "command", "Custom command" }
+                    }
+                    p { class: "form-help", "Coding agent CLI that runs each iteration" }
+                }
+
+                if let AgentBackendKind::Command { program, args } = draft().agent {
+                    div { class: "form-row",
+                        div { class: "form-group",
+                            label { "for": "agent-program", "Program" }
+                            input {
+                                id: "agent-program",
+                                r#type: "text",
+                                value: "{program}",
+                                oninput: move |e| {
+                                    if let AgentBackendKind::Command { program, .. } = &mut draft.write().agent {
+                                        *program = e.value();
+                                    }
+                                },
+                                placeholder: "my-agent",
+                            }
+                        }
+
+                        div { class: "form-group",
+                            label { "for": "agent-args", "Arguments" }
+                            input {
+                                id: "agent-args",
+                                r#type: "text",
+                                value: args.join(" "),
+                                oninput: move |e| {
+                                    if let AgentBackendKind::Command { args, .. } = &mut draft.write().agent {
+                                        *args = e.value().split_whitespace().map(String::from).collect();
+                                    }
+                                },
+                                placeholder: "--model {{model}} {{prompt}}",
+                            }
+                            p { class: "form-help", "{{model}} and {{prompt}} are substituted; the prompt is appended if omitted" }
+                        }
+                    }
+                }
+
                 div { class: "form-row",
                     div { class: "form-group",
                         label { "for": "prd-model", "PRD Model" }
@@ -227,11 +346,9 @@ pub fn RalphNewSession() -> Element {
                             id: "prd-model",
                             value: "{draft().prd_model}",
                             onchange: move |e| draft.write().prd_model = e.value(),
-                            option { value: "auto", "Auto (cursor-agent picks best model)" }
-                            option { value: "opus-4.5-thinking", "Claude Opus 4.5 (thinking)" }
-                            option { value: "sonnet-4.5-thinking", "Claude Sonnet 4.5 (thinking)" }
-                            option { value: "gpt-5.2-high", "GPT 5.2 High" }
-                            option { value: "composer-1", "Composer 1" }
+                            for model in agent_models().unwrap_or_default() {
+                                option { key: "{model.id}", value: "{model.id}", "{model.label}" }
+                            }
                         }
                         p { class: "form-help", "Model used for PRD generation" }
                     }
@@ -242,11 +359,9 @@ pub fn RalphNewSession() -> Element {
                             id: "execution-model",
                             value: "{draft().execution_model}",
                             onchange: move |e| draft.write().execution_model = e.value(),
-                            option { value: "auto", "Auto (cursor-agent picks best model)" }
-                            option { value: "opus-4.5-thinking", "Claude Opus 4.5 (thinking)" }
-                            option { value: "sonnet-4.5-thinking", "Claude Sonnet 4.5 (thinking)" }
-                            option { value: "gpt-5.2-high", "GPT 5.2 High" }
-                            option { value: "composer-1", "Composer 1" }
+                            for model in agent_models().unwrap_or_default() {
+                                option { key: "{model.id}", value: "{model.id}", "{model.label}" }
+                            }
                         }
                         p { class: "form-help", "Model used for code execution" }
                     }
@@ -269,6 +384,22 @@ pub fn RalphNewSession() -> Element {
                         }
                     }
 
+                    div { class: "form-group",
+                        label { "for": "max-attempts", "Max Attempts per Story" }
+                        input {
+                            id: "max-attempts",
+                            r#type: "number",
+                            value: "{draft().max_attempts_per_story}",
+                            oninput: move |e| {
+                                if let Ok(val) = e.value().parse::<u32>() {
+                                    draft.write().max_attempts_per_story = val;
+                                }
+                            },
+                            min: "0",
+                        }
+                        p { class: "form-help", "Failed attempts before a story is blocked; 0 for no limit" }
+                    }
+
                     div { class: "form-group",
                         label { "for": "warn-threshold", "Warn Threshold (tokens)" }
                         input {
@@ -309,7 +440,31 @@ pub fn RalphNewSession() -> Element {
                         oninput: move |e| draft.write().branch_name = e.value(),
                         placeholder: "ralph/my-feature",
                     }
-                    p { class: "form-help", "Leave empty to work on current branch" }
+                    p { class: "form-help", "Leave empty to use the PRD's branch" }
+                }
+
+                div { class: "form-group",
+                    label { "for": "base-branch", "Base Branch (optional)" }
+                    input {
+                        id: "base-branch",
+                        r#type: "text",
+                        value: "{draft().base_branch}",
+                        oninput: move |e| draft.write().base_branch = e.value(),
+                        placeholder: "main",
+                    }
+                    p { class: "form-help", "A new branch starts here; leave empty to start from what is checked out" }
+                }
+
+                div { class: "form-group",
+                    label { "for": "verify-command", "Verify Command (optional)" }
+                    input {
+                        id: "verify-command",
+                        r#type: "text",
+                        value: "{draft().verify_command}",
+                        oninput: move |e| draft.write().verify_command = e.value(),
+                        placeholder: "cargo test",
+                    }
+                    p { class: "form-help", "Must exit 0 before a story is marked passing" }
                 }
 
                 div { class: "form-group",
@@ -321,6 +476,106 @@ pub fn RalphNewSession() -> Element {
                         }
                         " Open PR when complete"
                     }
+                    if draft().open_pr {
+                        select {
+                            id: "forge",
+                            value: "{forge_key(&draft().forge)}",
+                            onchange: move |e| {
+                                let forge = match e.value().as_str() {
+                                    "gitlab" => ForgeKind::GitLab,
+                                    "gitea" => ForgeKind::Gitea { url: String::new() },
+                                    _ => ForgeKind::GitHub,
+                                };
+                                draft.write().forge = forge;
+                            },
+                            option { value: "github", "GitHub (gh)" }
+                            option { value: "gitlab", "GitLab (glab)" }
+                            option { value: "gitea", "Gitea" }
+                        }
+                        if let ForgeKind::Gitea { url } = draft().forge {
+                            input {
+                                id: "gitea-url",
+                                r#type: "text",
+                                value: "{url}",
+                                oninput: move |e| {
+                                    if let ForgeKind::Gitea { url } = &mut draft.write().forge {
+                                        *url = e.value();
+                                    }
+                                },
+                                placeholder: "https://gitea.example.com",
+                            }
+                            p { class: "form-help", "Authenticates with the GITEA_TOKEN environment variable on the server" }
+                        }
+                    }
+                }
+
+                div { class: "form-group",
+                    label { class: "checkbox-label",
+                        input {
+                            r#type: "checkbox",
+                            checked: draft().use_worktree,
+                            onchange: move |e| draft.write().use_worktree = e.checked(),
+                        }
+                        " Run in a separate git worktree"
+                    }
+                    p { class: "form-help", "Leaves your checkout alone; otherwise the agent works in it directly" }
+                    if draft().use_worktree {
+                        label { class: "checkbox-label",
+                            input {
+                                r#type: "checkbox",
+                                checked: draft().keep_worktree,
+                                onchange: move |e| draft.write().keep_worktree = e.checked(),
+                            }
+                            " Keep the worktree when the session completes"
+                        }
+                    } else {
+                        label { class: "checkbox-label",
+                            input {
+                                r#type: "checkbox",
+                                checked: draft().stash_changes,
+                                onchange: move |e| draft.write().stash_changes = e.checked(),
+                            }
+                            " Stash uncommitted changes instead of refusing to start"
+                        }
+                    }
+                }
+
+                div { class: "form-group",
+                    label { class: "checkbox-label",
+                        input {
+                            r#type: "checkbox",
+                            checked: draft().rollback_failed,
+                            onchange: move |e| draft.write().rollback_failed = e.checked(),
+                        }
+                        " Roll back failed iterations"
+                    }
+                    p { class: "form-help", "When a story fails verification or the session hits the gutter, reset the branch to where the iteration started" }
+                    if draft().rollback_failed {
+                        label { class: "checkbox-label",
+                            input {
+                                r#type: "checkbox",
+                                checked: draft().keep_attempts,
+                                onchange: move |e| draft.write().keep_attempts = e.checked(),
+                            }
+                            " Keep rolled-back attempts on ralph/attempts/ branches"
+                        }
+                    }
+                }
+
+                div { class: "form-group",
+                    label { "Gutter Rules" }
+                    p { class: "form-help", "The session stops for you to step in as soon as a checked rule fires" }
+                    for suggested in GutterRule::all() {
+                        GutterRuleInput {
+                            key: "{suggested.name()}",
+                            configured: draft().gutter_rules.iter().find(|r| r.name() == suggested.name()).cloned(),
+                            suggested: suggested.clone(),
+                            on_change: {
+                                let name = suggested.name();
+                                move |rule: Option<GutterRule>| set_gutter_rule(&mut draft.write().gutter_rules, name, rule)
+                            },
+                        }
+                    }
                 }
 
                 if let Some(err) = error() {
@@ -361,4 +616,162 @@ pub struct NewSessionDraft {
     pub rotate_threshold: u32,
     pub branch_name: String,
     pub open_pr: bool,
+    #[serde(default)]
+    pub verify_command: String,
+    #[serde(default)]
+    pub agent: AgentBackendKind,
+    #[serde(default = "default_use_worktree")]
+    pub use_worktree: bool,
+    #[serde(default)]
+    pub keep_worktree: bool,
+    #[serde(default)]
+    pub base_branch: String,
+    #[serde(default)]
+    pub stash_changes: bool,
+    #[serde(default)]
+    pub forge: ForgeKind,
+    #[serde(default)]
+    pub rollback_failed: bool,
+    #[serde(default = "default_keep_attempts")]
+    pub keep_attempts: bool,
+    #[serde(default = "GutterRule::defaults")]
+    pub gutter_rules: Vec<GutterRule>,
+    /// 0 means no limit.
+    #[serde(default = "default_max_attempts_per_story")]
+    pub max_attempts_per_story: u32,
+}
+
+fn default_use_worktree() -> bool {
+    true
+}
+
+fn default_keep_attempts() -> bool {
+    true
+}
+
+fn default_max_attempts_per_story() -> u32 {
+    5
+}
+
+/// Value of the agent backend `<select>` option for `agent`.
+fn agent_key(agent: &AgentBackendKind) -> &'static str {
+    match agent {
+        AgentBackendKind::Cursor => "cursor",
+        AgentBackendKind::ClaudeCode => "claude-code",
+        AgentBackendKind::Codex => "codex",
+        AgentBackendKind::Aider => "aider",
+        AgentBackendKind::Command { .. } => "command",
+    }
+}
+
+/// Value of the forge `<select>` option for `forge`.
+fn forge_key(forge: &ForgeKind) -> &'static str {
+    match forge {
+        ForgeKind::GitHub => "github",
+        ForgeKind::GitLab => "gitlab",
+        ForgeKind::Gitea { .. } => "gitea",
+    }
+}
+
+/// Checkbox and thresholds for one gutter rule. `configured` is the rule as
+/// the draft has it, if it is enabled.
+#[component]
+fn GutterRuleInput(
+    suggested: GutterRule,
+    configured: Option<GutterRule>,
+    on_change: EventHandler<Option<GutterRule>>,
+) -> Element {
+    let enabled = configured.is_some();
+    let rule = configured.unwrap_or(suggested);
+
+    rsx! {
+        div { class: "gutter-rule",
+            label { class: "checkbox-label",
+                input {
+                    r#type: "checkbox",
+                    checked: enabled,
+                    onchange: {
+                        let rule = rule.clone();
+                        move |e: FormEvent| on_change.call(e.checked().then(|| rule.clone()))
+                    },
+                }
+                " {rule_label(&rule)}"
+            }
+            if enabled {
+                for (index, (unit, value)) in rule_thresholds(&rule).into_iter().enumerate() {
+                    label { class: "gutter-threshold",
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            value: "{value}",
+                            oninput: {
+                                let rule = rule.clone();
+                                move |e: FormEvent| {
+                                    if let Some(value) = e.value().parse::<u64>().ok().filter(|&value| value > 0) {
+                                        on_change.call(Some(with_threshold(&rule, index, value)));
+                                    }
+                                }
+                            },
+                        }
+                        " {unit}"
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn rule_label(rule: &GutterRule) -> &'static str {
+    match rule {
+        GutterRule::RepeatedCommandFailure { .. } => "The same command keeps failing",
+        GutterRule::FileThrashing { .. } => "One file is rewritten over and over",
+        GutterRule::NoCommit { .. } => "Nothing gets committed",
+        GutterRule::RepeatedError { .. } => "The same error keeps coming back",
+        GutterRule::RevertedEdits { .. } => "Edits are reverted back and forth",
+        GutterRule::NoFileChanges { .. } => "Iterations change no files",
+    }
+}
+
+/// Each threshold of `rule` with the unit shown after its input.
+fn rule_thresholds(rule: &GutterRule) -> Vec<(&'static str, u64)> {
+    match rule {
+        GutterRule::RepeatedCommandFailure { count } => vec![("failures", *count as u64)],
+        GutterRule::FileThrashing { count, window_secs } => {
+            vec![("writes", *count as u64), ("seconds", *window_secs)]
+        }
+        GutterRule::NoCommit { minutes } => vec![("minutes", *minutes)],
+        GutterRule::RepeatedError { count } => vec![("times", *count as u64)],
+        GutterRule::RevertedEdits { count } => vec![("reverts", *count as u64)],
+        GutterRule::NoFileChanges { iterations } => vec![("iterations in a row", *iterations as u64)],
+    }
+}
+
+/// `rule` with its `index`th threshold (as listed by `rule_thresholds`) set to `value`.
+fn with_threshold(rule: &GutterRule, index: usize, value: u64) -> GutterRule {
+    let count = u32::try_from(value).unwrap_or(u32::MAX);
+    match rule.clone() {
+        GutterRule::RepeatedCommandFailure { .. } => GutterRule::RepeatedCommandFailure { count },
+        GutterRule::FileThrashing { window_secs, .. } if index == 0 => GutterRule::FileThrashing { count, window_secs },
+        GutterRule::FileThrashing { count, .. } => GutterRule::FileThrashing {
+            count,
+            window_secs: value,
+        },
+        GutterRule::NoCommit { .. } => GutterRule::NoCommit { minutes: value },
+        GutterRule::RepeatedError { .. } => GutterRule::RepeatedError { count },
+        GutterRule::RevertedEdits { .. } => GutterRule::RevertedEdits { count },
+        GutterRule::NoFileChanges { .. } => GutterRule::NoFileChanges { iterations: count },
+    }
+}
+
+/// Enable, update or (with `None`) disable the rule named `name`.
+fn set_gutter_rule(rules: &mut Vec<GutterRule>, name: &str, rule: Option<GutterRule>) {
+    let position = rules.iter().position(|r| r.name() == name);
+    match (position, rule) {
+        (Some(index), Some(rule)) => rules[index] = rule,
+        (Some(index), None) => {
+            rules.remove(index);
+        }
+        (None, Some(rule)) => rules.push(rule),
+        (None, None) => {}
+    }
 }